@@ -0,0 +1,20 @@
+// Fuzz target generated by `cargo-tauri-fuzz` for the Tauri command `{{command.name}}`
+//
+// Note: you may need to implement [FromRandomBytes] for your command argument types.
+// Structs and enums can use `#[derive(tauri_fuzz::tauri::FromRandomBytes)]`.
+
+tauri_fuzz::fuzz_tauri_command! {
+    // Name of the tauri command you want to fuzz
+    command: "{{command.name}}",
+    // Pointer to the tauri command you want to fuzz
+    path: {{crate_name_underscored}}::{{command.path}},
+    // Parameters names and types to the tauri command
+    parameters: {
+{{#each command.parameters}}
+        {{this.name}}: {{this.ty}},
+{{/each}}
+    },
+    // Policy chosen for the fuzzing
+    // Here the policy will not allow any access to the filesystem
+    policy: tauri_fuzz_policies::filesystem::no_file_access(),
+}
@@ -0,0 +1,56 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+// Policy suggested by `cargo-tauri-fuzz suggest-policy` for the fuzz target `{{fuzz_target}}`
+//
+// It allows the files, hosts and binaries that the commands accessed while fuzzing and denies
+// everything else. Review it before using it: resources such as temporary files may need to be
+// replaced by globs.
+// Use it in the fuzz target with `policy: suggested_policy()?`.
+
+use tauri_fuzz_policies::engine::{FuzzPolicy, PolicyBuilder, RuleError};
+use tauri_fuzz_policies::{external_process, filesystem, network};
+
+pub fn suggested_policy() -> Result<FuzzPolicy, RuleError> {
+    let policy = PolicyBuilder::new()
+        // Resources accessed by any of the commands
+{{#each global}}
+{{#if this.allowed}}
+        .deny(
+            "{{this.name}}",
+            {{this.constructor}}(vec![
+{{#each this.allowed}}
+                {{this}}.into(),
+{{/each}}
+            ]){{#if this.fallible}}?{{/if}},
+        )
+{{else}}
+        .deny("{{this.name}}", {{this.constructor}}(vec![]){{#if this.fallible}}?{{/if}})
+{{/if}}
+{{/each}}
+{{#if commands}}
+        // Each command is restricted to the resources it accessed
+{{/if}}
+{{#each commands}}
+        .for_command(
+            {{this.name}},
+            PolicyBuilder::new()
+{{#each this.rules}}
+{{#if this.allowed}}
+                .deny(
+                    "{{this.name}}",
+                    {{this.constructor}}(vec![
+{{#each this.allowed}}
+                        {{this}}.into(),
+{{/each}}
+                    ]){{#if this.fallible}}?{{/if}},
+                ){{#if @last}},{{/if}}
+{{else}}
+                .deny("{{this.name}}", {{this.constructor}}(vec![]){{#if this.fallible}}?{{/if}}){{#if @last}},{{/if}}
+{{/if}}
+{{/each}}
+        )
+{{/each}}
+        .build();
+    Ok(policy)
+}
@@ -4,8 +4,9 @@
 use std::path::PathBuf;
 
 use clap::Parser;
+use tauri_fuzz_policies::fuzz_target::STRICT_HOOKS_ENV_VAR;
 
-use crate::util::tauri_dir;
+use crate::util::{check_fuzz_target_status, tauri_dir};
 
 #[derive(Parser, Debug)]
 pub struct Options {
@@ -16,6 +17,11 @@ pub struct Options {
     /// The target to be fuzzed. This is usually the name of the fuzz target binary
     /// defined in Cargo.toml
     fuzz_target: String,
+
+    /// Also abort if an optional function of the policy could not be hooked, functions that are
+    /// not optional always abort the fuzzer
+    #[clap(long)]
+    strict_hooks: bool,
 }
 
 pub fn command(options: Options) -> anyhow::Result<()> {
@@ -32,11 +38,13 @@ pub fn command(options: Options) -> anyhow::Result<()> {
         )
     }
 
-    std::process::Command::new("cargo")
+    let mut command = std::process::Command::new("cargo");
+    command
         .args(["run", "--bin"])
         .arg(options.fuzz_target)
-        .current_dir(fuzz_dir)
-        .status()
-        .map(|_| ())
-        .map_err(Into::into)
+        .current_dir(fuzz_dir);
+    if options.strict_hooks {
+        command.env(STRICT_HOOKS_ENV_VAR, "1");
+    }
+    check_fuzz_target_status(command.status()?)
 }
@@ -0,0 +1,565 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Generate a fuzz target for each Tauri command of the crate.
+//!
+//! The crate sources are parsed with `syn` starting from `src/lib.rs` and following the module
+//! declarations. Every public function annotated with `#[tauri::command]` gets its own
+//! `fuzz_targets/fuzz_<command>.rs` and a `[[bin]]` entry in the fuzz `Cargo.toml`.
+//! Commands with the same name in different modules are told apart by their module path such as
+//! `fuzz_<module>_<command>.rs`.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::Parser;
+use handlebars::Handlebars;
+use quote::ToTokens;
+use serde::Serialize;
+use syn::{
+    visit_mut::{self, VisitMut},
+    FnArg, GenericArgument, Item, Pat, PathArguments, Type, TypeReference, UseTree, Visibility,
+};
+
+use crate::util::{crate_name, tauri_dir};
+
+const FUZZ_TARGET_TEMPLATE: &str = include_str!("../generate/fuzz_target.rs");
+
+/// Parameter types that are injected by Tauri and not sent by the webview
+const INJECTED_TYPES: [&str; 9] = [
+    "State",
+    "AppHandle",
+    "Window",
+    "Webview",
+    "WebviewWindow",
+    "Request",
+    "Channel",
+    "CommandScope",
+    "GlobalScope",
+];
+
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Set target directory for generate
+    #[clap(short, long)]
+    directory: Option<PathBuf>,
+    /// Set the crate name to be fuzzed
+    #[clap(short, long)]
+    crate_name: Option<String>,
+}
+
+pub fn command(options: Options) -> anyhow::Result<()> {
+    let cwd = match options.directory {
+        Some(dir) => dir,
+        None => tauri_dir()?,
+    };
+
+    let fuzz_dir = cwd.join("fuzz");
+    if !fuzz_dir.exists() {
+        anyhow::bail!(
+            "Couldn't find `fuzz` directory in {}, did you forget to run `cargo-tauri-fuzz init`?",
+            cwd.display()
+        )
+    }
+
+    let crate_name = match options.crate_name {
+        Some(name) => name,
+        None => crate_name(&cwd)?,
+    };
+    generate_fuzz_targets(&cwd, &crate_name)
+}
+
+/// A Tauri command found in the crate sources
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct TauriCommand {
+    /// Name of the command
+    name: String,
+    /// Path of the command from the crate root, without the crate name
+    path: String,
+    /// Parameters of the command that are sent by the webview
+    parameters: Vec<Parameter>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Parameter {
+    name: String,
+    ty: String,
+}
+
+/// Data used to render a fuzz target
+#[derive(Serialize)]
+struct FuzzTargetData<'a> {
+    crate_name_underscored: String,
+    command: &'a TauriCommand,
+}
+
+/// Generate the fuzz targets of all the Tauri commands found in the crate at `tauri_dir`.
+/// Existing fuzz targets are not overwritten.
+pub fn generate_fuzz_targets(tauri_dir: &Path, crate_name: &str) -> anyhow::Result<()> {
+    let lib_file = tauri_dir.join("src").join("lib.rs");
+    let commands = discover_commands(&lib_file)?;
+    if commands.is_empty() {
+        log::warn!("No Tauri command found in {}", lib_file.display());
+        return Ok(());
+    }
+
+    let fuzz_dir = tauri_dir.join("fuzz");
+    let targets_dir = fuzz_dir.join("fuzz_targets");
+    fs::create_dir_all(&targets_dir)?;
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+
+    let mut bins = vec![];
+    for (command, bin_name) in commands.iter().zip(bin_names(&commands)) {
+        let target_file = targets_dir.join(format!("{bin_name}.rs"));
+        if target_file.exists() {
+            log::info!("Fuzz target {} already exists", target_file.display());
+        } else {
+            let data = FuzzTargetData {
+                crate_name_underscored: crate_name.replace('-', "_"),
+                command,
+            };
+            let rendered = handlebars.render_template(FUZZ_TARGET_TEMPLATE, &data)?;
+            fs::write(&target_file, rendered)?;
+            log::info!("Generated fuzz target {}", target_file.display());
+        }
+        bins.push(bin_name);
+    }
+
+    add_bin_entries(&fuzz_dir.join("Cargo.toml"), &bins)
+}
+
+/// Name of the fuzz target of each command.
+/// Commands sharing their name are qualified with their module path.
+fn bin_names(commands: &[TauriCommand]) -> Vec<String> {
+    commands
+        .iter()
+        .map(|command| {
+            let is_ambiguous = commands
+                .iter()
+                .filter(|other| other.name == command.name)
+                .count()
+                > 1;
+            if is_ambiguous {
+                format!("fuzz_{}", command.path.replace("::", "_"))
+            } else {
+                format!("fuzz_{}", command.name)
+            }
+        })
+        .collect()
+}
+
+/// Append the `[[bin]]` entries of the fuzz targets that are missing from `cargo_toml`
+fn add_bin_entries(cargo_toml: &Path, bins: &[String]) -> anyhow::Result<()> {
+    let mut content = fs::read_to_string(cargo_toml)
+        .with_context(|| format!("Couldn't read {}", cargo_toml.display()))?;
+    let manifest: toml::Value = toml::from_str(&content)?;
+    let existing_bins: Vec<&str> = manifest
+        .get("bin")
+        .and_then(toml::Value::as_array)
+        .map(|bins| {
+            bins.iter()
+                .filter_map(|bin| bin.get("name").and_then(toml::Value::as_str))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Each fuzz target is declared once
+    let missing_bins: BTreeSet<&String> = bins
+        .iter()
+        .filter(|bin| !existing_bins.contains(&bin.as_str()))
+        .collect();
+    if missing_bins.is_empty() {
+        return Ok(());
+    }
+
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for bin in missing_bins {
+        writeln!(
+            content,
+            "\n[[bin]]\nname = \"{bin}\"\npath = \"fuzz_targets/{bin}.rs\"\ndoc = false"
+        )?;
+    }
+    fs::write(cargo_toml, content)?;
+    Ok(())
+}
+
+/// Find the Tauri commands of the crate whose root is `lib_file`
+fn discover_commands(lib_file: &Path) -> anyhow::Result<Vec<TauriCommand>> {
+    let file = parse_file(lib_file)?;
+    let mut visitor = CommandVisitor::default();
+    let src_dir = lib_file.parent().unwrap_or(Path::new("."));
+    visitor.visit_items(&file.items, &[], src_dir)?;
+
+    let reexports = root_reexports(&file.items);
+    let mut commands = visitor.commands;
+    for command in &mut commands {
+        command.path = reexported_path(&command.path, &reexports);
+    }
+    Ok(commands)
+}
+
+fn parse_file(path: &Path) -> anyhow::Result<syn::File> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    syn::parse_file(&content).with_context(|| format!("Couldn't parse {}", path.display()))
+}
+
+#[derive(Default)]
+struct CommandVisitor {
+    commands: Vec<TauriCommand>,
+}
+
+impl CommandVisitor {
+    /// Visit the items of a module whose submodules files are located in `children_dir`
+    fn visit_items(
+        &mut self,
+        items: &[Item],
+        module_path: &[String],
+        children_dir: &Path,
+    ) -> anyhow::Result<()> {
+        for item in items {
+            match item {
+                Item::Fn(function) if is_tauri_command(&function.attrs) => {
+                    let name = function.sig.ident.to_string();
+                    if !matches!(function.vis, Visibility::Public(_)) {
+                        log::warn!(
+                            "Tauri command `{name}` is not public, it needs to be accessible \
+                             from the fuzz crate"
+                        );
+                    }
+                    let mut path = module_path.to_vec();
+                    path.push(name.clone());
+                    self.commands.push(TauriCommand {
+                        name,
+                        path: path.join("::"),
+                        parameters: command_parameters(&function.sig),
+                    });
+                }
+                Item::Mod(module) => {
+                    let name = module.ident.to_string();
+                    let mut path = module_path.to_vec();
+                    path.push(name.clone());
+                    let module_dir = children_dir.join(&name);
+                    match &module.content {
+                        Some((_, items)) => {
+                            self.visit_items(items, &path, &module_dir)?;
+                        }
+                        None => {
+                            let (file, children_dir) = module_file(children_dir, &name)?;
+                            let file = parse_file(&file)?;
+                            self.visit_items(&file.items, &path, &children_dir)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// File of the module `name` declared in a module whose submodules are in `children_dir`,
+/// together with the directory of its own submodules
+fn module_file(children_dir: &Path, name: &str) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let module_dir = children_dir.join(name);
+    let file = children_dir.join(format!("{name}.rs"));
+    if file.exists() {
+        return Ok((file, module_dir));
+    }
+    let file = module_dir.join("mod.rs");
+    if file.exists() {
+        return Ok((file, module_dir));
+    }
+    anyhow::bail!(
+        "Couldn't find the file of module `{name}` in {}",
+        children_dir.display()
+    )
+}
+
+/// Whether the attributes contain `#[tauri::command]` or `#[command]`
+fn is_tauri_command(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let segments: Vec<String> = attr
+            .path()
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        segments == ["tauri", "command"] || segments == ["command"]
+    })
+}
+
+/// Parameters of the command that are not injected by Tauri
+fn command_parameters(signature: &syn::Signature) -> Vec<Parameter> {
+    signature
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(typed) => Some(typed),
+            FnArg::Receiver(_) => None,
+        })
+        .filter(|typed| !is_injected_type(&typed.ty))
+        .filter_map(|typed| {
+            let Pat::Ident(pat) = typed.pat.as_ref() else {
+                log::warn!(
+                    "Unsupported parameter pattern in Tauri command `{}`",
+                    signature.ident
+                );
+                return None;
+            };
+            Some(Parameter {
+                name: pat.ident.to_string(),
+                ty: type_to_string(&typed.ty),
+            })
+        })
+        .collect()
+}
+
+fn is_injected_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| INJECTED_TYPES.contains(&segment.ident.to_string().as_str())),
+        Type::Reference(reference) => is_injected_type(&reference.elem),
+        _ => false,
+    }
+}
+
+/// Format a parameter type so that it can be used in a fuzz target.
+/// Lifetimes are removed since the arguments are owned by the fuzz target.
+fn type_to_string(ty: &Type) -> String {
+    let mut ty = ty.clone();
+    LifetimeEraser.visit_type_mut(&mut ty);
+    ty.to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace("& ", "&")
+        .replace(" ,", ",")
+}
+
+/// Removes the lifetimes of references and the lifetime arguments of the types it visits
+struct LifetimeEraser;
+
+impl VisitMut for LifetimeEraser {
+    fn visit_type_reference_mut(&mut self, reference: &mut TypeReference) {
+        reference.lifetime = None;
+        visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_path_arguments_mut(&mut self, arguments: &mut PathArguments) {
+        if let PathArguments::AngleBracketed(generics) = arguments {
+            generics.args = std::mem::take(&mut generics.args)
+                .into_iter()
+                .filter(|argument| !matches!(argument, GenericArgument::Lifetime(_)))
+                .collect();
+            if generics.args.is_empty() {
+                *arguments = PathArguments::None;
+            }
+        }
+        visit_mut::visit_path_arguments_mut(self, arguments);
+    }
+}
+
+/// Items re-exported at the crate root with `pub use`, indexed by their path
+fn root_reexports(items: &[Item]) -> HashMap<String, String> {
+    let mut reexports = HashMap::new();
+    for item in items {
+        if let Item::Use(item_use) = item {
+            if matches!(item_use.vis, Visibility::Public(_)) {
+                collect_use_tree(&item_use.tree, &mut vec![], &mut reexports);
+            }
+        }
+    }
+    reexports
+}
+
+fn collect_use_tree(
+    tree: &UseTree,
+    prefix: &mut Vec<String>,
+    reexports: &mut HashMap<String, String>,
+) {
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            collect_use_tree(&path.tree, prefix, reexports);
+            prefix.pop();
+        }
+        UseTree::Name(name) => {
+            let alias = name.ident.to_string();
+            reexports.insert(use_path(prefix, &alias), alias);
+        }
+        UseTree::Rename(rename) => {
+            let alias = rename.rename.to_string();
+            reexports.insert(use_path(prefix, &rename.ident.to_string()), alias);
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_use_tree(tree, prefix, reexports);
+            }
+        }
+        UseTree::Glob(_) => {}
+    }
+}
+
+fn use_path(prefix: &[String], name: &str) -> String {
+    prefix
+        .iter()
+        .map(String::as_str)
+        .filter(|segment| *segment != "crate" && *segment != "self")
+        .chain(std::iter::once(name))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Shortest path to the command using the crate root re-exports.
+/// This allows to reach commands declared in private modules.
+fn reexported_path(path: &str, reexports: &HashMap<String, String>) -> String {
+    let segments: Vec<&str> = path.split("::").collect();
+    for i in (1..=segments.len()).rev() {
+        if let Some(alias) = reexports.get(&segments[..i].join("::")) {
+            return std::iter::once(alias.as_str())
+                .chain(segments[i..].iter().copied())
+                .collect::<Vec<_>>()
+                .join("::");
+        }
+    }
+    path.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands_of(source: &str) -> Vec<TauriCommand> {
+        let file = syn::parse_file(source).unwrap();
+        let mut visitor = CommandVisitor::default();
+        visitor
+            .visit_items(&file.items, &[], Path::new("."))
+            .unwrap();
+        let reexports = root_reexports(&file.items);
+        let mut commands = visitor.commands;
+        for command in &mut commands {
+            command.path = reexported_path(&command.path, &reexports);
+        }
+        commands
+    }
+
+    #[test]
+    fn discover_commands_and_parameters() {
+        let commands = commands_of(
+            r"
+            mod private {
+                pub mod commands {
+                    #[tauri::command]
+                    pub fn read(path: &str, size: Option<u32>, state: tauri::State<'_, u8>) {}
+
+                    pub fn not_a_command(path: &str) {}
+                }
+            }
+            pub use private::commands;
+
+            pub mod other {
+                use tauri::command;
+
+                #[command]
+                pub async fn write<'a>(app: AppHandle, content: &'a [u8], name: Cow<'a, str>) {}
+            }
+            ",
+        );
+        assert_eq!(
+            commands,
+            vec![
+                TauriCommand {
+                    name: "read".into(),
+                    path: "commands::read".into(),
+                    parameters: vec![
+                        Parameter {
+                            name: "path".into(),
+                            ty: "&str".into()
+                        },
+                        Parameter {
+                            name: "size".into(),
+                            ty: "Option<u32>".into()
+                        },
+                    ],
+                },
+                TauriCommand {
+                    name: "write".into(),
+                    path: "other::write".into(),
+                    parameters: vec![
+                        Parameter {
+                            name: "content".into(),
+                            ty: "&[u8]".into()
+                        },
+                        Parameter {
+                            name: "name".into(),
+                            ty: "Cow<str>".into()
+                        },
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_lifetimes_are_removed() {
+        let ty: Type = syn::parse_str("Option<Vec<&'a str>>").unwrap();
+        assert_eq!(type_to_string(&ty), "Option<Vec<&str>>");
+        let ty: Type = syn::parse_str("HashMap<Cow<'static, str>, Wrapper<'a>>").unwrap();
+        assert_eq!(type_to_string(&ty), "HashMap<Cow<str>, Wrapper>");
+    }
+
+    #[test]
+    fn commands_with_the_same_name_are_qualified() {
+        let commands = commands_of(
+            r"
+            pub mod files {
+                #[tauri::command]
+                pub fn read(path: &str) {}
+            }
+            pub mod sockets {
+                #[tauri::command]
+                pub fn read(port: u16) {}
+
+                #[tauri::command]
+                pub fn connect(port: u16) {}
+            }
+            ",
+        );
+        assert_eq!(
+            bin_names(&commands),
+            ["fuzz_files_read", "fuzz_sockets_read", "fuzz_connect"]
+        );
+    }
+
+    #[test]
+    fn bin_entries_are_not_duplicated() {
+        let dir = std::env::temp_dir().join(format!("generate-bins-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cargo_toml = dir.join("Cargo.toml");
+        fs::write(&cargo_toml, "[package]\nname = \"app-fuzz\"\n").unwrap();
+
+        let bins = ["fuzz_read".to_string(), "fuzz_read".to_string()];
+        add_bin_entries(&cargo_toml, &bins).unwrap();
+        add_bin_entries(&cargo_toml, &bins).unwrap();
+        let content = fs::read_to_string(&cargo_toml).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(content.matches("name = \"fuzz_read\"").count(), 1);
+    }
+}
@@ -8,13 +8,13 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::Context;
 use clap::Parser;
 use handlebars::Handlebars;
 use include_dir::{include_dir, Dir};
 use serde::Serialize;
 
-use crate::util::tauri_dir;
+use crate::generate::generate_fuzz_targets;
+use crate::util::{crate_name, tauri_dir};
 
 const TEMPLATE_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/template");
 
@@ -102,26 +102,14 @@ pub fn command(options: Options) -> anyhow::Result<()> {
 
     let crate_name = match options.crate_name {
         Some(name) => name,
-        None => {
-            let cargo_toml = std::fs::read_to_string(cwd.join("Cargo.toml"))
-                .with_context(|| format!("Couldn't find `Cargo.toml` in: {}", cwd.display()))?;
-
-            let cargo_toml: toml::Value = toml::from_str(&cargo_toml)?;
-            cargo_toml
-                .get("package")
-                .and_then(|p| p.get("name"))
-                .and_then(|n| n.as_str())
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Cargo.toml doesn't have a `name` field, try using `--crate-name` flag"
-                    )
-                })?
-                .to_string()
-        }
+        None => crate_name(&cwd)?,
     };
 
     data.insert("crate_name_underscored", crate_name.replace('-', "_"));
-    data.insert("crate_name", crate_name);
+    data.insert("crate_name", crate_name.clone());
+
+    render(&mut handlebars, &data, &TEMPLATE_DIR, fuzz_dir)?;
 
-    render(&mut handlebars, &data, &TEMPLATE_DIR, fuzz_dir)
+    // Generate a fuzz target for each Tauri command of the crate
+    generate_fuzz_targets(&cwd, &crate_name)
 }
@@ -6,7 +6,11 @@ use clap::{ArgAction, Parser, Subcommand};
 use log::Level;
 
 mod fuzz;
+mod generate;
 mod init;
+mod minimize;
+mod replay;
+mod suggest_policy;
 mod util;
 
 #[derive(Parser)]
@@ -36,6 +40,10 @@ struct Cli {
 enum Commands {
     Init(init::Options),
     Fuzz(fuzz::Options),
+    Generate(generate::Options),
+    Replay(replay::Options),
+    Minimize(replay::Options),
+    SuggestPolicy(suggest_policy::Options),
 }
 
 fn main() {
@@ -50,11 +58,20 @@ fn main() {
     let res = match cli.command {
         Commands::Init(opts) => init::command(opts),
         Commands::Fuzz(opts) => fuzz::command(opts),
+        Commands::Generate(opts) => generate::command(opts),
+        Commands::Replay(opts) => replay::command(opts),
+        Commands::Minimize(opts) => minimize::command(opts),
+        Commands::SuggestPolicy(opts) => suggest_policy::command(opts),
     };
 
     if let Err(e) = res {
         log::error!("{}", e);
-        std::process::exit(1);
+        // The outcome of the fuzz target is given by its exit code
+        let code = e
+            .downcast_ref::<util::FuzzTargetFailed>()
+            .and_then(|failed| failed.code)
+            .unwrap_or(1);
+        std::process::exit(code);
     }
 }
 
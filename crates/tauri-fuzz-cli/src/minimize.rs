@@ -0,0 +1,11 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+use tauri_fuzz_policies::fuzz_target::MINIMIZE_ENV_VAR;
+
+use crate::replay::{run_fuzz_target_with_input, Options};
+
+/// Minimize the sequences that violated the policy, this takes the same options as `replay`
+pub fn command(options: Options) -> anyhow::Result<()> {
+    run_fuzz_target_with_input(options, MINIMIZE_ENV_VAR)
+}
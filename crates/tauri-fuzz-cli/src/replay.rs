@@ -0,0 +1,56 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Parser;
+use tauri_fuzz_policies::fuzz_target::REPLAY_ENV_VAR;
+
+use crate::util::{check_fuzz_target_status, tauri_dir};
+
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Set target directory for the replay or the minimization
+    #[clap(short, long)]
+    directory: Option<PathBuf>,
+
+    /// The fuzz target that found the inputs. This is usually the name of the fuzz target binary
+    /// defined in Cargo.toml
+    fuzz_target: String,
+
+    /// An input file or a directory of inputs such as `fuzz_solutions/<fuzz_target>`
+    input: PathBuf,
+}
+
+pub fn command(options: Options) -> anyhow::Result<()> {
+    run_fuzz_target_with_input(options, REPLAY_ENV_VAR)
+}
+
+/// Run the fuzz target with the path of the input in the environment variable `env_var`
+pub fn run_fuzz_target_with_input(options: Options, env_var: &str) -> anyhow::Result<()> {
+    let cwd = match options.directory {
+        Some(dir) => dir,
+        None => tauri_dir()?,
+    };
+
+    let fuzz_dir = cwd.join("fuzz");
+    if !fuzz_dir.exists() {
+        anyhow::bail!(
+            "Couldn't find `fuzz` directory in {}, did you forget to run `cargo-tauri-fuzz init`?",
+            cwd.display()
+        )
+    }
+
+    // The fuzz target is run from the fuzz directory
+    let input = std::fs::canonicalize(&options.input)
+        .with_context(|| format!("Couldn't find input {}", options.input.display()))?;
+
+    let status = std::process::Command::new("cargo")
+        .args(["run", "--bin"])
+        .arg(options.fuzz_target)
+        .env(env_var, input)
+        .current_dir(fuzz_dir)
+        .status()?;
+    check_fuzz_target_status(status)
+}
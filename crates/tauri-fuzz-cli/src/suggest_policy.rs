@@ -0,0 +1,366 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Suggest a least-privilege policy for a fuzz target.
+//!
+//! The fuzz target is run in audit mode with every known hook attached for a while. The
+//! resources accessed by each command are then collected from the behavior profiles written by
+//! the fuzzer and turned into a policy that only allows them.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context;
+use clap::Parser;
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use tauri_fuzz_policies::fuzz_target::{AUDIT_ALL_HOOKS, AUDIT_ENV_VAR};
+
+use crate::util::tauri_dir;
+
+const SUGGESTED_POLICY_TEMPLATE: &str = include_str!("../generate/suggested_policy.rs");
+
+/// Command name used in the profiles for calls made before any command was invoked
+const UNKNOWN_COMMAND: &str = "<unknown>";
+
+/// Suffix of the directories where the fuzzer writes the behavior profiles
+const PROFILES_DIR_SUFFIX: &str = "_behavior_profiles";
+
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Set target directory for suggest-policy
+    #[clap(short, long)]
+    directory: Option<PathBuf>,
+
+    /// The fuzz target to audit. This is usually the name of the fuzz target binary defined in
+    /// Cargo.toml
+    fuzz_target: String,
+
+    /// How long the fuzz target is run, in seconds
+    #[clap(short, long, default_value_t = 60)]
+    seconds: u64,
+
+    /// File where the suggested policy is written, it is printed if not specified
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn command(options: Options) -> anyhow::Result<()> {
+    let cwd = match options.directory {
+        Some(dir) => dir,
+        None => tauri_dir()?,
+    };
+
+    let fuzz_dir = cwd.join("fuzz");
+    if !fuzz_dir.exists() {
+        anyhow::bail!(
+            "Couldn't find `fuzz` directory in {}, did you forget to run `cargo-tauri-fuzz init`?",
+            cwd.display()
+        )
+    }
+
+    // Build first so that the compilation is not counted in the fuzzing duration
+    let status = Command::new("cargo")
+        .args(["build", "--bin"])
+        .arg(&options.fuzz_target)
+        .current_dir(&fuzz_dir)
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("Failed to build fuzz target {}", options.fuzz_target)
+    }
+
+    let start = SystemTime::now();
+    log::info!(
+        "Auditing {} for {} seconds",
+        options.fuzz_target,
+        options.seconds
+    );
+    let mut command = Command::new("cargo");
+    command
+        .args(["run", "--bin"])
+        .arg(&options.fuzz_target)
+        .env(AUDIT_ENV_VAR, AUDIT_ALL_HOOKS)
+        .current_dir(&fuzz_dir);
+    // The fuzzer clients are stopped with the whole process group
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut fuzzer = command.spawn()?;
+    run_for(&mut fuzzer, Duration::from_secs(options.seconds))?;
+
+    let profile = load_profiles(&fuzz_dir.join("fuzz_solutions"), start)?;
+    if profile.is_empty() {
+        anyhow::bail!(
+            "No behavior was recorded while fuzzing {}",
+            options.fuzz_target
+        )
+    }
+    let policy = render_policy(&options.fuzz_target, &profile)?;
+    match options.output {
+        Some(file) => {
+            fs::write(&file, policy)
+                .with_context(|| format!("Couldn't write {}", file.display()))?;
+            log::info!("Suggested policy written to {}", file.display());
+        }
+        None => print!("{policy}"),
+    }
+    Ok(())
+}
+
+/// Let the fuzzer run for `duration` unless it stops before
+fn run_for(fuzzer: &mut Child, duration: Duration) -> anyhow::Result<()> {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        if fuzzer.try_wait()?.is_some() {
+            log::warn!("The fuzzer stopped before the end of the audit");
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    stop(fuzzer)
+}
+
+/// Stop the fuzzer and its clients
+#[cfg(unix)]
+fn stop(fuzzer: &mut Child) -> anyhow::Result<()> {
+    Command::new("kill")
+        .args(["-INT", &format!("-{}", fuzzer.id())])
+        .status()?;
+    fuzzer.wait()?;
+    Ok(())
+}
+
+/// Stop the fuzzer and its clients
+#[cfg(windows)]
+fn stop(fuzzer: &mut Child) -> anyhow::Result<()> {
+    Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &fuzzer.id().to_string()])
+        .status()?;
+    fuzzer.wait()?;
+    Ok(())
+}
+
+/// Resources accessed by a command, as recorded in the behavior profiles of `tauri_fuzz`
+#[derive(Debug, Default, Deserialize)]
+struct CommandProfile {
+    #[serde(default)]
+    files: BTreeSet<String>,
+    #[serde(default)]
+    binaries: BTreeSet<String>,
+    #[serde(default)]
+    hosts: BTreeSet<String>,
+}
+
+impl CommandProfile {
+    fn merge(&mut self, other: &CommandProfile) {
+        self.files.extend(other.files.iter().cloned());
+        self.binaries.extend(other.binaries.iter().cloned());
+        self.hosts.extend(other.hosts.iter().cloned());
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BehaviorProfile {
+    commands: BTreeMap<String, CommandProfile>,
+}
+
+/// Merge the behavior profiles written in `solutions_dir` since `start`, by command name
+fn load_profiles(
+    solutions_dir: &Path,
+    start: SystemTime,
+) -> anyhow::Result<BTreeMap<String, CommandProfile>> {
+    let mut commands: BTreeMap<String, CommandProfile> = BTreeMap::new();
+    let dirs = fs::read_dir(solutions_dir)
+        .with_context(|| format!("Couldn't read {}", solutions_dir.display()))?;
+    for dir in dirs {
+        let dir = dir?.path();
+        let is_profiles_dir = dir
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(PROFILES_DIR_SUFFIX));
+        if !dir.is_dir() || !is_profiles_dir {
+            continue;
+        }
+        for file in fs::read_dir(&dir)? {
+            let file = file?;
+            // Profiles of previous audits are ignored
+            if file.metadata()?.modified()? < start {
+                continue;
+            }
+            let path = file.path();
+            let json = fs::read_to_string(&path)?;
+            let profile: BehaviorProfile = serde_json::from_str(&json)
+                .with_context(|| format!("Couldn't parse profile {}", path.display()))?;
+            for (command, command_profile) in profile.commands {
+                commands.entry(command).or_default().merge(&command_profile);
+            }
+        }
+    }
+    Ok(commands)
+}
+
+/// Data used to render the suggested policy
+#[derive(Serialize)]
+struct PolicyData<'a> {
+    fuzz_target: &'a str,
+    /// Rules applying to every command
+    global: Vec<ResourceRule>,
+    commands: Vec<CommandRules>,
+}
+
+/// Rules applying to a single command
+#[derive(Serialize)]
+struct CommandRules {
+    /// Rust literal of the command name
+    name: String,
+    rules: Vec<ResourceRule>,
+}
+
+/// Rule allowing only a list of resources
+#[derive(Serialize)]
+struct ResourceRule {
+    /// Name of the sub-policy
+    name: &'static str,
+    /// Constructor of `tauri_fuzz_policies` taking the allowed resources
+    constructor: &'static str,
+    /// Whether the constructor returns a `Result`
+    fallible: bool,
+    /// Rust literals of the allowed resources
+    allowed: Vec<String>,
+}
+
+/// Render a policy allowing only the resources accessed by each command.
+///
+/// Every command is restricted to its own resources. A command rule that does not match falls
+/// back on the rules of every command, so these allow the resources of all the commands.
+fn render_policy(
+    fuzz_target: &str,
+    commands: &BTreeMap<String, CommandProfile>,
+) -> anyhow::Result<String> {
+    let mut all_resources = CommandProfile::default();
+    for profile in commands.values() {
+        all_resources.merge(profile);
+    }
+    let data = PolicyData {
+        fuzz_target,
+        global: resource_rules(&all_resources),
+        commands: commands
+            .iter()
+            .filter(|(command, _)| *command != UNKNOWN_COMMAND)
+            .map(|(command, profile)| CommandRules {
+                name: format!("{command:?}"),
+                rules: resource_rules(profile),
+            })
+            .collect(),
+    };
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    Ok(handlebars.render_template(SUGGESTED_POLICY_TEMPLATE, &data)?)
+}
+
+fn resource_rules(profile: &CommandProfile) -> Vec<ResourceRule> {
+    let literals = |resources: BTreeSet<String>| {
+        resources
+            .iter()
+            .map(|resource| format!("{resource:?}"))
+            .collect()
+    };
+    let files = profile.files.iter().map(|file| escape_glob(file)).collect();
+    // Contacted addresses are recorded with their port but hosts are allowed on any port
+    let hosts = profile
+        .hosts
+        .iter()
+        .map(|host| {
+            host.parse::<SocketAddr>()
+                .map_or_else(|_| host.clone(), |address| address.ip().to_string())
+        })
+        .collect();
+    vec![
+        ResourceRule {
+            name: "filesystem",
+            constructor: "filesystem::allow_only_paths",
+            fallible: true,
+            allowed: literals(files),
+        },
+        ResourceRule {
+            name: "network",
+            constructor: "network::allow_only_hosts",
+            fallible: true,
+            allowed: literals(hosts),
+        },
+        ResourceRule {
+            name: "processes",
+            constructor: "external_process::allow_only_binaries",
+            fallible: false,
+            allowed: literals(profile.binaries.clone()),
+        },
+    ]
+}
+
+/// Escape the characters of `path` that have a meaning in globs
+fn escape_glob(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']') {
+            escaped.push('[');
+            escaped.push(c);
+            escaped.push(']');
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(files: &[&str], binaries: &[&str], hosts: &[&str]) -> CommandProfile {
+        let set = |resources: &[&str]| resources.iter().map(ToString::to_string).collect();
+        CommandProfile {
+            files: set(files),
+            binaries: set(binaries),
+            hosts: set(hosts),
+        }
+    }
+
+    #[test]
+    fn policy_allows_observed_resources() {
+        let commands = BTreeMap::from([
+            (
+                UNKNOWN_COMMAND.to_string(),
+                profile(&["/etc/ld.so.cache"], &[], &[]),
+            ),
+            (
+                "read_file".to_string(),
+                profile(
+                    &["/tmp/[data]*.txt"],
+                    &[],
+                    &["93.184.215.14:443", "example.com"],
+                ),
+            ),
+            ("open".to_string(), profile(&[], &["xdg-open"], &[])),
+        ]);
+        let policy = render_policy("fuzz_read_file", &commands).unwrap();
+        syn::parse_file(&policy).unwrap();
+
+        assert!(policy.starts_with(
+            "// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang\n\
+             // SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0\n"
+        ));
+
+        assert!(policy.contains(r#""/tmp/[[]data[]][*].txt".into(),"#));
+        assert!(policy.contains(r#""93.184.215.14".into(),"#));
+        assert!(policy.contains(r#""example.com".into(),"#));
+        assert!(policy.contains(r#""/etc/ld.so.cache".into(),"#));
+        assert!(policy.contains(r#""open","#));
+        assert!(policy.contains(r#".deny("network", network::allow_only_hosts(vec![])?)"#));
+        assert!(!policy.contains(UNKNOWN_COMMAND));
+    }
+}
@@ -4,11 +4,14 @@
 use std::{
     cmp::Ordering,
     ffi::OsStr,
+    fmt::{Display, Formatter},
     path::{Path, PathBuf},
+    process::ExitStatus,
 };
 
 use anyhow::Context;
 use ignore::WalkBuilder;
+use tauri_fuzz_policies::fuzz_target::describe_exit_code;
 
 pub const TAURI_JSON: &str = "tauri.conf.json";
 pub const TAURI_JSON5: &str = "tauri.conf.json5";
@@ -79,3 +82,53 @@ pub fn tauri_dir() -> anyhow::Result<PathBuf> {
 
     anyhow::bail!("Couldn't recognize the current folder as a Tauri project. It must contain a `{TAURI_JSON}`, `{TAURI_JSON5}` or `{TAURI_TOML}` file in any subfolder.")
 }
+
+/// Name of the crate described by the `Cargo.toml` in `dir`
+pub fn crate_name(dir: &Path) -> anyhow::Result<String> {
+    let cargo_toml = std::fs::read_to_string(dir.join("Cargo.toml"))
+        .with_context(|| format!("Couldn't find `Cargo.toml` in: {}", dir.display()))?;
+
+    let cargo_toml: toml::Value = toml::from_str(&cargo_toml)?;
+    cargo_toml
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(ToString::to_string)
+        .ok_or_else(|| {
+            anyhow::anyhow!("Cargo.toml doesn't have a `name` field, try using `--crate-name` flag")
+        })
+}
+
+/// A fuzz target process that did not exit successfully.
+/// `cargo-tauri-fuzz` exits with the same code so that the outcome reaches its caller.
+#[derive(Debug)]
+pub struct FuzzTargetFailed {
+    /// Exit code of the fuzz target, `None` if it was terminated by a signal
+    pub code: Option<i32>,
+}
+
+impl Display for FuzzTargetFailed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => match describe_exit_code(code) {
+                Some(outcome) => write!(f, "The fuzz target exited with code {code}: {outcome}"),
+                None => write!(f, "The fuzz target exited with code {code}"),
+            },
+            None => write!(f, "The fuzz target was terminated by a signal"),
+        }
+    }
+}
+
+impl std::error::Error for FuzzTargetFailed {}
+
+/// Fail with [`FuzzTargetFailed`] if the fuzz target did not exit successfully
+pub fn check_fuzz_target_status(status: ExitStatus) -> anyhow::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(FuzzTargetFailed {
+            code: status.code(),
+        }
+        .into())
+    }
+}
@@ -10,41 +10,28 @@ mod test {
     use std::process::Command;
     use sysinfo::{Process, System};
 
-    // Custom toml file for the fuzz directory
-    // - to use local dependencies instead of remote
-    // - declare the fuzz target binaries
-    const CARGO_TOML: &str = r#"
-[package]
-name = "tauri-app-fuzz"
-version = "0.0.0"
-publish = false
-edition = "2021"
-
-[workspace]
-
-[build-dependencies]
-tauri-build = "2.0.0-beta"
-
-[dependencies]
-tauri-app = { path = ".." }
-tauri = { version = "2.0.0-beta" }
-tauri-utils = "2.0.0-beta"
-
-fuzzer =  { path = "../../../../../fuzzer", features = ["tauri"] }
-policies = { path = "../../../../../policies" }
-libafl =  { path = "../../../../../LibAFL/libafl" }
-
-
-[[bin]]
-name = "greet"
-path = "fuzz_targets/_template_.rs"
-doc = false
-
-[[bin]]
-name = "greet_full"
-path = "fuzz_targets/_template_full_.rs"
-doc = false
-"#;
+    // Dependencies of the generated fuzz crate that are replaced to use the local crates instead
+    // of the remote ones
+    const LOCAL_DEPENDENCIES: [(&str, &str); 2] = [
+        (
+            "tauri-fuzz-policies =",
+            r#"tauri-fuzz-policies = { path = "../../../../../tauri-fuzz-policies" }"#,
+        ),
+        (
+            "tauri-fuzz =",
+            r#"tauri-fuzz = { path = "../../../../../tauri-fuzz", features = ["tauri"] }"#,
+        ),
+    ];
+
+    /// Run a subcommand of the cli in `dir`
+    fn run_cli(subcommand: &str, dir: &Path) {
+        let status = Command::new("cargo")
+            .args(["run", "-p", "tauri-fuzz-cli", "--", subcommand])
+            .current_dir(dir)
+            .status()
+            .unwrap_or_else(|_| panic!("failed to run [{subcommand}]"));
+        assert!(status.success(), "[{subcommand}] failed");
+    }
 
     #[ignore]
     #[test]
@@ -54,36 +41,55 @@ doc = false
         let app_dir = tests_dir.join(Path::new("tauri-app"));
         let fuzz_dir = app_dir.join(["src-tauri", "fuzz"].iter().collect::<PathBuf>());
         let binaries_dir = fuzz_dir.join(["target", "debug"].iter().collect::<PathBuf>());
-        let fuzz_targets = ["greet", "greet_full"];
+        // A fuzz target is generated for each Tauri command of the app
+        let fuzz_targets = ["fuzz_greet"];
 
         // Build the cli
         Command::new("cargo")
-            .args(["build", "-p", "fuzzer-cli"])
+            .args(["build", "-p", "tauri-fuzz-cli"])
             .output()
-            .expect("failed to build [fuzzer-cli]");
+            .expect("failed to build [tauri-fuzz-cli]");
 
         // Init fuzz directory in tauri-app
-        Command::new("cargo")
-            .args(["run", "-p", "fuzzer-cli", "init"])
-            .current_dir(app_dir.clone())
-            .output()
-            .expect("failed to init fuzz directory");
+        run_cli("init", &app_dir);
 
         // Check if command was successful
         assert!(fuzz_dir.is_dir());
+        for target in fuzz_targets.iter() {
+            let target_file = fuzz_dir.join("fuzz_targets").join(format!("{target}.rs"));
+            assert!(target_file.is_file());
+        }
 
-        // Replace the Cargo.toml file with our custom one
+        // Generating again keeps a single declaration of each fuzz target
+        run_cli("generate", &app_dir);
         let cargo_path = fuzz_dir.join(Path::new("Cargo.toml"));
+        let manifest =
+            std::fs::read_to_string(&cargo_path).expect("Unable to read fuzz/Cargo.toml");
+        for target in fuzz_targets.iter() {
+            let declaration = format!("name = \"{target}\"");
+            assert_eq!(manifest.matches(&declaration).count(), 1);
+        }
+
+        // Use the local crates in fuzz/Cargo.toml
+        let manifest: Vec<&str> = manifest
+            .lines()
+            .map(|line| {
+                LOCAL_DEPENDENCIES
+                    .iter()
+                    .find(|(dependency, _)| line.starts_with(dependency))
+                    .map_or(line, |(_, local)| local)
+            })
+            .collect();
         let mut cargo_file = File::create(cargo_path).expect("Unable to open fuzz/Cargo.toml");
         cargo_file
-            .write_all(CARGO_TOML.as_bytes())
-            .expect("Failed to declare fuzz target in fuzz/Cargo.toml");
+            .write_all(manifest.join("\n").as_bytes())
+            .expect("Failed to use local dependencies in fuzz/Cargo.toml");
 
         // Build the fuzz targets
         let mut build_commands = vec![];
         for target in fuzz_targets.iter() {
             let build_cmd = Command::new("cargo")
-                .args(["build", "-p", "tauri-app-fuzz", "--bin", target])
+                .args(["build", "--bin", target])
                 .current_dir(fuzz_dir.clone())
                 .status()
                 .unwrap_or_else(|_| panic!("Build of [{}] was terminated by signal", target));
@@ -99,20 +105,25 @@ doc = false
         let mut s = System::new();
         for target in fuzz_targets.iter() {
             let binary = binaries_dir.join(PathBuf::from(target));
-            Command::new(binary.to_str().unwrap())
+            let mut fuzzer = Command::new(binary.to_str().unwrap())
+                .current_dir(fuzz_dir.clone())
                 .spawn()
-                .expect("Failed to fuzz with full template");
+                .expect("Failed to fuzz with generated fuzz target");
 
             // Wait for the fuzzer to start
             let one_sec = std::time::Duration::from_secs(1);
             std::thread::sleep(one_sec);
 
-            // Kill the fuzzing processes, the fuzzers should not have exited before
+            // The fuzzer should not have exited before
+            assert!(fuzzer.try_wait().unwrap().is_none());
+
+            // Kill the fuzzing processes
             s.refresh_processes();
             let fuzz_processes = s.processes_by_name(target).collect::<Vec<&Process>>();
             for proc in fuzz_processes.into_iter() {
                 proc.kill();
             }
+            let _ = fuzzer.wait();
         }
 
         // Clean the fuzz directory and check that it worked
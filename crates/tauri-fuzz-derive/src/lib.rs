@@ -0,0 +1,137 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Derive macros of `tauri-fuzz`.
+//! They are re-exported by `tauri-fuzz` which should be used instead of this crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Lifetime,
+    LifetimeParam, Type,
+};
+
+/// Derive `tauri_fuzz::tauri::FromRandomBytes` for a struct or an enum.
+///
+/// Fields are constructed in order, each one consuming the bytes it needs from the input.
+/// The variant of an enum is chosen from the first bytes of the input.
+#[proc_macro_derive(FromRandomBytes)]
+pub fn derive_from_random_bytes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_random_bytes(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_random_bytes(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let lifetime = Lifetime::new("'__random_bytes", Span::call_site());
+
+    let body = match &input.data {
+        Data::Struct(data) => construct(&quote!(Self), &data.fields, &lifetime),
+        Data::Enum(data) if data.variants.is_empty() => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "FromRandomBytes can't be derived for enums without variants",
+            ))
+        }
+        Data::Enum(data) => {
+            let nb_variants = data.variants.len();
+            let arms = data.variants.iter().enumerate().map(|(i, variant)| {
+                let variant_name = &variant.ident;
+                let value = construct(&quote!(Self::#variant_name), &variant.fields, &lifetime);
+                quote!(#i => #value)
+            });
+            quote! {
+                match input.choose_index(#nb_variants) {
+                    #(#arms,)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "FromRandomBytes can't be derived for unions",
+            ))
+        }
+    };
+
+    // The input must outlive the borrowed fields of the type
+    let mut impl_generics = input.generics.clone();
+    let mut lifetime_param = LifetimeParam::new(lifetime.clone());
+    lifetime_param.bounds.extend(
+        input
+            .generics
+            .lifetimes()
+            .map(|param| param.lifetime.clone()),
+    );
+    impl_generics
+        .params
+        .insert(0, GenericParam::Lifetime(lifetime_param));
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    for ty in field_types(&input.data) {
+        where_clause.predicates.push(parse_quote! {
+            #ty: ::tauri_fuzz::tauri::FromRandomBytes<#lifetime, Output = #ty>
+        });
+        where_clause.predicates.push(parse_quote! {
+            <#ty as ::tauri_fuzz::tauri::FromRandomBytes<#lifetime>>::Error:
+                ::std::error::Error + ::std::marker::Send + ::std::marker::Sync + 'static
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::tauri_fuzz::tauri::FromRandomBytes<#lifetime> for #name #ty_generics
+        #where_clause
+        {
+            type Output = Self;
+            type Error = ::tauri_fuzz::tauri::FromRandomBytesError;
+
+            fn from_random_bytes(bytes: &#lifetime [u8]) -> ::std::result::Result<Self, Self::Error> {
+                Self::from_random_input(&mut ::tauri_fuzz::tauri::RandomBytes::new(bytes))
+            }
+
+            fn from_random_input(
+                input: &mut ::tauri_fuzz::tauri::RandomBytes<#lifetime>,
+            ) -> ::std::result::Result<Self, Self::Error> {
+                ::std::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+/// Construct `path` with its fields taken in order from the input
+fn construct(path: &TokenStream2, fields: &Fields, lifetime: &Lifetime) -> TokenStream2 {
+    let values = fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote! {
+            <#ty as ::tauri_fuzz::tauri::FromRandomBytes<#lifetime>>::from_random_input(input)
+                .map_err(::tauri_fuzz::tauri::FromRandomBytesError::new)?
+        }
+    });
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(#path { #(#names: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+        Fields::Unit => quote!(#path),
+    }
+}
+
+/// Types of all the fields of a struct or an enum
+fn field_types(data: &Data) -> Vec<&Type> {
+    match data {
+        Data::Struct(data) => data.fields.iter().map(|field| &field.ty).collect(),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter().map(|field| &field.ty))
+            .collect(),
+        Data::Union(_) => vec![],
+    }
+}
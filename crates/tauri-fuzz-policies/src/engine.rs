@@ -7,6 +7,13 @@ use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use thiserror::Error;
 
+mod arguments;
+mod combinators;
+mod policy_file;
+pub use arguments::*;
+pub use combinators::*;
+pub use policy_file::*;
+
 pub type FuzzPolicy = Vec<FunctionPolicy>;
 
 /// Policy set around a function
@@ -21,27 +28,38 @@ pub struct FunctionPolicy {
     /// Conditions that define the policy
     pub rule: Rule,
 
-    /// Number of arguments that the function takes
-    pub nb_parameters: u32,
+    /// Types of the arguments that the function takes.
+    /// The rules can't read an argument as another type, see [`Arguments::with_signature`].
+    pub parameters: Vec<ArgType>,
 
     /// Description of the function policy
     pub description: String,
 
     /// If the function monitored is a Rust function.
-    /// Rust function names are mangled during compilation
+    /// Rust function names are mangled during compilation, `name` is then the full path of the
+    /// function such as `std::process::Command::spawn` and may contain `*` wildcards.
+    /// All the instances of a generic function are monitored unless `name` gives its generic
+    /// arguments.
     pub is_rust_function: bool,
+
+    /// If the function may be missing from its library such as libc functions that are only
+    /// provided by some libc versions.
+    /// The fuzzer refuses to start when a function that is not optional can't be hooked.
+    pub is_optional: bool,
 }
 
 impl FunctionPolicy {
-    /// Check the function policy in the specified context and if the invocation should be blocked
-    ///
-    /// # Panics
-    /// This may panic if the `Rule` was not built correctly.
-    /// For example a `Rule` on entry with a context on exit.
-    /// Or trying to access registers that does not contain a parameter or a return value of the
-    /// target function
+    /// Number of arguments that the function takes
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn nb_parameters(&self) -> u32 {
+        self.parameters.len() as u32
+    }
+
+    /// Check the function policy in the specified context and if the invocation should be blocked.
+    /// Calls whose arguments can't be evaluated by the rule, such as a dangling pointer or an
+    /// argument read as another type than the declared one, are blocked and the error is reported.
     pub fn should_block(&mut self, context: &Context) -> bool {
-        let should_block = self.rule.should_block(context);
+        let should_block = self.rule.should_block(context, &self.parameters);
         match should_block {
             Ok(false) => false,
             Ok(true) => {
@@ -49,18 +67,36 @@ impl FunctionPolicy {
                 // println!("{}", self.policy_infringement_message(context));
                 true
             }
-            Err(e) => panic!(
-                "Error in policy evaluation: {:?}.\nRule: {:?}.\nContext: {:?}",
-                e, self.rule, context
-            ),
+            Err(e) => {
+                log::error!(
+                    "Error in policy evaluation, the call is blocked: {e}.\n{}",
+                    self.policy_infringement_message(context)
+                );
+                true
+            }
         }
     }
 
     pub fn policy_infringement_message(&self, context: &Context) -> String {
-        format!(
-            "Policy was broken at function [{}].\nDescription: {}\nRule: {:?}\nContext: {:?}",
-            self.name, self.description, self.rule, context,
-        )
+        match self.blocking_rule() {
+            Some(rule) => format!(
+                "Policy [{}] was broken at function [{}].\nDescription: {}\nRule: {:?}\nContext: {:?}",
+                rule.policy, self.name, rule.description, rule.rules, context,
+            ),
+            None => format!(
+                "Policy was broken at function [{}].\nDescription: {}\nRule: {:?}\nContext: {:?}",
+                self.name, self.description, self.rule, context,
+            ),
+        }
+    }
+
+    /// Rule of the sub-policy that blocked the last call when the policy combines several
+    /// policies
+    pub fn blocking_rule(&self) -> Option<&SubRule> {
+        match &self.rule {
+            Combined(rule) => rule.blocking_rule(),
+            OnEntry(_) | OnExit(_) | OnEntryAndExit(..) => None,
+        }
     }
 }
 
@@ -68,16 +104,16 @@ impl FunctionPolicy {
 // maybe using cloneable `Box` can improve performance such as in this example:
 // https://play.rust-lang.org/?version=stable&mode=debug&edition=2018&gist=6ca48c4cff92370c907ecf4c548ee33c
 
-/// `ConditionOnParameters` is a closure on the registers containing the parameters of the function.
-/// Registers can contain a value but also a pointer depending on the type of the parameters.
-pub type ConditionOnParameters = Arc<dyn Fn(&[usize]) -> Result<bool, RuleError>>;
+/// `ConditionOnParameters` is a closure on the parameters of the function.
+/// Parameters are decoded according to their type with [`Arguments`].
+pub type ConditionOnParameters = Arc<dyn Fn(&Arguments) -> Result<bool, RuleError>>;
 /// `ConditionOnReturnValue` is a closure on the value contained in the return value register
 /// This can contain a value but also a pointer depending on the type of the return value.
 pub type ConditionOnReturnValue = Arc<dyn Fn(usize) -> Result<bool, RuleError>>;
 /// `ConditionOnParameters` but with an additional argument that can be used as storage to pass
 /// information for later usage
 pub type ConditionOnParametersWithStorage =
-    Arc<dyn Fn(&[usize], &mut Option<usize>) -> Result<bool, RuleError>>;
+    Arc<dyn Fn(&Arguments, &mut Option<usize>) -> Result<bool, RuleError>>;
 /// `ConditionOnReturnValue` but with an additional argument that can be used for the analysis
 pub type ConditionOnReturnValueWithStorage =
     Arc<dyn Fn(usize, &mut Option<usize>) -> Result<bool, RuleError>>;
@@ -101,6 +137,9 @@ pub enum Rule {
         ConditionOnReturnValueWithStorage,
         Option<usize>,
     ),
+
+    /// Rules of several policies on the same function, see [`PolicyBuilder`]
+    Combined(CombinedRule),
 }
 
 impl Debug for Rule {
@@ -109,6 +148,10 @@ impl Debug for Rule {
             Rule::OnEntry(_) => write!(f, "Rule::OnEntry"),
             Rule::OnExit(_) => write!(f, "Rule::OnExit"),
             Rule::OnEntryAndExit(_, _, _) => write!(f, "Rule::OnEntryAndExit"),
+            Rule::Combined(rule) => {
+                let policies: Vec<&str> = rule.rules.iter().map(|r| r.policy.as_str()).collect();
+                write!(f, "Rule::Combined({policies:?})")
+            }
         }
     }
 }
@@ -136,6 +179,14 @@ pub enum RuleError {
     /// A value was expected to be found in storage when evaluating a rule at a function exit
     #[error("No value stored for rule evaluation at exit: `{0}`")]
     ExpectedStorageEmpty(String),
+
+    /// A parameter points to memory that can't be read
+    #[error("Memory at `{0:#x}` is not readable")]
+    UnreadableMemory(usize),
+
+    /// The policy can't be created from the parameters it was given
+    #[error("Invalid policy: `{0}`")]
+    InvalidPolicy(String),
 }
 
 /// Context when evaluating if a function is respecting the specified policy.
@@ -170,11 +221,18 @@ impl Rule {
     /// Evaluate if rule is true given a context
     /// If it returns true it means that the rule has been verified and does not respect the policy
     /// We don't evaluate "entry" rules when given a "leave" context and vice-versa.
-    fn should_block(&mut self, context: &Context) -> Result<bool, RuleError> {
+    /// The arguments are decoded according to `signature`.
+    fn should_block(
+        &mut self,
+        context: &Context,
+        signature: &[ArgType],
+    ) -> Result<bool, RuleError> {
         match self {
             // Evaluate the function on entry
             OnEntry(block_condition) => match context {
-                EntryContext(parameters) => block_condition(parameters),
+                EntryContext(parameters) => {
+                    block_condition(&Arguments::with_signature(parameters, signature))
+                }
                 LeaveContext(_) => Ok(false),
             },
 
@@ -185,9 +243,14 @@ impl Rule {
             },
             // We block the function on entry
             OnEntryAndExit(entry_condition, exit_condition, stored_value) => match context {
-                EntryContext(parameters) => entry_condition(parameters, stored_value),
+                EntryContext(parameters) => entry_condition(
+                    &Arguments::with_signature(parameters, signature),
+                    stored_value,
+                ),
                 LeaveContext(return_value) => exit_condition(*return_value, stored_value),
             },
+            // Decide between the rules of the combined policies
+            Combined(rule) => rule.should_block(context, signature),
         }
     }
 }
@@ -201,16 +264,16 @@ mod tests {
         // Block on function entry
         let mut rule = Rule::OnEntry(crate::block_on_entry());
         let context = EntryContext(vec![]);
-        assert!(rule.should_block(&context).unwrap());
+        assert!(rule.should_block(&context, &[]).unwrap());
 
         // Check parameters
-        let mut rule1 = Rule::OnEntry(Arc::new(|params| Ok(params[0] == 1)));
-        let mut rule2 = Rule::OnEntry(Arc::new(|params| Ok(params[1] % 2 == 0)));
-        let mut rule3 = Rule::OnEntry(Arc::new(|params| Ok(params[2] == 4)));
+        let mut rule1 = Rule::OnEntry(Arc::new(|args| Ok(args.raw(0)? == 1)));
+        let mut rule2 = Rule::OnEntry(Arc::new(|args| Ok(args.raw(1)? % 2 == 0)));
+        let mut rule3 = Rule::OnEntry(Arc::new(|args| Ok(args.raw(2)? == 4)));
         let context = EntryContext(vec![1, 2, 3]);
-        assert!(rule1.should_block(&context).unwrap());
-        assert!(rule2.should_block(&context).unwrap());
-        assert!(!rule3.should_block(&context).unwrap());
+        assert!(rule1.should_block(&context, &[]).unwrap());
+        assert!(rule2.should_block(&context, &[]).unwrap());
+        assert!(!rule3.should_block(&context, &[]).unwrap());
     }
 
     #[test]
@@ -218,8 +281,8 @@ mod tests {
         let mut rule = Rule::OnExit(Arc::new(|v| Ok(v == 1)));
         let mut rule2 = Rule::OnExit(Arc::new(|v| Ok(v == 4)));
         let context = LeaveContext(1);
-        assert!(rule.should_block(&context).unwrap());
-        assert!(!rule2.should_block(&context).unwrap());
+        assert!(rule.should_block(&context, &[]).unwrap());
+        assert!(!rule2.should_block(&context, &[]).unwrap());
     }
 
     #[test]
@@ -227,23 +290,32 @@ mod tests {
         // Entry context with leave rule
         let context = EntryContext(vec![]);
         let mut rule = Rule::OnExit(Arc::new(|_| Ok(false)));
-        assert!(!rule.should_block(&context).unwrap());
+        assert!(!rule.should_block(&context, &[]).unwrap());
 
         // Leave context with entry rule
         let context = LeaveContext(0);
         let mut rule = Rule::OnEntry(crate::block_on_entry());
-        assert!(!rule.should_block(&context).unwrap());
+        assert!(!rule.should_block(&context, &[]).unwrap());
 
         // Entry context with not enough parameters
-        let mut rule = Rule::OnEntry(Arc::new(|params| {
-            if params.len() < 3 {
-                Err(RuleError::NumberOfParametersDontMatch(params.len()))
-            } else {
-                Ok(params[2] == 4)
-            }
-        }));
+        let mut rule = Rule::OnEntry(Arc::new(|args| Ok(args.raw(2)? == 4)));
         let context = EntryContext(vec![1, 2]);
-        assert!(rule.should_block(&context).is_err());
+        assert!(rule.should_block(&context, &[]).is_err());
+    }
+
+    #[test]
+    fn evaluation_errors_block() {
+        let mut policy = FunctionPolicy {
+            name: "open".into(),
+            lib: "libc.".into(),
+            rule: Rule::OnEntry(Arc::new(|args| Ok(args.raw(2)? == 4))),
+            parameters: vec![ArgType::Unknown; 2],
+            description: "Argument 2 is 4".into(),
+            is_rust_function: false,
+            is_optional: false,
+        };
+        assert!(policy.should_block(&EntryContext(vec![1, 2])));
+        assert!(!policy.should_block(&EntryContext(vec![1, 2, 3])));
     }
 
     #[test]
@@ -263,7 +335,7 @@ mod tests {
             }),
             None,
         );
-        assert!(!rule.should_block(&entry_context).unwrap());
-        assert!(!rule.should_block(&leave_context).unwrap());
+        assert!(!rule.should_block(&entry_context, &[]).unwrap());
+        assert!(!rule.should_block(&leave_context, &[]).unwrap());
     }
 }
@@ -0,0 +1,465 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Typed access to the arguments of a monitored function.
+//!
+//! Rules only get the raw registers of the monitored call. Arguments that are pointers may be
+//! null, dangling or point to garbage when the fuzzed code is buggy, so they are never
+//! dereferenced directly. Memory is copied through the kernel which reports unreadable addresses
+//! instead of crashing the fuzzer, and decoding failures are returned as [`RuleError`].
+
+use std::ffi::CString;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::engine::RuleError;
+
+/// Maximum length of the strings read from the arguments
+const MAX_STRING_LENGTH: usize = 64 * 1024;
+
+/// Size of the chunks read when looking for the end of a string.
+/// Chunks are aligned on their size so that they never cross a page boundary, this allows to read
+/// a string at the end of a mapping.
+const STRING_CHUNK_SIZE: usize = 256;
+
+/// Type of an argument of a monitored function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgType {
+    /// Integer such as a size, a mode or flags
+    Int,
+    /// File descriptor
+    Fd,
+    /// Pointer to a NUL-terminated string
+    Str,
+    /// Pointer to a NUL-terminated path
+    Path,
+    /// Pointer to a `sockaddr`
+    SockAddr,
+    /// Pointer to a value that is not decoded
+    Ptr,
+    /// Argument of unknown type, only its raw value is used
+    Unknown,
+}
+
+impl ArgType {
+    /// If the argument points to a string
+    pub fn is_string(self) -> bool {
+        matches!(self, ArgType::Str | ArgType::Path)
+    }
+}
+
+/// Signature made of `nb_parameters` arguments of unknown type
+pub fn untyped_signature(nb_parameters: u32) -> Vec<ArgType> {
+    vec![ArgType::Unknown; nb_parameters as usize]
+}
+
+/// Arguments of a call to a monitored function
+#[derive(Debug, Clone, Copy)]
+pub struct Arguments<'a> {
+    registers: &'a [usize],
+    /// Declared types of the arguments, empty when they are not known
+    signature: &'a [ArgType],
+}
+
+impl<'a> Arguments<'a> {
+    /// Arguments of unknown types, the accessors decode them as requested
+    pub fn new(registers: &'a [usize]) -> Self {
+        Arguments {
+            registers,
+            signature: &[],
+        }
+    }
+
+    /// Arguments of a function with the given signature.
+    /// The accessors fail on arguments declared with another type, arguments of type
+    /// [`ArgType::Unknown`] can be decoded as any type.
+    pub fn with_signature(registers: &'a [usize], signature: &'a [ArgType]) -> Self {
+        Arguments {
+            registers,
+            signature,
+        }
+    }
+
+    /// Raw values of the registers containing the arguments
+    pub fn registers(&self) -> &'a [usize] {
+        self.registers
+    }
+
+    /// Raw value of the argument at `index`
+    ///
+    /// # Errors
+    /// Fails if the function has less than `index + 1` arguments
+    pub fn raw(&self, index: usize) -> Result<usize, RuleError> {
+        self.registers
+            .get(index)
+            .copied()
+            .ok_or(RuleError::NumberOfParametersDontMatch(self.registers.len()))
+    }
+
+    /// Raw value of the argument at `index` if it is declared with one of the `expected` types
+    fn typed(&self, index: usize, expected: &[ArgType]) -> Result<usize, RuleError> {
+        match self.signature.get(index) {
+            Some(declared) if *declared != ArgType::Unknown && !expected.contains(declared) => {
+                Err(RuleError::ParametersTypeConversionError(format!(
+                    "argument {index} is declared as {declared:?} and can't be read as {expected:?}"
+                )))
+            }
+            _ => self.raw(index),
+        }
+    }
+
+    /// Argument at `index` as a C `int` such as flags or a file descriptor
+    ///
+    /// # Errors
+    /// Fails if the function has less than `index + 1` arguments or the argument is not declared
+    /// as an integer or a file descriptor
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    pub fn int(&self, index: usize) -> Result<i32, RuleError> {
+        // A C `int` is passed in the lower bits of the register
+        self.typed(index, &[ArgType::Int, ArgType::Fd])
+            .map(|value| value as i32)
+    }
+
+    /// NUL-terminated string pointed by the argument at `index`, `None` if it is a null pointer
+    ///
+    /// # Errors
+    /// Fails if the string is not readable or is too long, or the argument is not declared as a
+    /// string or a path
+    pub fn c_str(&self, index: usize) -> Result<Option<CString>, RuleError> {
+        let address = self.typed(index, &[ArgType::Str, ArgType::Path])?;
+        read_optional_c_string(address)
+    }
+
+    /// String pointed by the argument at `index`, `None` if it is a null pointer.
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+    ///
+    /// # Errors
+    /// Fails if the string is not readable or is too long, or the argument is not declared as a
+    /// string or a path
+    pub fn string(&self, index: usize) -> Result<Option<String>, RuleError> {
+        Ok(self
+            .c_str(index)?
+            .map(|string| string.to_string_lossy().into_owned()))
+    }
+
+    /// Path pointed by the argument at `index`, `None` if it is a null pointer
+    ///
+    /// # Errors
+    /// Fails if the path is not readable or is too long, or the argument is not declared as a
+    /// path
+    pub fn path(&self, index: usize) -> Result<Option<PathBuf>, RuleError> {
+        let address = self.typed(index, &[ArgType::Path])?;
+        let Some(path) = read_optional_c_string(address)? else {
+            return Ok(None);
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+            Ok(Some(std::ffi::OsString::from_vec(path.into_bytes()).into()))
+        }
+        #[cfg(not(unix))]
+        Ok(Some(path.to_string_lossy().into_owned().into()))
+    }
+
+    /// Address pointed by the `sockaddr` argument at `index`.
+    /// Returns `None` for a null pointer or an address that is not an IP address.
+    ///
+    /// # Errors
+    /// Fails if the address is not readable or the argument is not declared as a `sockaddr`
+    #[cfg(not(target_env = "msvc"))]
+    pub fn sockaddr(&self, index: usize) -> Result<Option<std::net::SocketAddr>, RuleError> {
+        use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+        let address = self.typed(index, &[ArgType::SockAddr])?;
+        if address == 0 {
+            return Ok(None);
+        }
+        // The family is read first since the address may be smaller than a `sockaddr_in6`
+        let sockaddr: libc::sockaddr = read_value(address)?;
+        match i32::from(sockaddr.sa_family) {
+            libc::AF_INET => {
+                let addr: libc::sockaddr_in = read_value(address)?;
+                let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                let port = u16::from_be(addr.sin_port);
+                Ok(Some(SocketAddr::V4(SocketAddrV4::new(ip, port))))
+            }
+            libc::AF_INET6 => {
+                let addr: libc::sockaddr_in6 = read_value(address)?;
+                let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                let port = u16::from_be(addr.sin6_port);
+                Ok(Some(SocketAddr::V6(SocketAddrV6::new(
+                    ip,
+                    port,
+                    addr.sin6_flowinfo,
+                    addr.sin6_scope_id,
+                ))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reference to the value of type `T` pointed by the argument at `index`, `None` if it is a
+    /// null pointer.
+    /// The memory of the value is checked to be readable and aligned.
+    ///
+    /// # Errors
+    /// Fails if the pointer is misaligned, the memory is not readable or the argument is not
+    /// declared as a pointer
+    ///
+    /// # Safety
+    /// The argument must point to a valid `T` if it points to readable memory. Only the memory
+    /// of the value itself is checked, not the memory it refers to.
+    pub unsafe fn reference<T>(&self, index: usize) -> Result<Option<&'a T>, RuleError> {
+        let address = self.typed(index, &[ArgType::Ptr])?;
+        if address == 0 {
+            return Ok(None);
+        }
+        if address % std::mem::align_of::<T>() != 0 {
+            return Err(RuleError::ParametersTypeConversionError(format!(
+                "pointer {address:#x} is not aligned for `{}`",
+                std::any::type_name::<T>()
+            )));
+        }
+        let mut probe = vec![0u8; std::mem::size_of::<T>()];
+        read_memory(address, &mut probe)?;
+        Ok(Some(&*(address as *const T)))
+    }
+}
+
+/// Copy a value of plain data type `T` from `address`
+pub(crate) fn read_value<T: Copy>(address: usize) -> Result<T, RuleError> {
+    let mut value = std::mem::MaybeUninit::<T>::uninit();
+    // The buffer covers exactly the memory of `value`
+    let buffer = unsafe {
+        std::slice::from_raw_parts_mut(value.as_mut_ptr().cast::<u8>(), std::mem::size_of::<T>())
+    };
+    read_memory(address, buffer)?;
+    // `T` is plain data and all its bytes were copied
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Read the NUL-terminated string at `address`, `None` if it is a null pointer
+fn read_optional_c_string(address: usize) -> Result<Option<CString>, RuleError> {
+    if address == 0 {
+        return Ok(None);
+    }
+    read_c_string(address).map(Some)
+}
+
+/// Read the NUL-terminated string at `address`
+fn read_c_string(address: usize) -> Result<CString, RuleError> {
+    let mut bytes = Vec::new();
+    let mut chunk = [0u8; STRING_CHUNK_SIZE];
+    while bytes.len() < MAX_STRING_LENGTH {
+        let current = address
+            .checked_add(bytes.len())
+            .ok_or(RuleError::UnreadableMemory(address))?;
+        // Don't read past the end of the page, the next one may not be mapped
+        let to_chunk_end = STRING_CHUNK_SIZE - current % STRING_CHUNK_SIZE;
+        let chunk = &mut chunk[..to_chunk_end];
+        read_memory(current, chunk)?;
+        match chunk.iter().position(|&b| b == 0) {
+            Some(end) => {
+                bytes.extend_from_slice(&chunk[..end]);
+                // There is no NUL byte in `bytes`
+                return Ok(CString::new(bytes).expect("NUL byte in C string"));
+            }
+            None => bytes.extend_from_slice(chunk),
+        }
+    }
+    Err(RuleError::ParametersTypeConversionError(format!(
+        "string at {address:#x} is longer than {MAX_STRING_LENGTH} bytes"
+    )))
+}
+
+#[cfg(not(target_env = "msvc"))]
+mod memory {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    use crate::engine::RuleError;
+
+    /// Maximum number of bytes copied through the pipe at once, smaller than the capacity of a
+    /// pipe on all platforms
+    const PIPE_CHUNK_SIZE: usize = 4096;
+
+    /// Pipe used to copy memory through the kernel, `write` fails with `EFAULT` instead of
+    /// crashing when the memory is not readable
+    struct ProbePipe {
+        read: OwnedFd,
+        write: OwnedFd,
+    }
+
+    impl ProbePipe {
+        fn new() -> std::io::Result<Self> {
+            let mut fds = [0; 2];
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // The file descriptors were just created and are owned by nothing else
+            let pipe = unsafe {
+                ProbePipe {
+                    read: OwnedFd::from_raw_fd(fds[0]),
+                    write: OwnedFd::from_raw_fd(fds[1]),
+                }
+            };
+            for fd in [&pipe.read, &pipe.write] {
+                unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+            }
+            Ok(pipe)
+        }
+    }
+
+    thread_local! {
+        static PROBE_PIPE: std::io::Result<ProbePipe> = ProbePipe::new();
+    }
+
+    /// Copy the memory at `address` into `buffer`
+    pub(crate) fn read_memory(address: usize, buffer: &mut [u8]) -> Result<(), RuleError> {
+        PROBE_PIPE.with(|pipe| {
+            let pipe = pipe.as_ref().map_err(|e| {
+                RuleError::EvaluationError(format!("Failed to create the probe pipe: {e}"))
+            })?;
+            let mut copied = 0;
+            while copied < buffer.len() {
+                let source = address
+                    .checked_add(copied)
+                    .ok_or(RuleError::UnreadableMemory(address))?;
+                let size = (buffer.len() - copied).min(PIPE_CHUNK_SIZE);
+                let written = unsafe {
+                    libc::write(pipe.write.as_raw_fd(), source as *const libc::c_void, size)
+                };
+                let Ok(written) = usize::try_from(written) else {
+                    return Err(RuleError::UnreadableMemory(source));
+                };
+                if written == 0 {
+                    return Err(RuleError::UnreadableMemory(source));
+                }
+                // The pipe contains exactly the `written` bytes that were just written
+                let read = unsafe {
+                    libc::read(
+                        pipe.read.as_raw_fd(),
+                        buffer[copied..].as_mut_ptr().cast(),
+                        written,
+                    )
+                };
+                if usize::try_from(read).ok() != Some(written) {
+                    return Err(RuleError::EvaluationError(
+                        "Failed to read back the probed memory".into(),
+                    ));
+                }
+                copied += written;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(target_env = "msvc")]
+mod memory {
+    use windows_sys::Win32::System::Diagnostics::Debug::ReadProcessMemory;
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    use crate::engine::RuleError;
+
+    /// Copy the memory at `address` into `buffer`.
+    /// `ReadProcessMemory` fails instead of crashing when the memory is not readable.
+    pub(crate) fn read_memory(address: usize, buffer: &mut [u8]) -> Result<(), RuleError> {
+        let mut read = 0;
+        let success = unsafe {
+            ReadProcessMemory(
+                GetCurrentProcess(),
+                address as *const std::ffi::c_void,
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+                &mut read,
+            )
+        };
+        if success == 0 || read != buffer.len() {
+            return Err(RuleError::UnreadableMemory(address));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) use memory::read_memory;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_strings() {
+        let path = CString::new("/etc/passwd").unwrap();
+        let invalid_utf8 = b"caf\xe9\0";
+        let registers = [path.as_ptr() as usize, 0, invalid_utf8.as_ptr() as usize];
+        let args = Arguments::new(&registers);
+
+        assert_eq!(args.c_str(0).unwrap(), Some(path.clone()));
+        assert_eq!(args.path(0).unwrap(), Some(PathBuf::from("/etc/passwd")));
+        assert_eq!(args.string(1).unwrap(), None);
+        assert_eq!(args.string(2).unwrap().unwrap(), "caf\u{fffd}");
+        assert!(matches!(
+            args.string(3),
+            Err(RuleError::NumberOfParametersDontMatch(3))
+        ));
+    }
+
+    #[test]
+    fn declared_types_are_checked() {
+        let path = CString::new("/etc/passwd").unwrap();
+        let registers = [path.as_ptr() as usize, 3, path.as_ptr() as usize];
+        let signature = [ArgType::Path, ArgType::Fd, ArgType::Unknown];
+        let args = Arguments::with_signature(&registers, &signature);
+
+        assert!(args.path(0).unwrap().is_some());
+        assert!(args.string(0).unwrap().is_some());
+        assert_eq!(args.int(1).unwrap(), 3);
+        assert!(matches!(
+            args.int(0),
+            Err(RuleError::ParametersTypeConversionError(_))
+        ));
+        assert!(args.path(1).is_err());
+        assert!(unsafe { args.reference::<u64>(1) }.is_err());
+        // Arguments of unknown type are decoded as requested
+        assert!(args.path(2).unwrap().is_some());
+        assert_eq!(args.raw(0).unwrap(), path.as_ptr() as usize);
+    }
+
+    #[test]
+    fn unreadable_memory_is_an_error() {
+        let registers = [8, usize::MAX - 4];
+        let args = Arguments::new(&registers);
+        assert!(matches!(args.c_str(0), Err(RuleError::UnreadableMemory(_))));
+        assert!(args.c_str(1).is_err());
+        assert!(unsafe { args.reference::<u64>(0) }.is_err());
+
+        let value = 42u64;
+        let registers = [std::ptr::addr_of!(value) as usize];
+        let args = Arguments::new(&registers);
+        assert_eq!(unsafe { args.reference::<u64>(0) }.unwrap(), Some(&42));
+    }
+
+    #[cfg(not(target_env = "msvc"))]
+    #[test]
+    fn decode_sockaddr() {
+        use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+        let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+        addr.sin_family = libc::sa_family_t::try_from(libc::AF_INET).unwrap();
+        addr.sin_port = 443u16.to_be();
+        addr.sin_addr.s_addr = u32::from(Ipv4Addr::new(93, 184, 215, 14)).to_be();
+        let registers = [0, std::ptr::addr_of!(addr) as usize];
+        let args = Arguments::new(&registers);
+
+        assert_eq!(args.sockaddr(0).unwrap(), None);
+        assert_eq!(
+            args.sockaddr(1).unwrap(),
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::new(93, 184, 215, 14),
+                443
+            )))
+        );
+    }
+}
@@ -0,0 +1,686 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Combine several policies into a single `FuzzPolicy`.
+//!
+//! Concatenating policies monitors the same function several times and can't express
+//! exceptions. A [`PolicyBuilder`] merges the rules of all the sub-policies on a function into a
+//! single [`FunctionPolicy`] with a [`CombinedRule`], which decides between the allow and deny
+//! rules and remembers which sub-policy blocked the call.
+//!
+//! ```rust,ignore
+//! // Read-only access to the filesystem except in /tmp/app and no `curl`
+//! let policy = PolicyBuilder::new()
+//!     .deny("read-only", filesystem::read_only_access())
+//!     .allow("app temp dir", filesystem::deny_paths(vec!["/tmp/app/**".into()])?)
+//!     .deny("no curl", external_process::block_on_entry(vec!["curl".into()]))
+//!     // The `save` command can write anywhere
+//!     .for_command("save", PolicyBuilder::new().allow("save", filesystem::no_file_write()))
+//!     .build();
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+use std::thread::ThreadId;
+
+use crate::engine::{ArgType, Context, FunctionPolicy, FuzzPolicy, Rule, RuleError};
+
+/// Command being fuzzed, used to apply the rules that are specific to a command.
+/// It points to one of the `COMMAND_NAMES` or is null outside of a command. The hooks read it
+/// from any thread and must not wait on a lock.
+static CURRENT_COMMAND: AtomicPtr<String> = AtomicPtr::new(std::ptr::null_mut());
+/// Names of the commands invoked by the fuzzer, they are leaked so that they live as long as
+/// `CURRENT_COMMAND` can point to them
+static COMMAND_NAMES: Mutex<Vec<&'static String>> = Mutex::new(Vec::new());
+
+/// Scope of a command invoked by the fuzzer.
+/// The rules specific to the command apply to the calls made until the scope is dropped or
+/// another command enters its scope. Async commands keep running after they were invoked, the
+/// scope has to be kept until they respond.
+///
+/// ```rust,ignore
+/// let _scope = CommandScope::enter("read_file");
+/// invoke_command(webview, request);
+/// ```
+pub struct CommandScope(&'static String);
+
+impl CommandScope {
+    /// Enter the scope of `command`, its name as given to [`PolicyBuilder::for_command`]
+    pub fn enter(command: &str) -> Self {
+        let name = intern_command(command);
+        CURRENT_COMMAND.store(std::ptr::from_ref(name).cast_mut(), Ordering::SeqCst);
+        CommandScope(name)
+    }
+}
+
+impl Drop for CommandScope {
+    fn drop(&mut self) {
+        // The scope of a command entered since then is left as is
+        let _ = CURRENT_COMMAND.compare_exchange(
+            std::ptr::from_ref(self.0).cast_mut(),
+            std::ptr::null_mut(),
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+    }
+}
+
+/// Command that is being invoked by the fuzzer
+pub fn current_command() -> Option<&'static str> {
+    let name = CURRENT_COMMAND.load(Ordering::SeqCst);
+    // SAFETY: the pointer is null or points to a leaked name of `COMMAND_NAMES`
+    unsafe { name.as_ref() }.map(String::as_str)
+}
+
+/// Leaked name of `command`, it's only leaked the first time the command is invoked
+fn intern_command(command: &str) -> &'static String {
+    let mut names = COMMAND_NAMES.lock().unwrap();
+    if let Some(name) = names.iter().find(|name| name.as_str() == command) {
+        return name;
+    }
+    let name: &'static String = Box::leak(Box::new(command.to_string()));
+    names.push(name);
+    name
+}
+
+/// Effect of a sub-policy when its rule matches a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// The call is allowed even if a deny rule matches it
+    Allow,
+    /// The call is blocked unless an allow rule matches it
+    Deny,
+}
+
+/// Which effect wins when both allow and deny rules match a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precedence {
+    /// Allow rules are exceptions to the deny rules
+    #[default]
+    AllowOverridesDeny,
+    /// Deny rules can't be overridden
+    DenyOverridesAllow,
+}
+
+/// Rule of a sub-policy in a [`CombinedRule`]
+#[derive(Debug, Clone)]
+pub struct SubRule {
+    /// Name of the sub-policy the rule comes from
+    pub policy: String,
+
+    /// Description of the function policy the rule comes from
+    pub description: String,
+
+    /// Effect of the rule when it matches
+    pub effect: Effect,
+
+    /// Command the rule is restricted to, the rule applies to every command when `None`
+    pub command: Option<String>,
+
+    /// The rule matches when all these rules block the call
+    pub rules: Vec<Rule>,
+}
+
+impl SubRule {
+    /// Each rule is evaluated in its own context: the rules that matched on entry, stored in
+    /// `matched_on_entry`, still match when leaving the function. This intersects rules checked
+    /// on entry with rules checked on exit.
+    /// A deny rule that matched on entry does not match again when leaving the function.
+    fn matches(
+        &mut self,
+        context: &Context,
+        signature: &[ArgType],
+        matched_on_entry: &mut Vec<bool>,
+    ) -> Result<bool, RuleError> {
+        // Every rule is evaluated since rules checked on entry and exit store values on entry
+        let mut matched = Vec::with_capacity(self.rules.len());
+        let mut error = None;
+        for rule in &mut self.rules {
+            match rule.should_block(context, signature) {
+                Ok(rule_matches) => matched.push(rule_matches),
+                Err(e) => {
+                    matched.push(false);
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        let sub_rule_matches = match context {
+            Context::EntryContext(_) => {
+                let all = matched.iter().all(|&m| m);
+                *matched_on_entry = matched;
+                all
+            }
+            Context::LeaveContext(_) => {
+                let on_entry = std::mem::take(matched_on_entry);
+                let on_entry = |index: usize| on_entry.get(index).copied().unwrap_or(false);
+                let all = matched
+                    .iter()
+                    .enumerate()
+                    .all(|(index, &m)| m || on_entry(index));
+                all && (self.effect == Effect::Allow || matched.iter().any(|&m| m))
+            }
+        };
+        match error {
+            Some(e) => Err(e),
+            None => Ok(sub_rule_matches),
+        }
+    }
+}
+
+/// Rules of several sub-policies on the same function
+#[derive(Debug, Clone)]
+pub struct CombinedRule {
+    /// Rules of the sub-policies
+    pub rules: Vec<SubRule>,
+
+    /// Effect that wins when both allow and deny rules match
+    pub precedence: Precedence,
+
+    /// Index of the rule that blocked the last call
+    blocking: Option<usize>,
+
+    /// Rules of each sub-rule that matched on the entry of the function, for each thread
+    /// that is in the function
+    matched_on_entry: HashMap<ThreadId, Vec<Vec<bool>>>,
+}
+
+impl CombinedRule {
+    pub fn new(rules: Vec<SubRule>, precedence: Precedence) -> Self {
+        CombinedRule {
+            rules,
+            precedence,
+            blocking: None,
+            matched_on_entry: HashMap::new(),
+        }
+    }
+
+    /// Rule of the sub-policy that blocked the last call
+    pub fn blocking_rule(&self) -> Option<&SubRule> {
+        self.blocking.map(|index| &self.rules[index])
+    }
+
+    /// Decide if the call is blocked.
+    /// An allow rule that can't be evaluated does not allow the call. The error of a deny rule
+    /// that can't be evaluated is returned if no other rule decides on the call, the call is then
+    /// blocked.
+    pub(crate) fn should_block(
+        &mut self,
+        context: &Context,
+        signature: &[ArgType],
+    ) -> Result<bool, RuleError> {
+        let command = if self.rules.iter().any(|rule| rule.command.is_some()) {
+            current_command()
+        } else {
+            None
+        };
+        let thread = std::thread::current().id();
+        let mut matched_on_entry = match context {
+            Context::EntryContext(_) => None,
+            Context::LeaveContext(_) => self.matched_on_entry.remove(&thread),
+        }
+        .unwrap_or_else(|| vec![vec![]; self.rules.len()]);
+        let mut matching = vec![];
+        let mut deny_error = None;
+        for (index, rule) in self.rules.iter_mut().enumerate() {
+            let applies = rule.command.is_none() || rule.command.as_deref() == command;
+            if !applies {
+                continue;
+            }
+            match rule.matches(context, signature, &mut matched_on_entry[index]) {
+                Ok(true) => matching.push(index),
+                Ok(false) => {}
+                Err(e) if rule.effect == Effect::Allow => {
+                    log::error!(
+                        "Allow rule of policy [{}] skipped, it can't be evaluated: {e}",
+                        rule.policy
+                    );
+                }
+                Err(e) => {
+                    deny_error.get_or_insert(e);
+                }
+            }
+        }
+        if let Context::EntryContext(_) = context {
+            self.matched_on_entry.insert(thread, matched_on_entry);
+        }
+
+        // Rules specific to the command override the rules of all commands
+        let command_specific = matching
+            .iter()
+            .any(|&index| self.rules[index].command.is_some());
+        matching.retain(|&index| self.rules[index].command.is_some() == command_specific);
+
+        let deny = matching
+            .iter()
+            .copied()
+            .find(|&index| self.rules[index].effect == Effect::Deny);
+        let allowed = matching
+            .iter()
+            .any(|&index| self.rules[index].effect == Effect::Allow);
+        let allowed = allowed && self.precedence == Precedence::AllowOverridesDeny;
+        self.blocking = if allowed { None } else { deny };
+        match deny_error {
+            Some(e) if !allowed && self.blocking.is_none() => Err(e),
+            _ => Ok(self.blocking.is_some()),
+        }
+    }
+}
+
+/// Function monitored by a sub-policy with the rules on it
+#[derive(Debug, Clone)]
+struct FunctionRules {
+    function: FunctionPolicy,
+    rules: Vec<SubRule>,
+}
+
+/// Builder combining several policies into one `FuzzPolicy`.
+/// The rules of all the sub-policies on the same function are merged so that each function is
+/// monitored by a single listener.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyBuilder {
+    functions: Vec<FunctionRules>,
+    precedence: Precedence,
+}
+
+impl PolicyBuilder {
+    pub fn new() -> Self {
+        PolicyBuilder::default()
+    }
+
+    /// Block the calls blocked by `policy`.
+    /// Adding several deny policies blocks the union of their calls.
+    #[must_use]
+    pub fn deny(self, name: impl Into<String>, policy: FuzzPolicy) -> Self {
+        self.add(&name.into(), Effect::Deny, policy)
+    }
+
+    /// Allow the calls blocked by `policy` even if a deny policy blocks them.
+    /// This is used to write exceptions, for example `deny_paths` lists the allowed paths.
+    #[must_use]
+    pub fn allow(self, name: impl Into<String>, policy: FuzzPolicy) -> Self {
+        self.add(&name.into(), Effect::Allow, policy)
+    }
+
+    /// Block the calls that are blocked by all the `policies`.
+    /// Functions that are not monitored by all of them are never blocked.
+    #[must_use]
+    pub fn deny_intersection(mut self, name: impl Into<String>, policies: &[FuzzPolicy]) -> Self {
+        let name = name.into();
+        let Some((first, others)) = policies.split_first() else {
+            return self;
+        };
+        for (index, function) in first.iter().enumerate() {
+            // The rules on a function are merged when it's first seen
+            if first[..index].iter().any(|f| same_function(f, function)) {
+                continue;
+            }
+            // Union of the rules of each policy on the function
+            let rules: Option<Vec<Rule>> = std::iter::once(first)
+                .chain(others)
+                .map(|policy| union_on_function(&name, policy, function))
+                .collect();
+            let Some(rules) = rules else {
+                continue;
+            };
+            let rule = SubRule {
+                policy: name.clone(),
+                description: format!("Intersection of the policies on {}", function.name),
+                effect: Effect::Deny,
+                command: None,
+                rules,
+            };
+            self.push(function, rule);
+        }
+        self
+    }
+
+    /// The rules of `overrides` only apply while `command` is invoked.
+    /// When they match a call they take precedence over the rules that apply to every command.
+    #[must_use]
+    pub fn for_command(mut self, command: impl Into<String>, overrides: PolicyBuilder) -> Self {
+        let command = command.into();
+        for function in overrides.functions {
+            for mut rule in function.rules {
+                rule.command = Some(command.clone());
+                self.push(&function.function, rule);
+            }
+        }
+        self
+    }
+
+    /// Set which effect wins when both allow and deny rules match a call
+    #[must_use]
+    pub fn precedence(mut self, precedence: Precedence) -> Self {
+        self.precedence = precedence;
+        self
+    }
+
+    /// Policy with a single function policy per monitored function
+    pub fn build(self) -> FuzzPolicy {
+        let precedence = self.precedence;
+        self.functions
+            .into_iter()
+            .map(|FunctionRules { function, rules }| {
+                let description = rules
+                    .iter()
+                    .map(|rule| format!("[{}] {}", rule.policy, rule.description))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                FunctionPolicy {
+                    rule: Rule::Combined(CombinedRule::new(rules, precedence)),
+                    description,
+                    ..function
+                }
+            })
+            .collect()
+    }
+
+    fn add(mut self, name: &str, effect: Effect, policy: FuzzPolicy) -> Self {
+        for function in policy {
+            let rule = SubRule {
+                policy: name.to_string(),
+                description: function.description.clone(),
+                effect,
+                command: None,
+                rules: vec![function.rule.clone()],
+            };
+            self.push(&function, rule);
+        }
+        self
+    }
+
+    /// Index of the function monitored by `function` if it's already monitored
+    fn find(&self, function: &FunctionPolicy) -> Option<usize> {
+        self.functions
+            .iter()
+            .position(|f| same_function(&f.function, function))
+    }
+
+    fn push(&mut self, function: &FunctionPolicy, rule: SubRule) {
+        match self.find(function) {
+            Some(index) => {
+                let monitored = &mut self.functions[index];
+                // All the arguments used by the rules must be available
+                if function.parameters.len() > monitored.function.parameters.len() {
+                    monitored
+                        .function
+                        .parameters
+                        .clone_from(&function.parameters);
+                }
+                // The function is only optional if every policy monitoring it allows it
+                monitored.function.is_optional &= function.is_optional;
+                monitored.rules.push(rule);
+            }
+            None => self.functions.push(FunctionRules {
+                function: function.clone(),
+                rules: vec![rule],
+            }),
+        }
+    }
+}
+
+/// Whether both function policies monitor the same function
+fn same_function(lhs: &FunctionPolicy, rhs: &FunctionPolicy) -> bool {
+    lhs.name == rhs.name && lhs.lib == rhs.lib && lhs.is_rust_function == rhs.is_rust_function
+}
+
+/// Rule blocking the calls to `function` that `policy` blocks, `None` if it does not monitor it
+fn union_on_function(name: &str, policy: &FuzzPolicy, function: &FunctionPolicy) -> Option<Rule> {
+    let mut rules: Vec<SubRule> = policy
+        .iter()
+        .filter(|f| same_function(f, function))
+        .map(|f| SubRule {
+            policy: name.to_string(),
+            description: f.description.clone(),
+            effect: Effect::Deny,
+            command: None,
+            rules: vec![f.rule.clone()],
+        })
+        .collect();
+    match rules.len() {
+        0 => None,
+        1 => rules.pop().and_then(|rule| rule.rules.into_iter().next()),
+        _ => Some(Rule::Combined(CombinedRule::new(
+            rules,
+            Precedence::default(),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ArgType;
+    use crate::engine::Context::{EntryContext, LeaveContext};
+    use std::sync::Arc;
+
+    /// Policy on `function` blocking calls whose parameter `arg` is `value`
+    fn block_arg(function: &str, arg: usize, value: usize) -> FuzzPolicy {
+        vec![FunctionPolicy {
+            name: function.into(),
+            lib: "libc.".into(),
+            rule: Rule::OnEntry(Arc::new(move |args| Ok(args.raw(arg)? == value))),
+            parameters: vec![ArgType::Int; 2],
+            description: format!("{function} argument {arg} is not {value}"),
+            is_rust_function: false,
+            is_optional: false,
+        }]
+    }
+
+    /// Policy on `function` blocking calls that return -1
+    fn fails(function: &str) -> FuzzPolicy {
+        vec![FunctionPolicy {
+            name: function.into(),
+            lib: "libc.".into(),
+            rule: Rule::OnExit(Arc::new(|ret| Ok(ret == usize::MAX))),
+            parameters: vec![ArgType::Int; 2],
+            description: format!("{function} does not fail"),
+            is_rust_function: false,
+            is_optional: false,
+        }]
+    }
+
+    /// Policy on `function` that can't be evaluated since it reads an integer as a path
+    fn failing_rule(function: &str) -> FuzzPolicy {
+        vec![FunctionPolicy {
+            name: function.into(),
+            lib: "libc.".into(),
+            rule: Rule::OnEntry(Arc::new(|args| Ok(args.path(0)?.is_some()))),
+            parameters: vec![ArgType::Int; 2],
+            description: format!("{function} argument 0 is a path"),
+            is_rust_function: false,
+            is_optional: false,
+        }]
+    }
+
+    /// Policy evaluated from another thread as the hooks do
+    struct AnyThread<T>(T);
+
+    // SAFETY: the rules of the tests only capture integers
+    unsafe impl<T> Send for AnyThread<T> {}
+
+    impl<T> AnyThread<T> {
+        fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    fn blocking_policy(policy: &mut FunctionPolicy, params: Vec<usize>) -> Option<String> {
+        policy
+            .should_block(&EntryContext(params))
+            .then(|| policy.blocking_rule().unwrap().policy.clone())
+    }
+
+    #[test]
+    fn union_and_exceptions() {
+        let mut policy = PolicyBuilder::new()
+            .deny("first", block_arg("open", 0, 1))
+            .deny("second", block_arg("open", 1, 3))
+            .allow("exception", block_arg("open", 1, 2))
+            .deny("other", block_arg("unlink", 0, 1))
+            .build();
+        // Hooks on the same function are merged
+        assert_eq!(policy.len(), 2);
+        let open = &mut policy[0];
+        assert_eq!(blocking_policy(open, vec![1, 0]).as_deref(), Some("first"));
+        assert_eq!(blocking_policy(open, vec![0, 3]).as_deref(), Some("second"));
+        assert_eq!(blocking_policy(open, vec![1, 2]), None);
+        assert_eq!(blocking_policy(open, vec![0, 0]), None);
+
+        let mut policy = PolicyBuilder::new()
+            .deny("first", block_arg("open", 0, 1))
+            .allow("exception", block_arg("open", 1, 2))
+            .precedence(Precedence::DenyOverridesAllow)
+            .build();
+        let open = &mut policy[0];
+        assert_eq!(blocking_policy(open, vec![1, 2]).as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn intersection() {
+        let mut policy = PolicyBuilder::new()
+            .deny_intersection(
+                "both",
+                &[
+                    block_arg("open", 0, 1),
+                    [block_arg("open", 1, 2), block_arg("unlink", 0, 1)].concat(),
+                ],
+            )
+            .build();
+        // `unlink` is not monitored by the first policy
+        assert_eq!(policy.len(), 1);
+        let open = &mut policy[0];
+        assert_eq!(blocking_policy(open, vec![1, 2]).as_deref(), Some("both"));
+        assert_eq!(blocking_policy(open, vec![1, 3]), None);
+        assert_eq!(blocking_policy(open, vec![0, 2]), None);
+    }
+
+    #[test]
+    fn intersection_on_entry_and_exit() {
+        let mut policy = PolicyBuilder::new()
+            .deny_intersection("both", &[block_arg("open", 0, 1), fails("open")])
+            .build();
+        let open = &mut policy[0];
+        assert_eq!(blocking_policy(open, vec![1, 0]), None);
+        assert!(open.should_block(&LeaveContext(usize::MAX)));
+        assert_eq!(open.blocking_rule().unwrap().policy, "both");
+
+        assert_eq!(blocking_policy(open, vec![1, 0]), None);
+        assert!(!open.should_block(&LeaveContext(0)));
+        assert_eq!(blocking_policy(open, vec![0, 0]), None);
+        assert!(!open.should_block(&LeaveContext(usize::MAX)));
+    }
+
+    #[test]
+    fn entry_results_are_kept_per_thread() {
+        let mut policy = PolicyBuilder::new()
+            .deny("no failure", fails("open"))
+            .allow("expected", block_arg("open", 0, 1))
+            .build();
+        let open = &mut policy[0];
+        // Allowed on entry in this thread
+        assert_eq!(blocking_policy(open, vec![1, 0]), None);
+        // Not allowed on entry in another thread
+        let in_thread = AnyThread(&mut *open);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let open = in_thread.into_inner();
+                assert_eq!(blocking_policy(open, vec![0, 0]), None);
+                assert!(open.should_block(&LeaveContext(usize::MAX)));
+            });
+        });
+        assert!(!open.should_block(&LeaveContext(usize::MAX)));
+    }
+
+    #[test]
+    fn command_overrides() {
+        let mut policy = PolicyBuilder::new()
+            .deny("read-only", block_arg("open", 1, 1))
+            .for_command(
+                "save",
+                PolicyBuilder::new()
+                    .allow("save", block_arg("open", 1, 1))
+                    .deny("no config", block_arg("open", 0, 7)),
+            )
+            .build();
+        let open = &mut policy[0];
+
+        let scope = CommandScope::enter("load");
+        assert_eq!(
+            blocking_policy(open, vec![0, 1]).as_deref(),
+            Some("read-only")
+        );
+        assert_eq!(blocking_policy(open, vec![7, 0]), None);
+
+        drop(scope);
+        let scope = CommandScope::enter("save");
+        assert_eq!(blocking_policy(open, vec![0, 1]), None);
+        assert_eq!(
+            blocking_policy(open, vec![7, 0]).as_deref(),
+            Some("no config")
+        );
+        drop(scope);
+        assert_eq!(current_command(), None);
+
+        // Leaving a command does not leave the command invoked after it
+        let load = CommandScope::enter("load");
+        let save = CommandScope::enter("save");
+        drop(load);
+        assert_eq!(current_command(), Some("save"));
+        drop(save);
+        assert_eq!(current_command(), None);
+        assert_eq!(
+            blocking_policy(open, vec![0, 1]).as_deref(),
+            Some("read-only")
+        );
+    }
+
+    #[test]
+    fn allowed_on_entry_and_exit() {
+        // The deny rule checks the return value, the allow rule the parameters
+        let mut policy = PolicyBuilder::new()
+            .deny("no failure", fails("open"))
+            .allow("expected", block_arg("open", 0, 1))
+            .build();
+        let open = &mut policy[0];
+        assert_eq!(blocking_policy(open, vec![1, 0]), None);
+        assert!(!open.should_block(&LeaveContext(usize::MAX)));
+
+        assert_eq!(blocking_policy(open, vec![0, 0]), None);
+        assert!(open.should_block(&LeaveContext(usize::MAX)));
+        assert_eq!(open.blocking_rule().unwrap().policy, "no failure");
+    }
+
+    #[test]
+    fn rules_that_cant_be_evaluated() {
+        assert!(failing_rule("open")[0].should_block(&EntryContext(vec![1, 0])));
+
+        // An allow rule that can't be evaluated does not allow the call
+        let mut policy = PolicyBuilder::new()
+            .deny("first", block_arg("open", 0, 1))
+            .allow("exception", failing_rule("open"))
+            .build();
+        let open = &mut policy[0];
+        assert_eq!(blocking_policy(open, vec![1, 0]).as_deref(), Some("first"));
+
+        // A deny rule that can't be evaluated blocks the call
+        let mut policy = PolicyBuilder::new()
+            .deny("failing", failing_rule("open"))
+            .deny("first", block_arg("open", 0, 1))
+            .build();
+        let open = &mut policy[0];
+        assert!(open.should_block(&EntryContext(vec![0, 0])));
+        assert!(open.blocking_rule().is_none());
+        assert_eq!(blocking_policy(open, vec![1, 0]).as_deref(), Some("first"));
+
+        // Unless an exception allows the call
+        let mut policy = PolicyBuilder::new()
+            .deny("failing", failing_rule("open"))
+            .allow("exception", block_arg("open", 1, 2))
+            .build();
+        let open = &mut policy[0];
+        assert!(open.should_block(&EntryContext(vec![0, 0])));
+        assert_eq!(blocking_policy(open, vec![0, 2]), None);
+    }
+}
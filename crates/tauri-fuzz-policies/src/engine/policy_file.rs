@@ -0,0 +1,724 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Declarative policy files that are parsed into a `FuzzPolicy` at runtime.
+//!
+//! This allows to write and review policies without having to recompile the fuzz targets.
+//! Both TOML and JSON formats are supported.
+//!
+//! Example of a TOML policy file:
+//!
+//! ```toml
+//! [[function]]
+//! name = "open"
+//! lib = "libc."
+//! # Types of the parameters, `nb_parameters = 2` can be given instead for untyped parameters
+//! parameters = ["path", "int"]
+//! description = "Files in /etc can't be opened with write access"
+//! # The function is blocked if any of the conditions is true
+//! on_entry = [
+//!   { condition = "all", conditions = [
+//!     { condition = "arg_str_matches", arg = 0, glob = "/etc/**" },
+//!     { condition = "arg_flags", arg = 1, mask = 3, value = 1 },
+//!   ] },
+//! ]
+//!
+//! [[function]]
+//! name = "std::process::Command::spawn"
+//! # `lib` defaults to the current binary
+//! nb_parameters = 2
+//! is_rust_function = true
+//! description = "No child process can be spawned"
+//! on_entry = [{ condition = "always" }]
+//!
+//! [[function]]
+//! name = "rename"
+//! lib = "libc."
+//! nb_parameters = 2
+//! description = "`rename` should not fail"
+//! on_exit = [{ condition = "return_value", op = "lt", value = 0 }]
+//! ```
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::engine::{
+    untyped_signature, ArgType, Arguments, FunctionPolicy, FuzzPolicy, Rule, RuleError,
+};
+
+/// Root of a policy file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyFile {
+    /// Functions monitored by the policy
+    #[serde(default, rename = "function")]
+    pub functions: Vec<FunctionPolicySpec>,
+}
+
+/// Declarative description of a `FunctionPolicy`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionPolicySpec {
+    /// Function name
+    pub name: String,
+
+    /// Lib in which the function resides.
+    /// Defaults to the current binary which is where Rust functions reside due to static linking
+    pub lib: Option<String>,
+
+    /// Types of the arguments that the function takes
+    #[serde(default)]
+    pub parameters: Option<Vec<ArgType>>,
+
+    /// Number of arguments that the function takes when their types are not given
+    #[serde(default)]
+    pub nb_parameters: Option<u32>,
+
+    /// Description of the function policy
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// If the function monitored is a Rust function
+    #[serde(default)]
+    pub is_rust_function: bool,
+
+    /// If the function may be missing, such as a Rust function that the app does not use.
+    /// The fuzzer refuses to start when a function that is not optional can't be hooked
+    #[serde(default)]
+    pub optional: bool,
+
+    /// Conditions checked on function entry. The function is blocked if any of them is true
+    #[serde(default)]
+    pub on_entry: Vec<ConditionSpec>,
+
+    /// Conditions checked on function exit. The function is blocked if any of them is true
+    #[serde(default)]
+    pub on_exit: Vec<ConditionSpec>,
+}
+
+/// Comparison operators available in conditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn compare(self, lhs: isize, rhs: isize) -> bool {
+        match self {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+        }
+    }
+}
+
+/// A condition that will block the monitored function when true
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ConditionSpec {
+    /// Always true
+    Always,
+
+    /// The nth argument is a C string that matches the glob pattern
+    ArgStrMatches { arg: usize, glob: String },
+
+    /// `arg & mask == value`
+    ArgFlags {
+        arg: usize,
+        mask: usize,
+        value: usize,
+    },
+
+    /// Compare the nth argument as a signed integer with `value`
+    ArgCompare {
+        arg: usize,
+        op: Comparison,
+        value: isize,
+    },
+
+    /// Compare the return value as a signed integer with `value`
+    ReturnValue { op: Comparison, value: isize },
+
+    /// True if all the sub-conditions are true
+    All { conditions: Vec<ConditionSpec> },
+
+    /// True if any of the sub-conditions is true
+    Any { conditions: Vec<ConditionSpec> },
+
+    /// True if the sub-condition is false
+    Not { of: Box<ConditionSpec> },
+}
+
+/// Errors that may happen when loading a policy file
+#[derive(Debug, Error)]
+pub enum PolicyFileError {
+    #[error("Failed to read policy file: `{0}`")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse TOML policy: `{0}`")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Failed to parse JSON policy: `{0}`")]
+    Json(#[from] serde_json::Error),
+
+    /// The file extension is neither `toml` nor `json`
+    #[error("Unsupported policy file format: `{0}`")]
+    UnsupportedFormat(String),
+
+    /// A condition is not valid for the function it's attached to
+    #[error("Invalid condition for function `{function}`: {reason}")]
+    InvalidCondition { function: String, reason: String },
+
+    /// The parameters of the function are not described correctly
+    #[error("Invalid parameters for function `{function}`: {reason}")]
+    InvalidParameters { function: String, reason: String },
+
+    /// A policy used instead of or along with the policy file can't be created, such as a
+    /// network policy with a host that can't be resolved
+    #[error("Failed to create the policy: `{0}`")]
+    Policy(#[from] RuleError),
+}
+
+/// Load a policy file in TOML or JSON format depending on its extension
+///
+/// # Errors
+/// Fails if the file can't be read, parsed or contains invalid conditions
+pub fn load_policy_file(path: impl AsRef<Path>) -> Result<FuzzPolicy, PolicyFileError> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => policy_from_toml(&content),
+        Some("json") => policy_from_json(&content),
+        _ => Err(PolicyFileError::UnsupportedFormat(
+            path.display().to_string(),
+        )),
+    }
+}
+
+/// Parse a policy written in TOML
+///
+/// # Errors
+/// Fails if the content can't be parsed or contains invalid conditions
+pub fn policy_from_toml(content: &str) -> Result<FuzzPolicy, PolicyFileError> {
+    let policy_file: PolicyFile = toml::from_str(content)?;
+    policy_file.into_fuzz_policy()
+}
+
+/// Parse a policy written in JSON
+///
+/// # Errors
+/// Fails if the content can't be parsed or contains invalid conditions
+pub fn policy_from_json(content: &str) -> Result<FuzzPolicy, PolicyFileError> {
+    let policy_file: PolicyFile = serde_json::from_str(content)?;
+    policy_file.into_fuzz_policy()
+}
+
+impl PolicyFile {
+    /// Convert the declarative policy into a `FuzzPolicy`
+    ///
+    /// # Errors
+    /// Fails if a function policy contains invalid conditions
+    pub fn into_fuzz_policy(self) -> Result<FuzzPolicy, PolicyFileError> {
+        self.functions
+            .into_iter()
+            .map(FunctionPolicySpec::into_function_policy)
+            .collect()
+    }
+}
+
+impl FunctionPolicySpec {
+    /// Convert the declarative function policy into a `FunctionPolicy`
+    ///
+    /// # Errors
+    /// Fails if a condition refers to an invalid argument or is used on the wrong side of the
+    /// call
+    pub fn into_function_policy(self) -> Result<FunctionPolicy, PolicyFileError> {
+        let parameters = match (self.parameters, self.nb_parameters) {
+            (Some(parameters), None) => parameters,
+            (None, Some(nb_parameters)) => untyped_signature(nb_parameters),
+            (parameters, _) => {
+                let reason = if parameters.is_some() {
+                    "only one of `parameters` and `nb_parameters` can be given"
+                } else {
+                    "either `parameters` or `nb_parameters` must be given"
+                };
+                return Err(PolicyFileError::InvalidParameters {
+                    function: self.name,
+                    reason: reason.into(),
+                });
+            }
+        };
+        // Conditions are checked and their globs compiled once, not on every monitored call
+        let on_entry = self
+            .on_entry
+            .iter()
+            .map(|condition| condition.compile(&self.name, &parameters, true))
+            .collect::<Result<Vec<_>, _>>()?;
+        let on_exit = self
+            .on_exit
+            .iter()
+            .map(|condition| condition.compile(&self.name, &parameters, false))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let on_entry = Arc::new(on_entry);
+        let on_exit = Arc::new(on_exit);
+        let rule = match (on_entry.is_empty(), on_exit.is_empty()) {
+            (true, true) => {
+                return Err(PolicyFileError::InvalidCondition {
+                    function: self.name,
+                    reason: "no condition was given on entry nor on exit".into(),
+                })
+            }
+            (false, true) => Rule::OnEntry(Arc::new(move |args| any_on_entry(&on_entry, args))),
+            (true, false) => Rule::OnExit(Arc::new(move |return_value| {
+                any_on_exit(&on_exit, return_value)
+            })),
+            (false, false) => Rule::OnEntryAndExit(
+                Arc::new(move |args, _storage| any_on_entry(&on_entry, args)),
+                Arc::new(move |return_value, _storage| any_on_exit(&on_exit, return_value)),
+                None,
+            ),
+        };
+
+        let lib = match self.lib {
+            Some(lib) => lib,
+            None => std::env::current_exe()
+                .map_err(|e| {
+                    RuleError::InvalidPolicy(format!(
+                        "Failed to get the binary path for the library of [{}]: {e}",
+                        self.name
+                    ))
+                })?
+                .to_string_lossy()
+                .to_string(),
+        };
+        let description = self
+            .description
+            .unwrap_or_else(|| format!("Policy file rule on [{}]", self.name));
+
+        Ok(FunctionPolicy {
+            name: self.name,
+            lib,
+            rule,
+            parameters,
+            description,
+            is_rust_function: self.is_rust_function,
+            is_optional: self.optional,
+        })
+    }
+}
+
+fn any_on_entry(conditions: &[Condition], args: &Arguments) -> Result<bool, RuleError> {
+    for condition in conditions {
+        if condition.evaluate_on_entry(args)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn any_on_exit(conditions: &[Condition], return_value: usize) -> Result<bool, RuleError> {
+    for condition in conditions {
+        if condition.evaluate_on_exit(return_value)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// A [`ConditionSpec`] ready to be evaluated
+#[derive(Debug)]
+enum Condition {
+    Always,
+    ArgStrMatches {
+        arg: usize,
+        pattern: glob::Pattern,
+    },
+    ArgFlags {
+        arg: usize,
+        mask: usize,
+        value: usize,
+    },
+    ArgCompare {
+        arg: usize,
+        op: Comparison,
+        value: isize,
+    },
+    ReturnValue {
+        op: Comparison,
+        value: isize,
+    },
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl ConditionSpec {
+    /// Check that the condition can be evaluated in its context and compile it.
+    /// Conditions on arguments are only available on entry and conditions on the return value
+    /// only on exit.
+    fn compile(
+        &self,
+        function: &str,
+        parameters: &[ArgType],
+        on_entry: bool,
+    ) -> Result<Condition, PolicyFileError> {
+        let invalid = |reason: String| PolicyFileError::InvalidCondition {
+            function: function.into(),
+            reason,
+        };
+        let compile_all = |conditions: &[ConditionSpec]| {
+            conditions
+                .iter()
+                .map(|c| c.compile(function, parameters, on_entry))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        // Conditions on arguments are checked against the declared type of the argument
+        let argument_type = |arg: usize| {
+            if !on_entry {
+                return Err(invalid(format!(
+                    "condition on argument {arg} can only be used on entry"
+                )));
+            }
+            parameters.get(arg).copied().ok_or_else(|| {
+                invalid(format!(
+                    "argument {arg} is out of range for a function with {} parameters",
+                    parameters.len()
+                ))
+            })
+        };
+        let is_integer =
+            |arg_type: ArgType| matches!(arg_type, ArgType::Int | ArgType::Fd | ArgType::Unknown);
+        match self {
+            ConditionSpec::Always => Ok(Condition::Always),
+            ConditionSpec::ArgStrMatches { arg, glob } => {
+                let arg_type = argument_type(*arg)?;
+                if !arg_type.is_string() && arg_type != ArgType::Unknown {
+                    return Err(invalid(format!(
+                        "argument {arg} of type `{arg_type:?}` can't be matched against a glob"
+                    )));
+                }
+                let pattern = glob::Pattern::new(glob)
+                    .map_err(|e| invalid(format!("invalid glob `{glob}`: {e}")))?;
+                Ok(Condition::ArgStrMatches { arg: *arg, pattern })
+            }
+            ConditionSpec::ArgFlags { arg, mask, value } => {
+                let arg_type = argument_type(*arg)?;
+                if !is_integer(arg_type) {
+                    return Err(invalid(format!(
+                        "argument {arg} of type `{arg_type:?}` can't be checked for flags"
+                    )));
+                }
+                Ok(Condition::ArgFlags {
+                    arg: *arg,
+                    mask: *mask,
+                    value: *value,
+                })
+            }
+            ConditionSpec::ArgCompare { arg, op, value } => {
+                let arg_type = argument_type(*arg)?;
+                if !is_integer(arg_type) {
+                    return Err(invalid(format!(
+                        "argument {arg} of type `{arg_type:?}` can't be compared to an integer"
+                    )));
+                }
+                Ok(Condition::ArgCompare {
+                    arg: *arg,
+                    op: *op,
+                    value: *value,
+                })
+            }
+            ConditionSpec::ReturnValue { op, value } => {
+                if on_entry {
+                    Err(invalid(
+                        "condition on the return value can only be used on exit".into(),
+                    ))
+                } else {
+                    Ok(Condition::ReturnValue {
+                        op: *op,
+                        value: *value,
+                    })
+                }
+            }
+            ConditionSpec::All { conditions } => compile_all(conditions).map(Condition::All),
+            ConditionSpec::Any { conditions } => compile_all(conditions).map(Condition::Any),
+            ConditionSpec::Not { of } => of
+                .compile(function, parameters, on_entry)
+                .map(|of| Condition::Not(Box::new(of))),
+        }
+    }
+}
+
+impl Condition {
+    /// Evaluate the condition given the function parameters
+    fn evaluate_on_entry(&self, args: &Arguments) -> Result<bool, RuleError> {
+        match self {
+            Condition::Always => Ok(true),
+            Condition::ArgStrMatches { arg, pattern } => {
+                let Some(value) = args.string(*arg)? else {
+                    return Ok(false);
+                };
+                Ok(pattern.matches(&value))
+            }
+            Condition::ArgFlags { arg, mask, value } => Ok(args.raw(*arg)? & mask == *value),
+            #[allow(clippy::cast_possible_wrap)]
+            Condition::ArgCompare { arg, op, value } => {
+                Ok(op.compare(args.raw(*arg)? as isize, *value))
+            }
+            Condition::ReturnValue { .. } => Ok(false),
+            Condition::All(conditions) => {
+                for condition in conditions {
+                    if !condition.evaluate_on_entry(args)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Condition::Any(conditions) => any_on_entry(conditions, args),
+            Condition::Not(of) => Ok(!of.evaluate_on_entry(args)?),
+        }
+    }
+
+    /// Evaluate the condition given the function return value
+    fn evaluate_on_exit(&self, return_value: usize) -> Result<bool, RuleError> {
+        match self {
+            Condition::Always => Ok(true),
+            #[allow(clippy::cast_possible_wrap)]
+            Condition::ReturnValue { op, value } => Ok(op.compare(return_value as isize, *value)),
+            Condition::ArgStrMatches { .. }
+            | Condition::ArgFlags { .. }
+            | Condition::ArgCompare { .. } => Ok(false),
+            Condition::All(conditions) => {
+                for condition in conditions {
+                    if !condition.evaluate_on_exit(return_value)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Condition::Any(conditions) => any_on_exit(conditions, return_value),
+            Condition::Not(of) => Ok(!of.evaluate_on_exit(return_value)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Context::{EntryContext, LeaveContext};
+    use std::ffi::CString;
+
+    const TOML_POLICY: &str = r#"
+[[function]]
+name = "open"
+lib = "libc."
+parameters = ["path", "int"]
+description = "Files in /etc can't be opened with write access"
+on_entry = [
+  { condition = "all", conditions = [
+    { condition = "arg_str_matches", arg = 0, glob = "/etc/**" },
+    { condition = "arg_flags", arg = 1, mask = 3, value = 1 },
+  ] },
+]
+
+[[function]]
+name = "rename"
+lib = "libc."
+nb_parameters = 2
+optional = true
+on_exit = [{ condition = "return_value", op = "lt", value = 0 }]
+"#;
+
+    #[test]
+    fn parse_toml_policy() {
+        let mut policy = policy_from_toml(TOML_POLICY).unwrap();
+        assert_eq!(policy.len(), 2);
+        assert_eq!(policy[0].name, "open");
+        assert_eq!(policy[0].lib, "libc.");
+        assert!(!policy[0].is_rust_function);
+        assert!(!policy[0].is_optional);
+        assert!(policy[1].is_optional);
+        assert_eq!(policy[1].description, "Policy file rule on [rename]");
+
+        let passwd = CString::new("/etc/passwd").unwrap();
+        let tmp = CString::new("/tmp/foo").unwrap();
+        let write_passwd = EntryContext(vec![passwd.as_ptr() as usize, 1]);
+        let read_passwd = EntryContext(vec![passwd.as_ptr() as usize, 0]);
+        let write_tmp = EntryContext(vec![tmp.as_ptr() as usize, 1]);
+        assert!(policy[0].should_block(&write_passwd));
+        assert!(!policy[0].should_block(&read_passwd));
+        assert!(!policy[0].should_block(&write_tmp));
+
+        assert!(policy[1].should_block(&LeaveContext(-1isize as usize)));
+        assert!(!policy[1].should_block(&LeaveContext(0)));
+    }
+
+    #[test]
+    fn parse_json_policy() {
+        let json = r#"{
+  "function": [
+    {
+      "name": "fopen",
+      "lib": "libc.",
+      "nb_parameters": 2,
+      "on_entry": [{ "condition": "unknown_condition" }]
+    }
+  ]
+}"#;
+        // Malformed condition
+        assert!(matches!(
+            policy_from_json(json),
+            Err(PolicyFileError::Json(_))
+        ));
+
+        let json = r#"{
+  "function": [
+    {
+      "name": "fopen",
+      "lib": "libc.",
+      "nb_parameters": 2,
+      "on_entry": [{ "condition": "always" }],
+      "on_exit": [{ "condition": "return_value", "op": "eq", "value": 0 }]
+    }
+  ]
+}"#;
+        let mut policy = policy_from_json(json).unwrap();
+        assert!(policy[0].should_block(&EntryContext(vec![0, 0])));
+        assert!(policy[0].should_block(&LeaveContext(0)));
+        assert!(!policy[0].should_block(&LeaveContext(1)));
+    }
+
+    #[test]
+    fn invalid_conditions() {
+        // Argument out of range
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+nb_parameters = 2
+on_entry = [{ condition = "arg_compare", arg = 2, op = "eq", value = 0 }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidCondition { .. })
+        ));
+
+        // Return value checked on entry
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+nb_parameters = 2
+on_entry = [{ condition = "return_value", op = "eq", value = 0 }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidCondition { .. })
+        ));
+
+        // No condition
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+nb_parameters = 2
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidCondition { .. })
+        ));
+
+        // Glob matched against an integer
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+parameters = ["path", "int"]
+on_entry = [{ condition = "arg_str_matches", arg = 1, glob = "*" }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidCondition { .. })
+        ));
+
+        // Flags checked on a path
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+parameters = ["path", "int"]
+on_entry = [{ condition = "arg_flags", arg = 0, mask = 3, value = 1 }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidCondition { .. })
+        ));
+
+        // String compared to an integer
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+parameters = ["path", "int"]
+on_entry = [{ condition = "arg_compare", arg = 0, op = "eq", value = 0 }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidCondition { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        // No parameters
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+on_entry = [{ condition = "always" }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidParameters { .. })
+        ));
+
+        // Both typed and untyped parameters
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+parameters = ["path", "int"]
+nb_parameters = 2
+on_entry = [{ condition = "always" }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::InvalidParameters { .. })
+        ));
+
+        // Unknown type
+        let toml = r#"
+[[function]]
+name = "open"
+lib = "libc."
+parameters = ["path", "flags"]
+on_entry = [{ condition = "always" }]
+"#;
+        assert!(matches!(
+            policy_from_toml(toml),
+            Err(PolicyFileError::Toml(_))
+        ));
+    }
+}
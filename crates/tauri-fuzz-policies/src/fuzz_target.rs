@@ -0,0 +1,52 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Interface of the fuzz targets shared with the tools that run them such as `cargo-tauri-fuzz`.
+//! This contains the environment variables read by the fuzz targets and the exit codes
+//! reporting the outcome of a replay.
+
+/// Environment variable read by the fuzz targets to replay the given input file, or all the
+/// inputs of the given directory, instead of fuzzing
+pub const REPLAY_ENV_VAR: &str = "TAURI_FUZZ_REPLAY";
+
+/// Environment variable read by the fuzz targets of sequences to minimize the given input file,
+/// or all the inputs of the given directory, instead of fuzzing.
+/// The minimization replays each candidate with [`REPLAY_ENV_VAR`] and keeps the ones exiting
+/// with [`POLICY_VIOLATION_EXIT_CODE`].
+pub const MINIMIZE_ENV_VAR: &str = "TAURI_FUZZ_MINIMIZE";
+
+/// Environment variable enabling the audit mode of the fuzz targets when it is set.
+/// In audit mode the calls breaking the policy are not blocked but recorded in behavior profiles.
+pub const AUDIT_ENV_VAR: &str = "TAURI_FUZZ_AUDIT";
+
+/// Value of [`AUDIT_ENV_VAR`] that monitors every known function instead of the fuzz target
+/// policy
+pub const AUDIT_ALL_HOOKS: &str = "all";
+
+/// Environment variable enabling the strict mode of the fuzz targets where every hook of the
+/// policy must attach, including the hooks of optional functions
+pub const STRICT_HOOKS_ENV_VAR: &str = "TAURI_FUZZ_STRICT_HOOKS";
+
+/// Exit code of a replay where the policy was violated, or of the replay of a directory where
+/// at least one input violated the policy
+pub const POLICY_VIOLATION_EXIT_CODE: i32 = 86;
+
+/// Exit code of a replay where the command crashed
+pub const CRASH_EXIT_CODE: i32 = 87;
+
+/// Exit code of a replay where a response assertion failed
+pub const FAILED_ASSERTION_EXIT_CODE: i32 = 88;
+
+/// Exit code of a replay where a command did not respond in time
+pub const TIMEOUT_EXIT_CODE: i32 = 89;
+
+/// Describe the outcome of a fuzz target that exited with `code`
+pub fn describe_exit_code(code: i32) -> Option<&'static str> {
+    match code {
+        POLICY_VIOLATION_EXIT_CODE => Some("the policy was violated"),
+        CRASH_EXIT_CODE => Some("the command crashed"),
+        FAILED_ASSERTION_EXIT_CODE => Some("a response assertion failed"),
+        TIMEOUT_EXIT_CODE => Some("the command timed out"),
+        _ => None,
+    }
+}
@@ -10,5 +10,6 @@
 )]
 
 pub mod engine;
+pub mod fuzz_target;
 pub mod policies;
 pub use policies::*;
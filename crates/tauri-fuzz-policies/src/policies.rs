@@ -4,11 +4,40 @@
 use crate::engine::{ConditionOnParameters, FuzzPolicy};
 pub mod external_process;
 pub mod filesystem;
-mod utils;
+#[cfg(not(target_env = "msvc"))]
+pub mod network;
 
 #[cfg(unix)]
 pub(crate) const LIBC: &str = "libc.";
 
+/// Libc functions that are not exported by every libc version.
+/// The large file variants are glibc extensions while `stat`, `lstat` and `fstatat` are only
+/// exported since glibc 2.33.
+#[cfg(unix)]
+const OPTIONAL_LIBC_FUNCTIONS: [&str; 15] = [
+    "fopen64",
+    "open64",
+    "openat64",
+    "creat64",
+    "fstatat64",
+    "lstat64",
+    "stat64",
+    "statx",
+    "renameat2",
+    "scandirat",
+    "execvpe",
+    "lchmod",
+    "stat",
+    "lstat",
+    "fstatat",
+];
+
+/// If a libc function may be missing from the libc the fuzzer is linked with
+#[cfg(unix)]
+pub(crate) fn is_optional_libc_function(name: &str) -> bool {
+    OPTIONAL_LIBC_FUNCTIONS.contains(&name)
+}
+
 pub fn no_policy() -> FuzzPolicy {
     vec![]
 }
@@ -20,3 +49,27 @@ pub(crate) fn block_on_entry() -> ConditionOnParameters {
 pub fn no_error_policy() -> FuzzPolicy {
     todo!()
 }
+
+/// Policy monitoring every function hooked by the policies of this crate.
+/// Any access to a file, a host or a binary breaks it, this is used in audit mode to record all
+/// the resources accessed by a command.
+#[cfg(not(target_env = "msvc"))]
+pub fn all_known_hooks() -> FuzzPolicy {
+    crate::engine::PolicyBuilder::new()
+        .deny("filesystem", filesystem::no_file_access())
+        .deny("file metadata", filesystem::no_metadata_access())
+        .deny("network", network::no_network_access())
+        .deny("processes", external_process::allow_only_binaries(vec![]))
+        .build()
+}
+
+/// Policy monitoring every function hooked by the policies of this crate.
+/// Any access to a file breaks it, this is used in audit mode to record all the resources
+/// accessed by a command.
+#[cfg(target_env = "msvc")]
+pub fn all_known_hooks() -> FuzzPolicy {
+    crate::engine::PolicyBuilder::new()
+        .deny("filesystem", filesystem::no_file_access())
+        .deny("processes", external_process::block_on_entry(vec![]))
+        .build()
+}
@@ -1,7 +1,8 @@
 // Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
 // SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
 
-use crate::engine::{FunctionPolicy, FuzzPolicy, Rule, RuleError};
+use crate::engine::ArgType::{self, Int, Ptr, Str};
+use crate::engine::{read_value, Arguments, FunctionPolicy, FuzzPolicy, Rule, RuleError};
 #[cfg(target_env = "msvc")]
 pub use msvc::*;
 #[cfg(not(target_env = "msvc"))]
@@ -11,7 +12,7 @@ use std::sync::Arc;
 #[cfg(not(target_env = "msvc"))]
 mod not_msvc {
     use super::*;
-    use crate::policies::LIBC;
+    use crate::policies::{is_optional_libc_function, LIBC};
     use std::process::Command;
 
     /// These are the functions that the Rust `Command` API gives to start an external
@@ -32,25 +33,23 @@ mod not_msvc {
         "std::process::Child::wait_with_output",
     ];
 
+    /// Signature of the monitored functions of the Rust `Command` API, the first register is
+    /// used for the returned value
+    const RUST_API_PARAMETERS: [ArgType; 2] = [Ptr, Ptr];
+
     fn block_monitored_binaries_on_entry(
         blocked_binaries: &[String],
-        registers: &[usize],
+        args: &Arguments,
     ) -> Result<bool, RuleError> {
-        // This is unsafe because we assume that registers at index 2 is a pointer to a Rust `Command`
-        let binary = unsafe {
-            let ptr = registers[1] as *const Command;
-            ptr.as_ref()
-                .unwrap()
-                .get_program()
-                .to_str()
-                .expect("Unexpected character in binary")
+        // The monitored functions are methods of `Command` which is given in the second register
+        let Some(command) = (unsafe { args.reference::<Command>(1)? }) else {
+            return Ok(false);
         };
-
-        println!("binary found: {binary:?}");
+        let binary = command.get_program().to_string_lossy();
 
         let is_monitored_binary = blocked_binaries
             .iter()
-            .any(|blocked_binary| blocked_binary.ends_with(binary));
+            .any(|blocked_binary| blocked_binary.ends_with(binary.as_ref()));
 
         Ok(is_monitored_binary)
     }
@@ -73,54 +72,118 @@ mod not_msvc {
                 FunctionPolicy {
                     name,
                     lib: current_bin.clone(),
-                    rule: Rule::OnEntry(Arc::new(move |registers| {
-                        block_monitored_binaries_on_entry(&blocked_binaries_clone, registers)
+                    rule: Rule::OnEntry(Arc::new(move |args| {
+                        block_monitored_binaries_on_entry(&blocked_binaries_clone, args)
                     })),
                     description,
-                    nb_parameters: 2,
+                    parameters: RUST_API_PARAMETERS.to_vec(),
                     is_rust_function: true,
+                    is_optional: false,
+                }
+            })
+            .collect()
+    }
+
+    /// Functions of the libc that execute a binary.
+    /// Couple of the function name, the index of the binary argument and the signature.
+    /// The binary of the `p` variants is searched in `PATH` so it is not typed as a path.
+    const MONITORED_LIBC_EXEC_FUNCTIONS: [(&str, usize, &[ArgType]); 6] = [
+        ("execve", 0, &[ArgType::Path, Ptr, Ptr]),
+        ("execv", 0, &[ArgType::Path, Ptr]),
+        ("execvp", 0, &[Str, Ptr]),
+        ("execvpe", 0, &[Str, Ptr, Ptr]),
+        ("posix_spawn", 1, &[Ptr, ArgType::Path, Ptr, Ptr, Ptr, Ptr]),
+        ("posix_spawnp", 1, &[Ptr, Str, Ptr, Ptr, Ptr, Ptr]),
+    ];
+
+    /// Binary executed by one of the monitored libc functions such as `execve`.
+    /// Returns `None` for other functions and binaries that can't be read.
+    pub fn executed_binary(function: &str, registers: &[usize]) -> Option<String> {
+        let &(_, binary_index, _) = MONITORED_LIBC_EXEC_FUNCTIONS
+            .iter()
+            .find(|(f, _, _)| *f == function)?;
+        Arguments::new(registers)
+            .string(binary_index)
+            .ok()
+            .flatten()
+    }
+
+    /// Block the execution of a binary that is not in `allowed_binaries`
+    fn block_non_allowed_binaries(
+        allowed_binaries: &[String],
+        args: &Arguments,
+        binary_index: usize,
+    ) -> Result<bool, RuleError> {
+        let Some(binary) = args.string(binary_index)? else {
+            return Ok(false);
+        };
+        Ok(!allowed_binaries.contains(&binary))
+    }
+
+    /// Policy that blocks the execution of binaries that are not specified.
+    /// Binaries are compared with the path given to the libc functions such as `execve` or
+    /// `posix_spawnp`, this also covers the child processes created through the Rust `Command`
+    /// API.
+    pub fn allow_only_binaries(allowed_binaries: Vec<String>) -> FuzzPolicy {
+        let allowed_binaries = Arc::new(allowed_binaries);
+        MONITORED_LIBC_EXEC_FUNCTIONS
+            .iter()
+            .map(|&(f, binary_index, parameters)| {
+                let allowed_binaries = allowed_binaries.clone();
+                FunctionPolicy {
+                    name: f.into(),
+                    lib: LIBC.into(),
+                    description: format!(
+                        "Execution of binaries is only allowed for {allowed_binaries:?}"
+                    ),
+                    rule: Rule::OnEntry(Arc::new(move |args| {
+                        block_non_allowed_binaries(&allowed_binaries, args, binary_index)
+                    })),
+                    parameters: parameters.to_vec(),
+                    is_rust_function: false,
+                    is_optional: is_optional_libc_function(f),
                 }
             })
             .collect()
     }
 
+    /// Value of the `io::Result` returned by one of the functions of the Rust `Command` API
+    fn process_result<T>(result: Option<&std::io::Result<T>>) -> Result<&T, RuleError> {
+        match result {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(e)) => Err(RuleError::EvaluationError(format!(
+                "Failed to execute process: {e}"
+            ))),
+            None => Err(RuleError::ParametersTypeConversionError(
+                "null pointer returned instead of a result".into(),
+            )),
+        }
+    }
+
     // Block calls to external binaries with the Rust api command function when they return an error
     fn block_rust_api_return_error(
         function_name: &str,
         register: usize,
     ) -> Result<bool, RuleError> {
+        // The register points to the `io::Result` returned by the function
+        let registers = [register];
+        let returned = Arguments::new(&registers);
         match function_name {
             "std::process::Command::output" | "std::process::Child::wait_with_output" => {
-                let output: &std::process::Output = unsafe {
-                    let ptr = register as *const std::io::Result<std::process::Output>;
-                    ptr.as_ref()
-                        .unwrap()
-                        .as_ref()
-                        .expect("Failed to execute process")
-                    // ptr.as_ref().unwrap().expect("Failed to execute process")
-                };
-                let status = output.status;
-                Ok(!status.success())
+                let output =
+                    unsafe { returned.reference::<std::io::Result<std::process::Output>>(0)? };
+                Ok(!process_result(output)?.status.success())
             }
             "std::process::Command::status" | "std::process::Child::wait" => {
-                let exit_status: &std::process::ExitStatus = unsafe {
-                    let ptr = register as *const std::io::Result<std::process::ExitStatus>;
-                    ptr.as_ref()
-                        .unwrap()
-                        .as_ref()
-                        .expect("Failed to execute process")
-                };
-                Ok(!exit_status.success())
+                let exit_status =
+                    unsafe { returned.reference::<std::io::Result<std::process::ExitStatus>>(0)? };
+                Ok(!process_result(exit_status)?.success())
             }
             "std::process::Child::try_wait" => {
-                let exit_status: &Option<std::process::ExitStatus> = unsafe {
-                    let ptr = register as *const std::io::Result<Option<std::process::ExitStatus>>;
-                    ptr.as_ref()
-                        .unwrap()
-                        .as_ref()
-                        .expect("Failed to execute process")
+                let exit_status = unsafe {
+                    returned.reference::<std::io::Result<Option<std::process::ExitStatus>>>(0)?
                 };
-                match exit_status {
+                match process_result(exit_status)? {
                     None => Ok(true),
                     Some(status) => Ok(!status.success()),
                 }
@@ -151,26 +214,36 @@ mod not_msvc {
                         block_rust_api_return_error(&name, return_value)
                     })),
                     description,
-                    nb_parameters: 2,
+                    parameters: RUST_API_PARAMETERS.to_vec(),
                     is_rust_function: true,
+                    is_optional: false,
                 }
             })
             .collect()
     }
 
-    const MONITORED_LIBC_API_ERROR_STATUS: [&str; 3] = ["wait", "waitid", "waitpid"];
+    /// Couple of the function name and the signature
+    const MONITORED_LIBC_API_ERROR_STATUS: [(&str, &[ArgType]); 3] = [
+        ("wait", &[Ptr]),
+        ("waitid", &[Int, Int, Ptr, Int]),
+        ("waitpid", &[Int, Ptr, Int]),
+    ];
 
     /// We store the status pointer that was given as argument. This will be used to get the child
     /// process exit status
     fn get_status_pointer(
         function_name: &str,
-        parameters: &[usize],
+        args: &Arguments,
         storage: &mut Option<usize>,
     ) -> Result<bool, RuleError> {
         let status_ptr = match function_name {
-            "wait" => parameters[0],
-            "waitpid" => parameters[1],
-            "waitid" => unimplemented!(),
+            "wait" => args.raw(0)?,
+            "waitpid" => args.raw(1)?,
+            "waitid" => {
+                return Err(RuleError::EvaluationError(
+                    "`waitid` exit status is not supported".into(),
+                ))
+            }
             _ => unreachable!(),
         };
         *storage = Some(status_ptr);
@@ -188,18 +261,20 @@ mod not_msvc {
             // There was an error while waiting for child process
             return Ok(true);
         }
-        let status_ptr: *const i32 = match storage {
-            Some(v) => *v as *const i32,
+        let status_ptr = match storage {
+            Some(v) => *v,
             None => {
                 return Err(RuleError::ExpectedStorageEmpty(
                     "Status pointer was not stored for evaluation of error".to_string(),
                 ))
             }
         };
-        unsafe {
-            let child_exit_status = libc::WEXITSTATUS(*status_ptr);
-            Ok(child_exit_status != 0)
+        if status_ptr == 0 {
+            // The caller is not interested in the exit status
+            return Ok(false);
         }
+        let status: i32 = read_value(status_ptr)?;
+        Ok(libc::WEXITSTATUS(status) != 0)
     }
 
     /// Block calls to  `wait`, `waitid` and `waitpid` when they get an error status
@@ -207,29 +282,64 @@ mod not_msvc {
     pub fn block_on_child_process_error_status() -> FuzzPolicy {
         MONITORED_LIBC_API_ERROR_STATUS
             .iter()
-            .map(|f| {
-                let name: String = (*f).into();
+            .map(|&(f, parameters)| {
+                let name: String = f.into();
                 let name2: String = name.clone();
                 let description = format!("External binary {f} returned with error status");
                 FunctionPolicy {
                     name: name.clone(),
                     lib: LIBC.into(),
                     rule: Rule::OnEntryAndExit(
-                        Arc::new(move |parameters, storage| {
-                            get_status_pointer(&name, parameters, storage)
-                        }),
+                        Arc::new(move |args, storage| get_status_pointer(&name, args, storage)),
                         Arc::new(move |return_value, storage| {
                             block_libc_return_error(&name2, return_value, storage)
                         }),
                         None,
                     ),
                     description,
-                    nb_parameters: 3,
+                    parameters: parameters.to_vec(),
                     is_rust_function: false,
+                    is_optional: is_optional_libc_function(f),
                 }
             })
             .collect()
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::engine::Context::EntryContext;
+        use std::ffi::CString;
+
+        #[test]
+        fn binaries_of_monitored_functions() {
+            let curl = CString::new("/usr/bin/curl").unwrap();
+            let registers = [0, curl.as_ptr() as usize, 0, 0, 0, 0];
+            assert_eq!(
+                executed_binary("posix_spawn", &registers).as_deref(),
+                Some("/usr/bin/curl")
+            );
+            assert_eq!(executed_binary("execve", &registers), None);
+            assert_eq!(executed_binary("fork", &registers), None);
+        }
+
+        #[test]
+        fn only_allowed_binaries_are_executed() {
+            let mut policy = allow_only_binaries(vec!["/usr/bin/curl".into()]);
+            let execve = policy.iter_mut().find(|p| p.name == "execve").unwrap();
+            let curl = CString::new("/usr/bin/curl").unwrap();
+            let sh = CString::new("/bin/sh").unwrap();
+            assert!(!execve.should_block(&EntryContext(vec![curl.as_ptr() as usize, 0, 0])));
+            assert!(execve.should_block(&EntryContext(vec![sh.as_ptr() as usize, 0, 0])));
+
+            let posix_spawnp = policy
+                .iter_mut()
+                .find(|p| p.name == "posix_spawnp")
+                .unwrap();
+            let registers = vec![0, sh.as_ptr() as usize, 0, 0, 0, 0];
+            assert!(posix_spawnp.should_block(&EntryContext(registers)));
+        }
+    }
 }
 
 #[cfg(target_env = "msvc")]
@@ -241,12 +351,28 @@ mod msvc {
     /// These functions are used to wait for child processes
     /// TODO: Maybe reimplement this but with same functions but from `ntdll.dll`
     const MONITORED_WAIT_FUNCTIONS: [(&str, &str); 1] = [("GetExitCodeProcess", KERNEL32)];
+    const WAIT_PARAMETERS: [ArgType; 2] = [Ptr, Ptr];
 
-    /// Functions that we are monitoring coupled with the library in which they reside
+    /// Functions that we are monitoring coupled with the library in which they reside and their
+    /// signature.
     /// These functions are used to create a child process
     /// TODO: Maybe reimplement this but with same functions but from `ntdll.dll`
-    const MONITORED_CREATE_PROCESS_FUNCTIONS: [(&str, &str); 2] =
-        [("CreateProcessA", KERNEL32), ("CreateProcessW", KERNEL32)];
+    const MONITORED_CREATE_PROCESS_FUNCTIONS: [(&str, &str, &[ArgType]); 2] = [
+        (
+            "CreateProcessA",
+            KERNEL32,
+            &[Str, Str, Ptr, Ptr, Int, Int, Ptr, Str, Ptr, Ptr],
+        ),
+        // Wide strings are decoded by the rule itself
+        (
+            "CreateProcessW",
+            KERNEL32,
+            &[Ptr, Ptr, Ptr, Ptr, Int, Int, Ptr, Ptr, Ptr, Ptr],
+        ),
+    ];
+
+    /// Maximum number of characters read from a wide string
+    const MAX_WIDE_STRING_LENGTH: usize = 32 * 1024;
 
     /// Policy that will block the execution anytime a child process returns an error
     pub fn block_on_child_process_error_status() -> FuzzPolicy {
@@ -261,8 +387,9 @@ mod msvc {
                     None,
                 ),
                 description: format!("[`{f}`] exit code from child process is non-0 "),
-                nb_parameters: 2,
+                parameters: WAIT_PARAMETERS.to_vec(),
                 is_rust_function: false,
+                is_optional: false,
             })
             .collect::<FuzzPolicy>()
     }
@@ -271,11 +398,8 @@ mod msvc {
     // This will be used when exiting the function to check the exit status
     // NOTE: not sure on the guarantees that parameter order is kept in C programs
     // Use with caution
-    fn store_status_ptr(
-        parameters: &[usize],
-        storage: &mut Option<usize>,
-    ) -> Result<bool, RuleError> {
-        *storage = Some(parameters[1]);
+    fn store_status_ptr(args: &Arguments, storage: &mut Option<usize>) -> Result<bool, RuleError> {
+        *storage = Some(args.raw(1)?);
         Ok(false)
     }
 
@@ -294,8 +418,8 @@ mod msvc {
         // `GetExitCodeProcess` status pointer is of type `LPDWORD`
         // Following the crate `winapi` this corresponds to u32 raw pointer
         // <https://docs.rs/winapi/latest/winapi/shared/minwindef/type.LPDWORD.html>
-        let status_ptr: *const u32 = match storage {
-            Some(v) => *v as *const u32,
+        let status_ptr = match storage {
+            Some(v) => *v,
             None => {
                 return Err(RuleError::ExpectedStorageEmpty(
                     "Status pointer was not stored for evaluation of error".to_string(),
@@ -303,13 +427,9 @@ mod msvc {
             }
         };
 
-        unsafe {
-            println!("status_ptr: {:?}", status_ptr);
-            println!("*status_ptr: {:?}", *status_ptr);
-
-            // Standard is that success should return 0
-            Ok(*status_ptr != 0)
-        }
+        // Standard is that success should return 0
+        let status: u32 = read_value(status_ptr)?;
+        Ok(status != 0)
     }
 
     /// Block child processes that are created through `MONITORED_CREATE_PROCESS_FUNCTIONS`
@@ -317,100 +437,107 @@ mod msvc {
     pub fn block_on_entry(blocked_binaries: Vec<String>) -> FuzzPolicy {
         MONITORED_CREATE_PROCESS_FUNCTIONS
             .into_iter()
-            .map(|(f, lib)| {
+            .map(|(f, lib, parameters)| {
                 let blocked_binaries_clone = blocked_binaries.clone();
                 FunctionPolicy {
                     name: f.into(),
                     lib: lib.into(),
-                    rule: Rule::OnEntry(Arc::new(move |registers| {
-                        block_monitored_binaries_on_entry(f, &blocked_binaries_clone, registers)
+                    rule: Rule::OnEntry(Arc::new(move |args| {
+                        block_monitored_binaries_on_entry(f, &blocked_binaries_clone, args)
                     })),
                     description: format!(
                         "[`{f}`] These executables are not allowed: {:?}",
                         blocked_binaries
                     ),
-                    nb_parameters: 10,
+                    parameters: parameters.to_vec(),
                     is_rust_function: false,
+                    is_optional: false,
                 }
             })
             .collect::<FuzzPolicy>()
     }
 
-    use std::os::windows::prelude::OsStringExt;
-    fn block_monitored_binaries_on_entry(
+    /// Binary executed by `CreateProcessA` or `CreateProcessW`.
+    /// The application name is optional, the binary is then the first token of the command line.
+    /// Returns `None` for other functions and binaries that can't be read.
+    pub fn executed_binary(function: &str, registers: &[usize]) -> Option<String> {
+        if !MONITORED_CREATE_PROCESS_FUNCTIONS
+            .iter()
+            .any(|(f, _, _)| *f == function)
+        {
+            return None;
+        }
+        let (application_name, command_line) =
+            process_strings(function, &Arguments::new(registers)).ok()?;
+        application_name.or_else(|| command_line.as_deref().map(command_line_program))
+    }
+
+    /// Program at the start of a command line, it's quoted if it contains spaces
+    fn command_line_program(command_line: &str) -> String {
+        let command_line = command_line.trim_start();
+        let program = match command_line.strip_prefix('"') {
+            Some(quoted) => quoted.split('"').next(),
+            None => command_line.split_whitespace().next(),
+        };
+        program.unwrap_or_default().to_string()
+    }
+
+    /// Application name and command line given to one of the functions creating a process,
+    /// both can be null
+    fn process_strings(
         function_name: &str,
-        blocked_binaries: &[String],
-        registers: &[usize],
-    ) -> Result<bool, RuleError> {
+        args: &Arguments,
+    ) -> Result<(Option<String>, Option<String>), RuleError> {
         match function_name {
-            "CreateProcessA" => {
-                // Related docs: <https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createprocessa>
-
-                // Full path to executable
-                // Type is LPCSTR
-                let command_path = registers[0] as *const i8;
-                // Command as if it was executed in cmd.exe
-                // Type is LPSTR
-                let command_line = registers[1] as *const i8;
-
-                let block_command_path =
-                    contains_blocked_binaries(lpstr_to_string(command_path), blocked_binaries);
-                let block_command_line =
-                    contains_blocked_binaries(lpstr_to_string(command_line), blocked_binaries);
-
-                Ok(block_command_path || block_command_line)
-            }
-            "CreateProcessW" => {
-                // Related docs: <https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createprocessw>
-
-                // Full path to executable
-                // Type is LPCWSTR
-                let command_path = registers[0] as *const u16;
-                // Command as if it was executed in cmd.exe
-                // Type is LPWSTR
-                let command_line = registers[1] as *const u16;
-
-                let block_command_path =
-                    contains_blocked_binaries(lpwstr_to_string(command_path), blocked_binaries);
-                let block_command_line =
-                    contains_blocked_binaries(lpwstr_to_string(command_line), blocked_binaries);
-
-                Ok(block_command_path || block_command_line)
-            }
+            // Related docs: <https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createprocessa>
+            // Full path to executable of type LPCSTR and command as if it was executed in
+            // cmd.exe of type LPSTR
+            "CreateProcessA" => Ok((args.string(0)?, args.string(1)?)),
+            // Related docs: <https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createprocessw>
+            // Same as `CreateProcessA` with types LPCWSTR and LPWSTR
+            "CreateProcessW" => Ok((
+                lpwstr_to_string(args.raw(0)?)?,
+                lpwstr_to_string(args.raw(1)?)?,
+            )),
             _ => unimplemented!(),
         }
     }
 
-    fn lpstr_to_string(windows_str: *const i8) -> Option<String> {
-        if windows_str.is_null() {
-            return None;
-        }
+    use std::os::windows::prelude::OsStringExt;
+    fn block_monitored_binaries_on_entry(
+        function_name: &str,
+        blocked_binaries: &[String],
+        args: &Arguments,
+    ) -> Result<bool, RuleError> {
+        let (command_path, command_line) = process_strings(function_name, args)?;
 
-        let c_str;
-        // The functions we are monitoring are supposed to be called with valid null-terminated strings
-        unsafe {
-            c_str = std::ffi::CStr::from_ptr(windows_str);
-        }
-        let rust_string = c_str.to_string_lossy().to_string();
-        println!("ANSI: {}", rust_string);
-        Some(rust_string)
+        let block_command_path = contains_blocked_binaries(command_path, blocked_binaries);
+        let block_command_line = contains_blocked_binaries(command_line, blocked_binaries);
+        Ok(block_command_path || block_command_line)
     }
 
-    fn lpwstr_to_string(windows_str: *const u16) -> Option<String> {
-        if windows_str.is_null() {
-            return None;
+    /// Read the null-terminated wide string at `address`, `None` if it is a null pointer
+    fn lpwstr_to_string(address: usize) -> Result<Option<String>, RuleError> {
+        if address == 0 {
+            return Ok(None);
         }
-        let slice;
-        // The functions we are monitoring are supposed to be called with valid null-terminated strings
-        unsafe {
-            let length = (0..).take_while(|&i| *windows_str.offset(i) != 0).count();
-            slice = std::slice::from_raw_parts(windows_str, length);
+        let mut wide = Vec::new();
+        loop {
+            if wide.len() >= MAX_WIDE_STRING_LENGTH {
+                return Err(RuleError::ParametersTypeConversionError(format!(
+                    "wide string at {address:#x} is too long"
+                )));
+            }
+            let c: u16 = read_value(address + 2 * wide.len())?;
+            if c == 0 {
+                break;
+            }
+            wide.push(c);
         }
-        let rust_string = std::ffi::OsString::from_wide(slice)
+        let rust_string = std::ffi::OsString::from_wide(&wide)
             .to_string_lossy()
             .into_owned();
-        println!("unicode: {}", rust_string);
-        Some(rust_string)
+        Ok(Some(rust_string))
     }
 
     /// Check if 'command' calls one of the blocked binaries
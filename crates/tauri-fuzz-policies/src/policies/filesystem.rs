@@ -3,90 +3,376 @@
 
 #![allow(dead_code)]
 pub use file_policy_impl::*;
+#[cfg(not(target_env = "msvc"))]
+pub use paths::{accessed_paths, allow_only_paths, allow_writes_only_to, deny_paths, path_policy};
+
+#[cfg(not(target_env = "msvc"))]
+mod paths;
 
 #[cfg(not(target_env = "msvc"))]
 mod file_policy_impl {
-    use crate::engine::{FunctionPolicy, FuzzPolicy, Rule, RuleError};
-    use crate::policies::{block_on_entry, LIBC};
+    use super::paths::MODIFY_FUNCTIONS;
+    use crate::engine::ArgType::{self, Fd, Int, Path, Ptr, Str};
+    use crate::engine::{Arguments, FunctionPolicy, FuzzPolicy, Rule, RuleError};
+    use crate::policies::{block_on_entry, is_optional_libc_function, LIBC};
+    use std::ffi::CString;
     use std::sync::Arc;
 
-    // Functions that are monitored when it comes to file system access
-    const MONITORED_FUNCTIONS: [&str; 3] = ["fopen", "open", "open64"];
+    /// Where the access mode of a function opening a file is found
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) enum AccessArg {
+        /// The register at this index contains a `fopen` mode string
+        ModeString(usize),
+        /// The register at this index contains `open` flags
+        Flags(usize),
+        /// The function always creates the file with write access such as `creat`
+        CreateWriteOnly,
+    }
 
-    pub fn no_file_access() -> FuzzPolicy {
-        MONITORED_FUNCTIONS
-            .iter()
-            .map(|f| {
-                let name: String = (*f).into();
-                let description = format!("Access to [{f}] denied");
-                FunctionPolicy {
-                    name,
-                    lib: LIBC.into(),
-                    rule: Rule::OnEntry(block_on_entry()),
-                    description,
-                    nb_parameters: 2,
-                    is_rust_function: false,
+    /// Access mode requested when opening a file
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum AccessMode {
+        ReadOnly,
+        WriteOnly,
+        ReadWrite,
+    }
+
+    /// Access requested when opening a file
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Access {
+        pub(crate) mode: AccessMode,
+        /// The file may be created or truncated
+        pub(crate) create_or_truncate: bool,
+    }
+
+    impl Access {
+        /// Check if the access may modify the filesystem
+        pub(crate) fn is_write(self) -> bool {
+            self.mode != AccessMode::ReadOnly || self.create_or_truncate
+        }
+
+        pub(crate) fn is_read(self) -> bool {
+            self.mode != AccessMode::WriteOnly
+        }
+    }
+
+    /// A function that opens a file
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct OpenFunction {
+        pub(crate) name: &'static str,
+        /// Index of the directory file descriptor argument for the `*at` functions
+        pub(crate) dirfd: Option<usize>,
+        /// Index of the path argument
+        pub(crate) path: usize,
+        pub(crate) access: AccessArg,
+        pub(crate) parameters: &'static [ArgType],
+    }
+
+    impl OpenFunction {
+        const fn new(
+            name: &'static str,
+            path: usize,
+            access: AccessArg,
+            parameters: &'static [ArgType],
+        ) -> Self {
+            OpenFunction {
+                name,
+                dirfd: None,
+                path,
+                access,
+                parameters,
+            }
+        }
+
+        const fn at(name: &'static str, access: AccessArg, parameters: &'static [ArgType]) -> Self {
+            OpenFunction {
+                name,
+                dirfd: Some(0),
+                path: 1,
+                access,
+                parameters,
+            }
+        }
+    }
+
+    // Functions that are monitored when it comes to opening files
+    pub(crate) const OPEN_FUNCTIONS: [OpenFunction; 9] = [
+        OpenFunction::new("fopen", 0, AccessArg::ModeString(1), &[Path, Str]),
+        OpenFunction::new("fopen64", 0, AccessArg::ModeString(1), &[Path, Str]),
+        OpenFunction::new("freopen", 0, AccessArg::ModeString(1), &[Path, Str, Ptr]),
+        OpenFunction::new("open", 0, AccessArg::Flags(1), &[Path, Int, Int]),
+        OpenFunction::new("open64", 0, AccessArg::Flags(1), &[Path, Int, Int]),
+        OpenFunction::at("openat", AccessArg::Flags(2), &[Fd, Path, Int, Int]),
+        OpenFunction::at("openat64", AccessArg::Flags(2), &[Fd, Path, Int, Int]),
+        OpenFunction::new("creat", 0, AccessArg::CreateWriteOnly, &[Path, Int]),
+        OpenFunction::new("creat64", 0, AccessArg::CreateWriteOnly, &[Path, Int]),
+    ];
+
+    // Functions that create files, directories or links without opening them.
+    // Couples of function name and signature
+    const CREATE_FUNCTIONS: [(&str, &[ArgType]); 8] = [
+        ("mkdir", &[Path, Int]),
+        ("mkdirat", &[Fd, Path, Int]),
+        ("mknod", &[Path, Int, Int]),
+        ("mknodat", &[Fd, Path, Int, Int]),
+        ("link", &[Path, Path]),
+        ("linkat", &[Fd, Path, Fd, Path, Int]),
+        // The target of a symlink is stored as is and not resolved
+        ("symlink", &[Str, Path]),
+        ("symlinkat", &[Str, Fd, Path]),
+    ];
+
+    // Functions that delete files or directories
+    const DELETE_FUNCTIONS: [(&str, &[ArgType]); 4] = [
+        ("unlink", &[Path]),
+        ("unlinkat", &[Fd, Path, Int]),
+        ("remove", &[Path]),
+        ("rmdir", &[Path]),
+    ];
+
+    // Functions that rename files or directories
+    const RENAME_FUNCTIONS: [(&str, &[ArgType]); 3] = [
+        ("rename", &[Path, Path]),
+        ("renameat", &[Fd, Path, Fd, Path]),
+        ("renameat2", &[Fd, Path, Fd, Path, Int]),
+    ];
+
+    // Functions that modify permissions or ownership of files
+    const PERMISSION_FUNCTIONS: [(&str, &[ArgType]); 8] = [
+        ("chmod", &[Path, Int]),
+        ("fchmod", &[Fd, Int]),
+        ("fchmodat", &[Fd, Path, Int, Int]),
+        ("chown", &[Path, Int, Int]),
+        ("fchown", &[Fd, Int, Int]),
+        ("lchown", &[Path, Int, Int]),
+        ("fchownat", &[Fd, Path, Int, Int, Int]),
+        ("lchmod", &[Path, Int]),
+    ];
+
+    // Functions that list the content of a directory
+    const DIRECTORY_LISTING_FUNCTIONS: [(&str, &[ArgType]); 4] = [
+        ("opendir", &[Path]),
+        ("fdopendir", &[Fd]),
+        ("scandir", &[Path, Ptr, Ptr, Ptr]),
+        ("scandirat", &[Fd, Path, Ptr, Ptr, Ptr]),
+    ];
+
+    // Functions that read file metadata
+    const METADATA_FUNCTIONS: [(&str, &[ArgType]); 7] = [
+        ("stat", &[Path, Ptr]),
+        ("stat64", &[Path, Ptr]),
+        ("lstat", &[Path, Ptr]),
+        ("lstat64", &[Path, Ptr]),
+        ("fstatat", &[Fd, Path, Ptr, Int]),
+        ("fstatat64", &[Fd, Path, Ptr, Int]),
+        ("statx", &[Fd, Path, Int, Int, Ptr]),
+    ];
+
+    /// Get the access requested by a function opening a file
+    pub(crate) fn requested_access(
+        access: AccessArg,
+        args: &Arguments,
+    ) -> Result<Access, RuleError> {
+        let access = match access {
+            AccessArg::ModeString(index) => {
+                let mode = args
+                    .c_str(index)?
+                    .map(CString::into_bytes)
+                    .unwrap_or_default();
+                let mode_access = match mode.first() {
+                    _ if mode.contains(&b'+') => AccessMode::ReadWrite,
+                    Some(b'w' | b'a') => AccessMode::WriteOnly,
+                    _ => AccessMode::ReadOnly,
+                };
+                Access {
+                    mode: mode_access,
+                    create_or_truncate: matches!(mode.first(), Some(b'w' | b'a')),
                 }
+            }
+            AccessArg::Flags(index) => {
+                let flags = args.int(index)?;
+                let mode = match flags & libc::O_ACCMODE {
+                    libc::O_RDONLY => AccessMode::ReadOnly,
+                    libc::O_WRONLY => AccessMode::WriteOnly,
+                    _ => AccessMode::ReadWrite,
+                };
+                Access {
+                    mode,
+                    create_or_truncate: flags & (libc::O_CREAT | libc::O_TRUNC) != 0,
+                }
+            }
+            AccessArg::CreateWriteOnly => Access {
+                mode: AccessMode::WriteOnly,
+                create_or_truncate: true,
+            },
+        };
+        Ok(access)
+    }
+
+    /// Policy on functions opening files that blocks depending on the access requested
+    fn open_functions_policy(description: &str, should_block: fn(Access) -> bool) -> FuzzPolicy {
+        OPEN_FUNCTIONS
+            .iter()
+            .map(|&function| FunctionPolicy {
+                name: function.name.into(),
+                lib: LIBC.into(),
+                rule: Rule::OnEntry(Arc::new(move |args| {
+                    Ok(should_block(requested_access(function.access, args)?))
+                })),
+                description: format!("[{}] {description}", function.name),
+                parameters: function.parameters.to_vec(),
+                is_rust_function: false,
+                is_optional: is_optional_libc_function(function.name),
             })
             .collect()
     }
 
-    // Check if the flag is READ_ONLY
-    fn block_non_read_only(params: &[usize]) -> Result<bool, RuleError> {
-        let flag = params[1];
-        let res = (flag & libc::O_ACCMODE as usize) == libc::O_RDONLY as usize;
-        Ok(!res)
+    /// Policy that blocks any call to the functions given
+    fn block_functions_policy(functions: &[(&str, &[ArgType])], description: &str) -> FuzzPolicy {
+        functions
+            .iter()
+            .map(|&(f, parameters)| FunctionPolicy {
+                name: f.into(),
+                lib: LIBC.into(),
+                rule: Rule::OnEntry(block_on_entry()),
+                description: format!("[{f}] {description}"),
+                parameters: parameters.to_vec(),
+                is_rust_function: false,
+                is_optional: is_optional_libc_function(f),
+            })
+            .collect()
     }
 
-    pub fn read_only_access() -> FuzzPolicy {
-        MONITORED_FUNCTIONS
+    /// Policy that blocks any call to the functions that create, delete, rename files or modify
+    /// their permissions without opening them
+    fn modify_functions_policy(description: &str) -> FuzzPolicy {
+        MODIFY_FUNCTIONS
             .iter()
-            .map(|f| {
-                let name: String = (*f).into();
-                let description = format!("Access to [{f}] is only allowed with read-only access");
-                FunctionPolicy {
-                    name,
-                    lib: LIBC.into(),
-                    rule: Rule::OnEntry(Arc::new(block_non_read_only)),
-                    description,
-                    nb_parameters: 2,
-                    is_rust_function: false,
-                }
+            .map(|function| FunctionPolicy {
+                name: function.name.into(),
+                lib: LIBC.into(),
+                rule: Rule::OnEntry(block_on_entry()),
+                description: format!("[{}] {description}", function.name),
+                parameters: function.parameters.to_vec(),
+                is_rust_function: false,
+                is_optional: is_optional_libc_function(function.name),
             })
             .collect()
     }
 
-    // Check if the flag is WRITE_ONLY
-    fn block_non_write_only(params: &[usize]) -> Result<bool, RuleError> {
-        let flag = params[1];
-        let res = (flag & libc::O_ACCMODE as usize) == libc::O_WRONLY as usize;
-        Ok(!res)
+    /// Block any access to the filesystem: opening, creating, deleting, renaming files,
+    /// modifying permissions and listing directories
+    pub fn no_file_access() -> FuzzPolicy {
+        let mut policy = open_functions_policy("Access denied", |_| true);
+        policy.extend(no_file_creation_without_open());
+        policy.extend(no_file_deletion());
+        policy.extend(no_file_rename());
+        policy.extend(no_permission_change());
+        policy.extend(no_directory_listing());
+        policy
+    }
+
+    /// Policy where files can only be opened with read-only access.
+    /// Creating, deleting, renaming files and modifying their permissions are blocked
+    pub fn read_only_access() -> FuzzPolicy {
+        let description = "Access is only allowed with read-only access";
+        let mut policy = open_functions_policy(description, Access::is_write);
+        policy.extend(modify_functions_policy(description));
+        policy
     }
 
+    /// Policy where files can only be opened with write-only access.
+    /// Creating, deleting, renaming files and modifying their permissions are blocked
     pub fn write_only_access() -> FuzzPolicy {
-        MONITORED_FUNCTIONS
-            .iter()
-            .map(|f| {
-                let name: String = (*f).into();
-                let description = format!("Access to [{f}] is only allowed with write-only access");
-                FunctionPolicy {
-                    name,
-                    lib: LIBC.into(),
-                    rule: Rule::OnEntry(Arc::new(block_non_write_only)),
-                    description,
-                    nb_parameters: 2,
-                    is_rust_function: false,
-                }
-            })
-            .collect()
+        let description = "Access is only allowed with write-only access";
+        let mut policy =
+            open_functions_policy(description, |access| access.mode != AccessMode::WriteOnly);
+        policy.extend(modify_functions_policy(description));
+        policy
+    }
+
+    /// Block opening files with read access
+    pub fn no_file_read() -> FuzzPolicy {
+        open_functions_policy("Read access denied", Access::is_read)
+    }
+
+    /// Block opening files with write access or that may create or truncate a file.
+    /// Creating, deleting, renaming files and modifying their permissions are blocked too
+    pub fn no_file_write() -> FuzzPolicy {
+        let mut policy = open_functions_policy("Write access denied", Access::is_write);
+        policy.extend(modify_functions_policy("Write access denied"));
+        policy
+    }
+
+    fn no_file_creation_without_open() -> FuzzPolicy {
+        block_functions_policy(&CREATE_FUNCTIONS, "Creation of files denied")
+    }
+
+    /// Block creation of files, directories and links
+    pub fn no_file_creation() -> FuzzPolicy {
+        let mut policy = open_functions_policy("Creation of files denied", |access| {
+            access.create_or_truncate
+        });
+        policy.extend(no_file_creation_without_open());
+        policy
+    }
+
+    /// Block deletion of files and directories
+    pub fn no_file_deletion() -> FuzzPolicy {
+        block_functions_policy(&DELETE_FUNCTIONS, "Deletion of files denied")
+    }
+
+    /// Block renaming of files and directories
+    pub fn no_file_rename() -> FuzzPolicy {
+        block_functions_policy(&RENAME_FUNCTIONS, "Renaming of files denied")
+    }
+
+    /// Block modifications of permissions and ownership of files
+    pub fn no_permission_change() -> FuzzPolicy {
+        block_functions_policy(
+            &PERMISSION_FUNCTIONS,
+            "Modification of file permissions denied",
+        )
+    }
+
+    /// Block listing of directories
+    pub fn no_directory_listing() -> FuzzPolicy {
+        block_functions_policy(&DIRECTORY_LISTING_FUNCTIONS, "Listing directories denied")
+    }
+
+    /// Block reading file metadata
+    pub fn no_metadata_access() -> FuzzPolicy {
+        block_functions_policy(&METADATA_FUNCTIONS, "Access to file metadata denied")
     }
 
-    /// Checks if the filename contained in the first register is part of the blocked files
+    /// Signature of one of the monitored functions
+    pub(crate) fn signature(function: &str) -> Option<&'static [ArgType]> {
+        if let Some(open) = OPEN_FUNCTIONS.iter().find(|f| f.name == function) {
+            return Some(open.parameters);
+        }
+        [
+            CREATE_FUNCTIONS.as_slice(),
+            &DELETE_FUNCTIONS,
+            &RENAME_FUNCTIONS,
+            &PERMISSION_FUNCTIONS,
+            &DIRECTORY_LISTING_FUNCTIONS,
+            &METADATA_FUNCTIONS,
+        ]
+        .into_iter()
+        .flatten()
+        .find(|(f, _)| *f == function)
+        .map(|&(_, parameters)| parameters)
+    }
+
+    /// Checks if the filename contained in the path register is part of the blocked files
     fn block_access_to_filenames(
         blocked_files: &[String],
-        registers: &[usize],
+        path_index: usize,
+        args: &Arguments,
     ) -> Result<bool, RuleError> {
-        let filename = unsafe { crate::policies::utils::nth_argument_as_str(registers, 0) };
+        let Some(filename) = args.string(path_index)? else {
+            return Ok(false);
+        };
 
         Ok(blocked_files
             .iter()
@@ -95,44 +381,114 @@ mod file_policy_impl {
 
     /// Block access to file with name [`filename`].
     pub fn no_access_to_filenames(blocked_files: Vec<String>) -> FuzzPolicy {
-        MONITORED_FUNCTIONS
+        OPEN_FUNCTIONS
             .iter()
-            .map(move |f| {
-                let name: String = (*f).into();
+            .map(move |function| {
                 let description = format!(
                     "Access to following files is denied: {:?}",
                     blocked_files.clone()
                 );
                 let blocked_files_clone = blocked_files.clone();
+                let path_index = function.path;
                 FunctionPolicy {
-                    name,
+                    name: function.name.into(),
                     lib: LIBC.into(),
-                    rule: Rule::OnEntry(Arc::new(move |registers| {
-                        block_access_to_filenames(&blocked_files_clone, registers)
+                    rule: Rule::OnEntry(Arc::new(move |args| {
+                        block_access_to_filenames(&blocked_files_clone, path_index, args)
                     })),
                     description,
-                    nb_parameters: 2,
+                    parameters: function.parameters.to_vec(),
                     is_rust_function: false,
+                    is_optional: is_optional_libc_function(function.name),
                 }
             })
             .collect()
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn access_from_mode_string() {
+            let access = |mode: &str| {
+                let mode = CString::new(mode).unwrap();
+                let registers = [0, mode.as_ptr() as usize];
+                requested_access(AccessArg::ModeString(1), &Arguments::new(&registers)).unwrap()
+            };
+            assert_eq!(access("r").mode, AccessMode::ReadOnly);
+            assert!(!access("rb").is_write());
+            assert_eq!(access("w").mode, AccessMode::WriteOnly);
+            assert!(access("a").create_or_truncate);
+            assert_eq!(access("r+").mode, AccessMode::ReadWrite);
+            assert!(!access("r+").create_or_truncate);
+            assert_eq!(access("w+").mode, AccessMode::ReadWrite);
+        }
+
+        #[test]
+        fn access_from_flags() {
+            let access = |flags: i32| {
+                let registers = [0, 0, flags as usize];
+                requested_access(AccessArg::Flags(2), &Arguments::new(&registers)).unwrap()
+            };
+            assert!(!access(libc::O_RDONLY).is_write());
+            assert!(access(libc::O_RDONLY).is_read());
+            assert!(!access(libc::O_WRONLY).is_read());
+            assert!(access(libc::O_RDONLY | libc::O_CREAT).is_write());
+            assert_eq!(access(libc::O_RDWR).mode, AccessMode::ReadWrite);
+            let no_args = Arguments::new(&[]);
+            assert!(
+                requested_access(AccessArg::CreateWriteOnly, &no_args)
+                    .unwrap()
+                    .create_or_truncate
+            );
+        }
+
+        #[test]
+        fn operation_policies() {
+            let names =
+                |policy: FuzzPolicy| policy.into_iter().map(|p| p.name).collect::<Vec<String>>();
+            assert!(names(no_file_deletion()).contains(&"unlinkat".to_string()));
+            assert!(names(no_file_rename()).contains(&"renameat".to_string()));
+            assert!(names(no_file_creation()).contains(&"mkdir".to_string()));
+            assert!(names(no_file_creation()).contains(&"openat".to_string()));
+            assert!(names(no_metadata_access()).contains(&"statx".to_string()));
+            for policy in [read_only_access(), write_only_access(), no_file_write()] {
+                let names = names(policy);
+                assert!(["unlink", "rename", "mkdir", "chmod"]
+                    .iter()
+                    .all(|f| names.contains(&f.to_string())));
+            }
+
+            let mut create_policy = no_file_creation()
+                .into_iter()
+                .find(|p| p.name == "openat")
+                .unwrap();
+            let create = crate::engine::Context::EntryContext(vec![0, 0, libc::O_CREAT as usize]);
+            let read = crate::engine::Context::EntryContext(vec![0, 0, libc::O_RDONLY as usize]);
+            assert!(create_policy.should_block(&create));
+            assert!(!create_policy.should_block(&read));
+        }
+    }
 }
 
 #[cfg(target_env = "msvc")]
 mod file_policy_impl {
-    use core::slice;
-
-    use crate::engine::{FunctionPolicy, FuzzPolicy, Rule, RuleError};
+    use crate::engine::ArgType::{self, Int, Ptr};
+    use crate::engine::{
+        read_memory, read_value, Arguments, FunctionPolicy, FuzzPolicy, Rule, RuleError,
+    };
     use crate::policies::block_on_entry;
     use nt_string::unicode_string::NtUnicodeStr;
     use std::sync::Arc;
     use windows_sys::Wdk::Foundation::OBJECT_ATTRIBUTES;
-    use windows_sys::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE};
+    use windows_sys::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE, UNICODE_STRING};
 
     const FILE_CRT: &str = "ntdll.dll";
     // Doc to NtCreateFile: https://learn.microsoft.com/en-us/windows/win32/api/winternl/nf-winternl-ntcreatefile
     const OPEN_FILE: &str = "NtCreateFile";
+    const OPEN_FILE_PARAMETERS: [ArgType; 11] =
+        [Ptr, Int, Ptr, Ptr, Ptr, Int, Int, Int, Int, Ptr, Int];
 
     pub fn no_file_access() -> FuzzPolicy {
         vec![FunctionPolicy {
@@ -140,8 +496,9 @@ mod file_policy_impl {
             lib: FILE_CRT.into(),
             rule: Rule::OnEntry(block_on_entry()),
             description: format!("Access to [{FILE_CRT}::{OPEN_FILE}] denied"),
-            nb_parameters: 11,
+            parameters: OPEN_FILE_PARAMETERS.to_vec(),
             is_rust_function: false,
+            is_optional: false,
         }]
     }
 
@@ -152,8 +509,9 @@ mod file_policy_impl {
             lib: FILE_CRT.into(),
             rule: Rule::OnEntry(Arc::new(block_non_read_only_flag)),
             description: format!("Access to [{FILE_CRT}::{OPEN_FILE}] restricted to read-only"),
-            nb_parameters: 11,
+            parameters: OPEN_FILE_PARAMETERS.to_vec(),
             is_rust_function: false,
+            is_optional: false,
         }]
     }
 
@@ -162,8 +520,9 @@ mod file_policy_impl {
     // NOTE: flag values seems to differ from the documentation:
     // https://learn.microsoft.com/en-us/windows/win32/api/winternl/nf-winternl-ntcreatefile
     // Refer to the diary for more details
-    fn block_non_read_only_flag(params: &[usize]) -> Result<bool, RuleError> {
-        let flag = params[1] as u32;
+    #[allow(clippy::cast_possible_truncation)]
+    fn block_non_read_only_flag(args: &Arguments) -> Result<bool, RuleError> {
+        let flag = args.raw(1)? as u32;
         let res = (flag & GENERIC_MASK) == GENERIC_READ;
         Ok(!res)
     }
@@ -175,46 +534,75 @@ mod file_policy_impl {
             lib: FILE_CRT.into(),
             rule: Rule::OnEntry(Arc::new(block_non_write_only_flag)),
             description: format!("Access to [{FILE_CRT}::{OPEN_FILE}] restricted to read-only"),
-            nb_parameters: 11,
+            parameters: OPEN_FILE_PARAMETERS.to_vec(),
             is_rust_function: false,
+            is_optional: false,
         }]
     }
 
     // Checks if the flag is WRITE_ONLY
     // NOTE: flag values seems to differ from the documentation. Refer to the diary for more
     // details
-    fn block_non_write_only_flag(params: &[usize]) -> Result<bool, RuleError> {
-        let flag = params[1] as u32;
+    #[allow(clippy::cast_possible_truncation)]
+    fn block_non_write_only_flag(args: &Arguments) -> Result<bool, RuleError> {
+        let flag = args.raw(1)? as u32;
         let res = (flag & GENERIC_MASK) == GENERIC_WRITE;
         Ok(!res)
     }
 
-    /// Checks if the filename contained in the first register is part of the blocked files
+    /// Path of the file opened by `NtCreateFile`, the name in its object attributes
+    fn opened_file(args: &Arguments) -> Result<Option<String>, RuleError> {
+        let obj_attr_ptr = args.raw(2)?;
+        if obj_attr_ptr == 0 {
+            return Ok(None);
+        }
+        let obj_attr: OBJECT_ATTRIBUTES = read_value(obj_attr_ptr)?;
+        if obj_attr.ObjectName.is_null() {
+            return Ok(None);
+        }
+
+        // Convert win32 UNICODE_STRING to a rust String, its length is in bytes
+        let object_name: UNICODE_STRING = read_value(obj_attr.ObjectName as usize)?;
+        let mut filename_bytes = vec![0u8; usize::from(object_name.Length)];
+        read_memory(object_name.Buffer as usize, &mut filename_bytes)?;
+        let filename: Vec<u16> = filename_bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+            .collect();
+        // Get a proper UNICODE_STRING parsing with the nt-string crate
+        let unicode_data = NtUnicodeStr::try_from_u16_until_nul(&filename).map_err(|_| {
+            RuleError::ParametersTypeConversionError(String::from(
+                "Failed to get Unicode string from parameter",
+            ))
+        })?;
+
+        Ok(Some(String::from_utf16_lossy(unicode_data.as_slice())))
+    }
+
+    /// Path given to `NtCreateFile`.
+    /// Returns no path for other functions and paths that can't be read.
+    pub fn accessed_paths(function: &str, registers: &[usize]) -> Vec<String> {
+        if function != OPEN_FILE {
+            return vec![];
+        }
+        opened_file(&Arguments::new(registers))
+            .ok()
+            .flatten()
+            .into_iter()
+            .collect()
+    }
+
+    /// Checks if the filename contained in the object attributes is part of the blocked files
     fn block_access_to_filenames(
         blocked_files: &[String],
-        registers: &[usize],
+        args: &Arguments,
     ) -> Result<bool, RuleError> {
-        let obj_attr_ptr = registers[2] as *const OBJECT_ATTRIBUTES;
-        unsafe {
-            let obj_attr: OBJECT_ATTRIBUTES = *obj_attr_ptr;
-
-            // Convert win32 UNICODE_STRING to a rust String
-            let filename_buffer = (*obj_attr.ObjectName).Buffer;
-            let filename_slice =
-                slice::from_raw_parts(filename_buffer, (*obj_attr.ObjectName).Length as usize);
-            // Get a proper UNICODE_STRING parsing with the nt-string crate
-            let unicode_data =
-                NtUnicodeStr::try_from_u16_until_nul(filename_slice).map_err(|_| {
-                    RuleError::ParametersTypeConversionError(String::from(
-                        "Failed to get Unicode string from parameter",
-                    ))
-                })?;
-
-            let file_path = String::from_utf16_lossy(unicode_data.as_slice());
-            Ok(blocked_files
-                .iter()
-                .any(|blocked_filename| file_path.ends_with(blocked_filename)))
-        }
+        let Some(file_path) = opened_file(args)? else {
+            return Ok(false);
+        };
+        Ok(blocked_files
+            .iter()
+            .any(|blocked_filename| file_path.ends_with(blocked_filename)))
     }
 
     /// Block access to file with name [`filename`].
@@ -223,12 +611,13 @@ mod file_policy_impl {
         vec![FunctionPolicy {
             name: OPEN_FILE.into(),
             lib: FILE_CRT.into(),
-            rule: Rule::OnEntry(Arc::new(move |registers| {
-                block_access_to_filenames(&blocked_files_clone, registers)
+            rule: Rule::OnEntry(Arc::new(move |args| {
+                block_access_to_filenames(&blocked_files_clone, args)
             })),
             description: format!("Access to files {:?} denied", blocked_files),
-            nb_parameters: 11,
+            parameters: OPEN_FILE_PARAMETERS.to_vec(),
             is_rust_function: false,
+            is_optional: false,
         }]
     }
 }
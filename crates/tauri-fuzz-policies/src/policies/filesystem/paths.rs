@@ -0,0 +1,617 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Path-aware filesystem policies.
+//!
+//! Paths given to the monitored functions are resolved against the current working directory
+//! or the directory file descriptor and then canonicalized before being checked.
+//! This prevents escaping the policy with `..`, symlinks or relative paths.
+//!
+//! Rules are given as globs where `*` matches inside a path component and `**` matches across
+//! components. Environment variables such as `$APPDATA` or `${HOME}` are expanded.
+
+use super::file_policy_impl::{requested_access, signature, OPEN_FUNCTIONS};
+use crate::engine::ArgType::{self, Fd, Int, Str};
+use crate::engine::{Arguments, FunctionPolicy, FuzzPolicy, Rule, RuleError};
+use crate::policies::{is_optional_libc_function, LIBC};
+use glob::{MatchOptions, Pattern};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// A function that modifies the filesystem at the paths given as arguments
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ModifyFunction {
+    pub(crate) name: &'static str,
+    /// Path arguments as couples of the index of the optional directory file descriptor and the
+    /// index of the path
+    pub(crate) paths: &'static [(Option<usize>, usize)],
+    pub(crate) parameters: &'static [ArgType],
+}
+
+impl ModifyFunction {
+    const fn new(
+        name: &'static str,
+        paths: &'static [(Option<usize>, usize)],
+        parameters: &'static [ArgType],
+    ) -> Self {
+        ModifyFunction {
+            name,
+            paths,
+            parameters,
+        }
+    }
+}
+
+/// Path argument, `ArgType::Path` can't be imported since it conflicts with `std::path::Path`
+const P: ArgType = ArgType::Path;
+
+/// Functions that create, delete, rename files or modify their permissions.
+/// Calls to these functions are always considered as write accesses
+pub(crate) const MODIFY_FUNCTIONS: [ModifyFunction; 20] = [
+    ModifyFunction::new("mkdir", &[(None, 0)], &[P, Int]),
+    ModifyFunction::new("mkdirat", &[(Some(0), 1)], &[Fd, P, Int]),
+    ModifyFunction::new("mknod", &[(None, 0)], &[P, Int, Int]),
+    ModifyFunction::new("mknodat", &[(Some(0), 1)], &[Fd, P, Int, Int]),
+    ModifyFunction::new("link", &[(None, 1)], &[P, P]),
+    ModifyFunction::new("linkat", &[(Some(2), 3)], &[Fd, P, Fd, P, Int]),
+    ModifyFunction::new("symlink", &[(None, 1)], &[Str, P]),
+    ModifyFunction::new("symlinkat", &[(Some(1), 2)], &[Str, Fd, P]),
+    ModifyFunction::new("unlink", &[(None, 0)], &[P]),
+    ModifyFunction::new("unlinkat", &[(Some(0), 1)], &[Fd, P, Int]),
+    ModifyFunction::new("remove", &[(None, 0)], &[P]),
+    ModifyFunction::new("rmdir", &[(None, 0)], &[P]),
+    ModifyFunction::new("rename", &[(None, 0), (None, 1)], &[P, P]),
+    ModifyFunction::new("renameat", &[(Some(0), 1), (Some(2), 3)], &[Fd, P, Fd, P]),
+    ModifyFunction::new(
+        "renameat2",
+        &[(Some(0), 1), (Some(2), 3)],
+        &[Fd, P, Fd, P, Int],
+    ),
+    ModifyFunction::new("chmod", &[(None, 0)], &[P, Int]),
+    ModifyFunction::new("fchmodat", &[(Some(0), 1)], &[Fd, P, Int, Int]),
+    ModifyFunction::new("chown", &[(None, 0)], &[P, Int, Int]),
+    ModifyFunction::new("lchown", &[(None, 0)], &[P, Int, Int]),
+    ModifyFunction::new("fchownat", &[(Some(0), 1)], &[Fd, P, Int, Int, Int]),
+];
+
+/// Allow and deny rules on paths
+#[derive(Debug)]
+struct PathRules {
+    /// If specified, paths that do not match any of these globs are denied
+    allowed: Option<Vec<Pattern>>,
+    /// Paths that match any of these globs are denied
+    denied: Vec<Pattern>,
+    /// Only check accesses that may modify the filesystem
+    only_writes: bool,
+}
+
+impl PathRules {
+    fn new(
+        allowed: Option<Vec<String>>,
+        denied: Vec<String>,
+        only_writes: bool,
+    ) -> Result<Self, RuleError> {
+        let compile_all = |globs: Vec<String>| globs.iter().map(|g| compile_glob(g)).collect();
+        Ok(PathRules {
+            allowed: allowed.map(compile_all).transpose()?,
+            denied: compile_all(denied)?,
+            only_writes,
+        })
+    }
+
+    /// Check if accessing `path` breaks the rules
+    fn is_denied(&self, path: &Path, is_write: bool) -> bool {
+        if self.only_writes && !is_write {
+            return false;
+        }
+        let matches = |pattern: &Pattern| pattern.matches_path_with(path, glob_match_options());
+        if self.denied.iter().any(matches) {
+            return true;
+        }
+        match &self.allowed {
+            Some(allowed) => !allowed.iter().any(matches),
+            None => false,
+        }
+    }
+}
+
+fn glob_match_options() -> MatchOptions {
+    MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    }
+}
+
+/// Expand environment variables such as `$HOME` or `${HOME}` in a glob
+fn expand_env_vars(glob: &str) -> Result<String, RuleError> {
+    let mut expanded = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if braced && chars.next() != Some('}') {
+            return Err(RuleError::InvalidPolicy(format!(
+                "Unclosed environment variable in glob {glob}"
+            )));
+        }
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        let value = std::env::var(&name).map_err(|_| {
+            RuleError::InvalidPolicy(format!(
+                "Environment variable ${name} used in {glob} is not set"
+            ))
+        })?;
+        expanded.push_str(&value);
+    }
+    Ok(expanded)
+}
+
+/// Compile a glob into a pattern.
+/// The literal prefix of the glob is resolved the same way paths given to the monitored functions
+/// are, so that symlinks in the glob itself (such as `/tmp` on `MacOS`) do not prevent matching.
+fn compile_glob(glob: &str) -> Result<Pattern, RuleError> {
+    let expanded = expand_env_vars(glob)?;
+    let (prefix, suffix) = match expanded.find(['*', '?', '[']) {
+        Some(index) => {
+            // Split at the last separator before the first special character
+            let split = expanded[..index].rfind('/').map_or(0, |i| i + 1);
+            expanded.split_at(split)
+        }
+        None => (expanded.as_str(), ""),
+    };
+
+    let resolved_prefix = if prefix.is_empty() {
+        String::new()
+    } else {
+        let resolved = resolve_path(Path::new(prefix), None)
+            .map_err(|e| RuleError::InvalidPolicy(format!("Failed to resolve glob {glob}: {e}")))?;
+        let mut resolved = Pattern::escape(&resolved.to_string_lossy());
+        if prefix.ends_with('/') && !resolved.ends_with('/') {
+            resolved.push('/');
+        }
+        resolved
+    };
+
+    Pattern::new(&format!("{resolved_prefix}{suffix}"))
+        .map_err(|e| RuleError::InvalidPolicy(format!("Invalid glob {glob}: {e}")))
+}
+
+/// Get the path of the directory referred by a file descriptor
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn fd_path(fd: i32) -> Result<PathBuf, RuleError> {
+    std::fs::read_link(format!("/proc/self/fd/{fd}")).map_err(|e| {
+        RuleError::EvaluationError(format!("Failed to get path of file descriptor {fd}: {e}"))
+    })
+}
+
+/// Get the path of the directory referred by a file descriptor
+#[cfg(target_os = "macos")]
+fn fd_path(fd: i32) -> Result<PathBuf, RuleError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buffer = vec![0u8; libc::PATH_MAX as usize];
+    let res = unsafe { libc::fcntl(fd, libc::F_GETPATH, buffer.as_mut_ptr()) };
+    if res == -1 {
+        return Err(RuleError::EvaluationError(format!(
+            "Failed to get path of file descriptor {fd}"
+        )));
+    }
+    let path = std::ffi::CStr::from_bytes_until_nul(&buffer)
+        .map_err(|e| RuleError::EvaluationError(e.to_string()))?;
+    Ok(std::ffi::OsStr::from_bytes(path.to_bytes()).into())
+}
+
+/// Get the path of the directory referred by a file descriptor
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn fd_path(fd: i32) -> Result<PathBuf, RuleError> {
+    Err(RuleError::EvaluationError(format!(
+        "Resolving the path of file descriptor {fd} is not supported on this platform"
+    )))
+}
+
+/// Resolve a path the way the OS would.
+/// Relative paths are resolved against the directory file descriptor if given or the current
+/// working directory. Symlinks and `..` are resolved for the parts of the path that exist.
+pub(crate) fn resolve_path(path: &Path, dirfd: Option<i32>) -> Result<PathBuf, RuleError> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        let base = match dirfd {
+            Some(fd) if fd != libc::AT_FDCWD => fd_path(fd)?,
+            _ => std::env::current_dir().map_err(|e| {
+                RuleError::EvaluationError(format!("Failed to get current directory: {e}"))
+            })?,
+        };
+        base.join(path)
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved.push(component),
+            Component::CurDir => {}
+            // The resolved path has no symlinks so going to the parent is correct
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                // Resolve symlinks if the path exists, otherwise the path is being created
+                if let Ok(canonical) = std::fs::canonicalize(&resolved) {
+                    resolved = canonical;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Check the path at `path_index` against the rules
+fn block_on_path_rules(
+    name: &str,
+    (dirfd_index, path_index): (Option<usize>, usize),
+    is_write: bool,
+    rules: &PathRules,
+    args: &Arguments,
+) -> Result<bool, RuleError> {
+    let Some(path) = args.path(path_index)? else {
+        return Ok(false);
+    };
+    let dirfd = dirfd_index.map(|index| args.int(index)).transpose()?;
+    let resolved = resolve_path(&path, dirfd)?;
+    let denied = rules.is_denied(&resolved, is_write);
+    if denied {
+        log::error!("[{name}] access to {} denied", resolved.display());
+    }
+    Ok(denied)
+}
+
+/// Path arguments of a monitored function as couples of the index of the optional directory
+/// file descriptor and the index of the path.
+/// These are the arguments checked by the path policies. The other functions, such as `stat`,
+/// resolve their paths against the directory file descriptor that precedes them.
+fn path_arguments(function: &str) -> Vec<(Option<usize>, usize)> {
+    if let Some(open) = OPEN_FUNCTIONS.iter().find(|f| f.name == function) {
+        return vec![(open.dirfd, open.path)];
+    }
+    if let Some(modify) = MODIFY_FUNCTIONS.iter().find(|f| f.name == function) {
+        return modify.paths.to_vec();
+    }
+    let Some(parameters) = signature(function) else {
+        return vec![];
+    };
+    parameters
+        .iter()
+        .enumerate()
+        .filter(|&(_, &parameter)| parameter == P)
+        .map(|(index, _)| {
+            let dirfd = index.checked_sub(1).filter(|&i| parameters[i] == Fd);
+            (dirfd, index)
+        })
+        .collect()
+}
+
+/// Paths accessed by one of the monitored functions, resolved as the path policies resolve them
+/// so that they can be given as is to [`allow_only_paths`].
+/// Returns no path for other functions and paths that can't be read or resolved.
+pub fn accessed_paths(function: &str, registers: &[usize]) -> Vec<String> {
+    let args = Arguments::new(registers);
+    path_arguments(function)
+        .into_iter()
+        .filter_map(|(dirfd_index, path_index)| {
+            let path = args.path(path_index).ok().flatten()?;
+            let dirfd = dirfd_index.map(|index| args.int(index)).transpose().ok()?;
+            let resolved = resolve_path(&path, dirfd).ok()?;
+            Some(resolved.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+fn path_rules_policy(rules: PathRules, description: &str) -> FuzzPolicy {
+    let rules = Arc::new(rules);
+    let open_policies = OPEN_FUNCTIONS.iter().map(|&function| {
+        let rules = rules.clone();
+        FunctionPolicy {
+            name: function.name.into(),
+            lib: LIBC.into(),
+            rule: Rule::OnEntry(Arc::new(move |args| {
+                let is_write = requested_access(function.access, args)?.is_write();
+                block_on_path_rules(
+                    function.name,
+                    (function.dirfd, function.path),
+                    is_write,
+                    &rules,
+                    args,
+                )
+            })),
+            description: description.into(),
+            parameters: function.parameters.to_vec(),
+            is_rust_function: false,
+            is_optional: is_optional_libc_function(function.name),
+        }
+    });
+    let modify_policies = MODIFY_FUNCTIONS.iter().map(|&function| {
+        let rules = rules.clone();
+        FunctionPolicy {
+            name: function.name.into(),
+            lib: LIBC.into(),
+            rule: Rule::OnEntry(Arc::new(move |args| {
+                for &path_arg in function.paths {
+                    if block_on_path_rules(function.name, path_arg, true, &rules, args)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            })),
+            description: description.into(),
+            parameters: function.parameters.to_vec(),
+            is_rust_function: false,
+            is_optional: is_optional_libc_function(function.name),
+        }
+    });
+    open_policies.chain(modify_policies).collect()
+}
+
+/// Policy on paths with an allow-list and a deny-list of globs.
+/// Denied globs take precedence over allowed globs.
+/// If `allowed` is empty, any path that is not denied is allowed.
+///
+/// # Errors
+/// Fails if a glob is invalid or uses an environment variable that is not set
+pub fn path_policy(allowed: Vec<String>, denied: Vec<String>) -> Result<FuzzPolicy, RuleError> {
+    let description = format!("Access is only allowed to {allowed:?} and denied to {denied:?}");
+    let allowed = if allowed.is_empty() {
+        None
+    } else {
+        Some(allowed)
+    };
+    let rules = PathRules::new(allowed, denied, false)?;
+    Ok(path_rules_policy(rules, &description))
+}
+
+/// Block any access to paths that do not match one of the `allowed` globs
+///
+/// # Errors
+/// Fails if a glob is invalid or uses an environment variable that is not set
+pub fn allow_only_paths(allowed: Vec<String>) -> Result<FuzzPolicy, RuleError> {
+    let description = format!("Access is only allowed to {allowed:?}");
+    let rules = PathRules::new(Some(allowed), vec![], false)?;
+    Ok(path_rules_policy(rules, &description))
+}
+
+/// Block any access to paths that match one of the `denied` globs
+///
+/// # Errors
+/// Fails if a glob is invalid or uses an environment variable that is not set
+pub fn deny_paths(denied: Vec<String>) -> Result<FuzzPolicy, RuleError> {
+    let description = format!("Access is denied to {denied:?}");
+    let rules = PathRules::new(None, denied, false)?;
+    Ok(path_rules_policy(rules, &description))
+}
+
+/// Block any access that may modify the filesystem outside of the `allowed` globs.
+/// Read-only accesses are not restricted.
+///
+/// # Errors
+/// Fails if a glob is invalid or uses an environment variable that is not set
+pub fn allow_writes_only_to(allowed: Vec<String>) -> Result<FuzzPolicy, RuleError> {
+    let description = format!("Write access is only allowed to {allowed:?}");
+    let rules = PathRules::new(Some(allowed), vec![], true)?;
+    Ok(path_rules_policy(rules, &description))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    /// Create a fresh directory for a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tauri-fuzz-paths-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("app")).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    fn c_path(path: &Path) -> CString {
+        CString::new(path.as_os_str().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn resolve_parent_and_current_dirs() {
+        let dir = test_dir("dots");
+        let path = dir.join("app/./../app/../outside/foo.txt");
+        assert_eq!(
+            resolve_path(&path, None).unwrap(),
+            dir.join("outside/foo.txt")
+        );
+    }
+
+    #[test]
+    fn resolve_symlinks() {
+        let dir = test_dir("symlinks");
+        std::os::unix::fs::symlink(&dir, dir.join("app/link")).unwrap();
+        let path = dir.join("app/link/secret.txt");
+        assert_eq!(resolve_path(&path, None).unwrap(), dir.join("secret.txt"));
+    }
+
+    #[test]
+    fn resolve_relative_to_dirfd() {
+        let dir = test_dir("dirfd");
+        let app_dir = c_path(&dir.join("app"));
+        let fd = unsafe { libc::open(app_dir.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+        assert!(fd >= 0);
+        let resolved = resolve_path(Path::new("../foo.txt"), Some(fd)).unwrap();
+        unsafe { libc::close(fd) };
+        assert_eq!(resolved, dir.join("foo.txt"));
+
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(
+            resolve_path(Path::new("foo.txt"), Some(libc::AT_FDCWD)).unwrap(),
+            cwd.join("foo.txt")
+        );
+    }
+
+    #[test]
+    fn paths_of_monitored_functions() {
+        let dir = test_dir("accessed");
+        let from = c_path(&dir.join("app/../foo.txt"));
+        let to = c_path(&dir.join("app/bar.txt"));
+        let at_fdcwd = libc::AT_FDCWD as usize;
+        let registers = [
+            at_fdcwd,
+            from.as_ptr() as usize,
+            at_fdcwd,
+            to.as_ptr() as usize,
+        ];
+        let expected = [dir.join("foo.txt"), dir.join("app/bar.txt")]
+            .map(|path| path.to_string_lossy().into_owned());
+        assert_eq!(accessed_paths("renameat", &registers), expected);
+
+        // Relative paths are resolved against the directory file descriptor
+        let app_dir = c_path(&dir.join("app"));
+        let fd = unsafe { libc::open(app_dir.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+        assert!(fd >= 0);
+        let relative = CString::new("../foo.txt").unwrap();
+        let registers = [fd as usize, relative.as_ptr() as usize, 0, 0];
+        let stat_paths = accessed_paths("fstatat", &registers);
+        unsafe { libc::close(fd) };
+        assert_eq!(stat_paths, expected[..1]);
+
+        // The mode of `fopen` is not a path
+        let mode = CString::new("r").unwrap();
+        let registers = [from.as_ptr() as usize, mode.as_ptr() as usize];
+        assert_eq!(accessed_paths("fopen", &registers), expected[..1]);
+        assert!(accessed_paths("fchmod", &[3, 0]).is_empty());
+        assert!(accessed_paths("read", &registers).is_empty());
+    }
+
+    #[test]
+    fn expand_globs() {
+        std::env::set_var("TAURI_FUZZ_TEST_VAR", "/foo");
+        assert_eq!(
+            expand_env_vars("$TAURI_FUZZ_TEST_VAR/**").unwrap(),
+            "/foo/**"
+        );
+        assert_eq!(
+            expand_env_vars("${TAURI_FUZZ_TEST_VAR}bar").unwrap(),
+            "/foobar"
+        );
+        assert_eq!(expand_env_vars("/a$/b").unwrap(), "/a$/b");
+    }
+
+    #[test]
+    fn invalid_globs_are_errors() {
+        assert!(matches!(
+            allow_only_paths(vec!["$TAURI_FUZZ_UNSET_TEST_VAR/**".into()]),
+            Err(RuleError::InvalidPolicy(_))
+        ));
+        assert!(matches!(
+            deny_paths(vec!["${HOME/**".into()]),
+            Err(RuleError::InvalidPolicy(_))
+        ));
+        assert!(matches!(
+            path_policy(vec![], vec!["/tmp/***".into()]),
+            Err(RuleError::InvalidPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn allow_list_blocks_escapes() {
+        let dir = test_dir("allow");
+        std::os::unix::fs::symlink(&dir, dir.join("app/link")).unwrap();
+        let rules = PathRules::new(
+            Some(vec![format!("{}/app/**", dir.display())]),
+            vec![],
+            false,
+        )
+        .unwrap();
+
+        let inside = c_path(&dir.join("app/foo.txt"));
+        let escape_dots = c_path(&dir.join("app/../foo.txt"));
+        let escape_symlink = c_path(&dir.join("app/link/foo.txt"));
+        let registers = |path: &CString| vec![path.as_ptr() as usize, libc::O_RDONLY as usize, 0];
+        let block = |path: &CString| {
+            let registers = registers(path);
+            block_on_path_rules(
+                "open",
+                (None, 0),
+                false,
+                &rules,
+                &Arguments::new(&registers),
+            )
+            .unwrap()
+        };
+
+        assert!(!block(&inside));
+        assert!(block(&escape_dots));
+        assert!(block(&escape_symlink));
+    }
+
+    #[test]
+    fn deny_list_and_writes() {
+        let dir = test_dir("deny");
+        let rules = PathRules::new(
+            Some(vec![format!("{}/**", dir.display())]),
+            vec![format!("{}/app/*.key", dir.display())],
+            false,
+        )
+        .unwrap();
+        assert!(rules.is_denied(&dir.join("app/secret.key"), false));
+        assert!(!rules.is_denied(&dir.join("app/secret.txt"), false));
+        assert!(!rules.is_denied(&dir.join("app/nested/secret.key"), false));
+        assert!(rules.is_denied(Path::new("/outside"), false));
+
+        let rules = PathRules::new(
+            Some(vec![format!("{}/app/**", dir.display())]),
+            vec![],
+            true,
+        )
+        .unwrap();
+        assert!(!rules.is_denied(&dir.join("foo.txt"), false));
+        assert!(rules.is_denied(&dir.join("foo.txt"), true));
+        assert!(!rules.is_denied(&dir.join("app/foo.txt"), true));
+    }
+
+    #[test]
+    fn modifications_are_writes() {
+        let dir = test_dir("modify");
+        let policy = allow_writes_only_to(vec![format!("{}/app/**", dir.display())]).unwrap();
+        let mut rename = policy.into_iter().find(|p| p.name == "renameat").unwrap();
+
+        let inside = c_path(&dir.join("app/foo.txt"));
+        let outside = c_path(&dir.join("foo.txt"));
+        let at_fdcwd = libc::AT_FDCWD as usize;
+        let renameat = |from: &CString, to: &CString| {
+            crate::engine::Context::EntryContext(vec![
+                at_fdcwd,
+                from.as_ptr() as usize,
+                at_fdcwd,
+                to.as_ptr() as usize,
+            ])
+        };
+        assert!(!rename.should_block(&renameat(&inside, &inside)));
+        assert!(rename.should_block(&renameat(&inside, &outside)));
+        assert!(rename.should_block(&renameat(&outside, &inside)));
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Policies on network access.
+//! The libc functions `socket`, `connect`, `sendto` and `getaddrinfo` are monitored.
+//!
+//! NOTE: Windows is not supported yet
+
+use crate::engine::ArgType::{self, Fd, Int, Ptr, SockAddr, Str};
+use crate::engine::{Arguments, FunctionPolicy, FuzzPolicy, Rule, RuleError};
+use crate::policies::{is_optional_libc_function, LIBC};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+/// Function that creates a socket
+const SOCKET: &str = "socket";
+const SOCKET_PARAMETERS: [ArgType; 3] = [Int, Int, Int];
+/// Functions that send data to a remote address.
+/// Couple of the function name, the index of the `sockaddr` argument and the signature
+const MONITORED_ADDRESS_FUNCTIONS: [(&str, usize, &[ArgType]); 2] = [
+    ("connect", 1, &[Fd, SockAddr, Int]),
+    ("sendto", 4, &[Fd, Ptr, Int, Int, SockAddr, Int]),
+];
+/// Function that resolves a hostname
+const GETADDRINFO: &str = "getaddrinfo";
+const GETADDRINFO_PARAMETERS: [ArgType; 4] = [Str, Str, Ptr, Ptr];
+
+/// Remote address given to one of the monitored functions sending data such as `connect`.
+/// Returns `None` for other functions, addresses that are not IP addresses and addresses that
+/// can't be read.
+pub fn contacted_address(function: &str, registers: &[usize]) -> Option<SocketAddr> {
+    let &(_, addr_index, _) = MONITORED_ADDRESS_FUNCTIONS
+        .iter()
+        .find(|(f, _, _)| *f == function)?;
+    Arguments::new(registers)
+        .sockaddr(addr_index)
+        .ok()
+        .flatten()
+}
+
+/// Hostname given to `getaddrinfo`.
+/// Returns `None` for other functions and hostnames that can't be read.
+pub fn resolved_host(function: &str, registers: &[usize]) -> Option<String> {
+    if function != GETADDRINFO {
+        return None;
+    }
+    Arguments::new(registers).string(0).ok().flatten()
+}
+
+/// Check if the socket created is an internet socket.
+/// Unix sockets are not considered as network access
+fn block_inet_socket(args: &Arguments) -> Result<bool, RuleError> {
+    let domain = args.int(0)?;
+    Ok(domain == libc::AF_INET || domain == libc::AF_INET6)
+}
+
+/// Policy that blocks creation of internet sockets, connections, sending data and hostname
+/// resolution
+pub fn no_network_access() -> FuzzPolicy {
+    let mut policy = vec![FunctionPolicy {
+        name: SOCKET.into(),
+        lib: LIBC.into(),
+        rule: Rule::OnEntry(Arc::new(block_inet_socket)),
+        description: format!("Creation of internet socket with [{SOCKET}] denied"),
+        parameters: SOCKET_PARAMETERS.to_vec(),
+        is_rust_function: false,
+        is_optional: false,
+    }];
+    policy.extend(
+        MONITORED_ADDRESS_FUNCTIONS
+            .iter()
+            .map(|&(f, addr_index, parameters)| FunctionPolicy {
+                name: f.into(),
+                lib: LIBC.into(),
+                rule: Rule::OnEntry(Arc::new(move |args| {
+                    Ok(args.sockaddr(addr_index)?.is_some())
+                })),
+                description: format!("Network access with [{f}] denied"),
+                parameters: parameters.to_vec(),
+                is_rust_function: false,
+                is_optional: is_optional_libc_function(f),
+            }),
+    );
+    policy.push(FunctionPolicy {
+        name: GETADDRINFO.into(),
+        lib: LIBC.into(),
+        rule: Rule::OnEntry(crate::policies::block_on_entry()),
+        description: format!("Hostname resolution with [{GETADDRINFO}] denied"),
+        parameters: GETADDRINFO_PARAMETERS.to_vec(),
+        is_rust_function: false,
+        is_optional: false,
+    });
+    policy
+}
+
+/// Resolve the IP addresses of the hosts given.
+/// Hosts can either be IP addresses or hostnames.
+fn resolve_hosts(hosts: &[String]) -> Result<HashSet<IpAddr>, RuleError> {
+    let mut ips = HashSet::new();
+    for host in hosts {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            ips.insert(ip);
+            continue;
+        }
+        let addresses = (host.as_str(), 0)
+            .to_socket_addrs()
+            .map_err(|e| RuleError::InvalidPolicy(format!("Failed to resolve host {host}: {e}")))?;
+        ips.extend(addresses.map(|addr| addr.ip()));
+    }
+    Ok(ips)
+}
+
+/// Block the call if the address in register `addr_index` is not an allowed IP
+fn block_non_allowed_ips(
+    allowed_ips: &HashSet<IpAddr>,
+    args: &Arguments,
+    addr_index: usize,
+) -> Result<bool, RuleError> {
+    Ok(args
+        .sockaddr(addr_index)?
+        .is_some_and(|addr| !allowed_ips.contains(&addr.ip())))
+}
+
+/// Block the resolution of hostnames that are not allowed
+fn block_non_allowed_hostnames(
+    allowed_hosts: &[String],
+    args: &Arguments,
+) -> Result<bool, RuleError> {
+    let Some(hostname) = args.string(0)? else {
+        // No hostname was given, only a service
+        return Ok(false);
+    };
+    Ok(!allowed_hosts.contains(&hostname))
+}
+
+/// Policy that blocks network access to hosts that are not specified.
+/// Hosts can either be IP addresses or hostnames.
+/// Hostnames are resolved when creating the policy.
+///
+/// # Errors
+/// Fails if one of the hostnames can't be resolved
+pub fn allow_only_hosts(allowed_hosts: Vec<String>) -> Result<FuzzPolicy, RuleError> {
+    let allowed_ips = Arc::new(resolve_hosts(&allowed_hosts)?);
+    let mut policy: FuzzPolicy = MONITORED_ADDRESS_FUNCTIONS
+        .iter()
+        .map(|&(f, addr_index, parameters)| {
+            let allowed_ips = allowed_ips.clone();
+            FunctionPolicy {
+                name: f.into(),
+                lib: LIBC.into(),
+                rule: Rule::OnEntry(Arc::new(move |args| {
+                    block_non_allowed_ips(&allowed_ips, args, addr_index)
+                })),
+                description: format!("Network access is only allowed to {allowed_hosts:?}"),
+                parameters: parameters.to_vec(),
+                is_rust_function: false,
+                is_optional: is_optional_libc_function(f),
+            }
+        })
+        .collect();
+
+    let description = format!("Hostname resolution is only allowed for {allowed_hosts:?}");
+    policy.push(FunctionPolicy {
+        name: GETADDRINFO.into(),
+        lib: LIBC.into(),
+        rule: Rule::OnEntry(Arc::new(move |args| {
+            block_non_allowed_hostnames(&allowed_hosts, args)
+        })),
+        description,
+        parameters: GETADDRINFO_PARAMETERS.to_vec(),
+        is_rust_function: false,
+        is_optional: false,
+    });
+    Ok(policy)
+}
+
+/// Policy that blocks network access to the specified ports
+pub fn block_ports(blocked_ports: Vec<u16>) -> FuzzPolicy {
+    let blocked_ports = Arc::new(blocked_ports);
+    MONITORED_ADDRESS_FUNCTIONS
+        .iter()
+        .map(|&(f, addr_index, parameters)| {
+            let blocked_ports_clone = blocked_ports.clone();
+            FunctionPolicy {
+                name: f.into(),
+                lib: LIBC.into(),
+                rule: Rule::OnEntry(Arc::new(move |args| {
+                    Ok(args
+                        .sockaddr(addr_index)?
+                        .is_some_and(|addr| blocked_ports_clone.contains(&addr.port())))
+                })),
+                description: format!("Network access to ports {blocked_ports:?} denied"),
+                parameters: parameters.to_vec(),
+                is_rust_function: false,
+                is_optional: is_optional_libc_function(f),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Context::EntryContext;
+    use std::net::TcpListener;
+
+    /// Create a `sockaddr_in` pointing to the loopback address with given port
+    fn loopback_sockaddr(port: u16) -> libc::sockaddr_in {
+        let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+        addr.sin_family = libc::AF_INET as libc::sa_family_t;
+        addr.sin_port = port.to_be();
+        addr.sin_addr.s_addr = u32::from(std::net::Ipv4Addr::LOCALHOST).to_be();
+        addr
+    }
+
+    fn connect_context(addr: &libc::sockaddr_in) -> crate::engine::Context {
+        let addr_ptr = std::ptr::from_ref(addr) as usize;
+        EntryContext(vec![0, addr_ptr, std::mem::size_of::<libc::sockaddr_in>()])
+    }
+
+    fn connect_policy(policy: FuzzPolicy) -> FunctionPolicy {
+        policy.into_iter().find(|p| p.name == "connect").unwrap()
+    }
+
+    #[test]
+    fn decode_sockaddr() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let addr = loopback_sockaddr(port);
+        let registers = [0, std::ptr::from_ref(&addr) as usize, 0];
+        let contacted = contacted_address("connect", &registers);
+        assert_eq!(contacted, Some(server.local_addr().unwrap()));
+        assert_eq!(contacted_address("connect", &[0, 0, 0]), None);
+        assert_eq!(contacted_address("open", &registers), None);
+    }
+
+    #[test]
+    fn no_network() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = loopback_sockaddr(server.local_addr().unwrap().port());
+        let mut policy = connect_policy(no_network_access());
+        assert!(policy.should_block(&connect_context(&addr)));
+
+        let mut socket_policy = no_network_access().remove(0);
+        let unix_socket = EntryContext(vec![libc::AF_UNIX as usize, 0, 0]);
+        let inet_socket = EntryContext(vec![libc::AF_INET as usize, 0, 0]);
+        assert!(!socket_policy.should_block(&unix_socket));
+        assert!(socket_policy.should_block(&inet_socket));
+    }
+
+    #[test]
+    fn allowed_hosts() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = loopback_sockaddr(server.local_addr().unwrap().port());
+
+        let mut policy = connect_policy(allow_only_hosts(vec!["127.0.0.1".into()]).unwrap());
+        assert!(!policy.should_block(&connect_context(&addr)));
+
+        let mut policy = connect_policy(allow_only_hosts(vec!["10.0.0.1".into()]).unwrap());
+        assert!(policy.should_block(&connect_context(&addr)));
+
+        // Hostnames that can't be resolved are reported when creating the policy
+        assert!(matches!(
+            allow_only_hosts(vec!["host.invalid".into()]),
+            Err(RuleError::InvalidPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn blocked_ports() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        let addr = loopback_sockaddr(port);
+
+        let mut policy = connect_policy(block_ports(vec![port]));
+        assert!(policy.should_block(&connect_context(&addr)));
+
+        let mut policy = connect_policy(block_ports(vec![port.wrapping_add(1)]));
+        assert!(!policy.should_block(&connect_context(&addr)));
+    }
+}
@@ -26,11 +26,15 @@ pub fn no_file_access() -> FuzzPolicy {
             // Description used when an execution does not respect the rule specified above
             description: "Access to [fopen] denied".into(),
 
-            // Number of parameters the function takes
-            nb_parameters: 2,
+            // Types of the parameters the function takes, they are used to decode the arguments
+            // safely
+            parameters: vec![ArgType::Path, ArgType::Int],
 
             // Specify if we are monitoring a Rust function
             is_rust_function: false,
+
+            // Specify if the fuzzer can still start when the function can't be found
+            is_optional: false,
         },
         // We also monitor a second function that can violate our security policy
         FunctionPolicy {
@@ -38,8 +42,9 @@ pub fn no_file_access() -> FuzzPolicy {
             lib: LIBC.into(),
             rule: Rule::OnEntry(block_on_entry),
             description: "Access to [open64] denied".into(),
-            nb_parameters: 2,
+            parameters: vec![ArgType::Path, ArgType::Int],
             is_rust_function: false,
+            is_optional: false,
         },
     ]
 }
@@ -0,0 +1,68 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Throughput of a harness that rebuilds the mock app for every input compared to a harness
+//! that reuses it.
+//!
+//! Run with `cargo bench -p tauri-fuzz --features tauri --bench mock_app`
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::sync::atomic::{AtomicU32, Ordering};
+use tauri::test::{mock_builder, MockRuntime};
+use tauri::Manager;
+use tauri_fuzz::tauri::{
+    create_invoke_request, invoke_command_minimal, CommandArgs, MockAppHarness,
+};
+use tauri_fuzz::Invocation;
+
+#[derive(Default)]
+struct Counter(AtomicU32);
+
+#[tauri::command]
+fn greet(counter: tauri::State<'_, Counter>, name: String) -> String {
+    counter.0.fetch_add(1, Ordering::Relaxed);
+    format!("Hello {name}")
+}
+
+fn setup() -> tauri::Builder<MockRuntime> {
+    mock_builder()
+        .manage(Counter::default())
+        .invoke_handler(tauri::generate_handler![greet])
+}
+
+fn execution(harness: &mut MockAppHarness) {
+    let mut args = CommandArgs::new();
+    args.insert("name", "foo");
+    let request = create_invoke_request(None, "greet", args);
+    invoke_command_minimal(harness.start_execution(), request);
+    // Done by the fuzzer after each execution
+    let _ = Invocation::take_all();
+}
+
+fn mock_app(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mock_app");
+    group.throughput(Throughput::Elements(1));
+
+    // Behaviour of the harnesses written before `MockAppHarness`
+    group.bench_function("rebuild_per_input", |b| {
+        let mut harness = MockAppHarness::builder(setup).reuse_app(false).build();
+        b.iter(|| execution(&mut harness));
+    });
+
+    group.bench_function("reuse", |b| {
+        let mut harness = MockAppHarness::builder(setup).build();
+        b.iter(|| execution(&mut harness));
+    });
+
+    group.bench_function("reuse_with_reset", |b| {
+        let mut harness = MockAppHarness::builder(setup)
+            .reset(|app| app.state::<Counter>().0.store(0, Ordering::Relaxed))
+            .build();
+        b.iter(|| execution(&mut harness));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, mock_app);
+criterion_main!(benches);
@@ -0,0 +1,364 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Behavior profiles recorded in audit mode.
+//!
+//! In audit mode the function listeners don't block the calls that break the policy. Every call
+//! to a monitored function is recorded in the [`BehaviorProfile`] of the command that made it,
+//! with the files it touched, the binaries it spawned and the hosts it contacted.
+//! Profiles are used to write the initial policy of a command and to compare the behavior of
+//! two releases of an app.
+//!
+//! Audit mode is enabled with the [`AUDIT_ENV_VAR`] environment variable. Each fuzzer process
+//! writes its profile in a directory next to the solutions directory, see
+//! [`BehaviorProfile::load`].
+//! When the variable is set to [`AUDIT_ALL_HOOKS`] the policy of the fuzz target is replaced by
+//! one monitoring every known function, to record all the resources accessed by the commands.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri_fuzz_policies::engine::FuzzPolicy;
+pub use tauri_fuzz_policies::fuzz_target::{AUDIT_ALL_HOOKS, AUDIT_ENV_VAR};
+#[cfg(not(target_env = "msvc"))]
+use tauri_fuzz_policies::network;
+use tauri_fuzz_policies::{external_process, filesystem};
+
+use crate::policy_violation::DecodedArgument;
+
+/// Command name used for calls made before any command was invoked
+const UNKNOWN_COMMAND: &str = "<unknown>";
+
+/// Interval at which the profile of the process is saved while fuzzing
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Profile of the current process, whether it changed since it was last saved and when it was
+/// last saved
+static PROFILE: Mutex<(BehaviorProfile, bool, Option<Instant>)> = Mutex::new((
+    BehaviorProfile {
+        commands: BTreeMap::new(),
+    },
+    false,
+    None,
+));
+/// File where the profile is saved when the process exits
+static PROFILE_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Behavior of the fuzzed commands observed in audit mode
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BehaviorProfile {
+    /// Behavior of each command, by command name
+    pub commands: BTreeMap<String, CommandProfile>,
+}
+
+/// Behavior of a command observed in audit mode
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandProfile {
+    /// Number of calls to each monitored function
+    pub calls: BTreeMap<String, u64>,
+    /// Files touched by the command
+    pub files: BTreeSet<String>,
+    /// Binaries spawned by the command
+    pub binaries: BTreeSet<String>,
+    /// Hosts contacted or resolved by the command
+    pub hosts: BTreeSet<String>,
+    /// Descriptions of the policies that would have blocked a call of the command
+    pub policy_violations: BTreeSet<String>,
+}
+
+impl CommandProfile {
+    /// Record a call to `function` with its arguments.
+    /// The resources are read from the arguments as the policies monitoring the function do and
+    /// files are resolved like the path policies resolve them.
+    fn record(&mut self, function: &str, arguments: &[DecodedArgument]) {
+        *self.calls.entry(function.to_string()).or_default() += 1;
+        let registers: Vec<usize> = arguments.iter().map(|a| a.value).collect();
+        self.files
+            .extend(filesystem::accessed_paths(function, &registers));
+        self.binaries
+            .extend(external_process::executed_binary(function, &registers));
+        self.hosts.extend(contacted_hosts(function, &registers));
+    }
+
+    fn merge(&mut self, other: CommandProfile) {
+        for (function, nb_calls) in other.calls {
+            *self.calls.entry(function).or_default() += nb_calls;
+        }
+        self.files.extend(other.files);
+        self.binaries.extend(other.binaries);
+        self.hosts.extend(other.hosts);
+        self.policy_violations.extend(other.policy_violations);
+    }
+
+    /// Behavior of `self` that is not in `baseline`
+    fn new_behaviors(&self, baseline: &CommandProfile) -> CommandProfile {
+        CommandProfile {
+            calls: self
+                .calls
+                .iter()
+                .filter(|(function, _)| !baseline.calls.contains_key(*function))
+                .map(|(function, nb_calls)| (function.clone(), *nb_calls))
+                .collect(),
+            files: self.files.difference(&baseline.files).cloned().collect(),
+            binaries: self
+                .binaries
+                .difference(&baseline.binaries)
+                .cloned()
+                .collect(),
+            hosts: self.hosts.difference(&baseline.hosts).cloned().collect(),
+            policy_violations: self
+                .policy_violations
+                .difference(&baseline.policy_violations)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+            && self.files.is_empty()
+            && self.binaries.is_empty()
+            && self.hosts.is_empty()
+            && self.policy_violations.is_empty()
+    }
+}
+
+impl BehaviorProfile {
+    /// Record a call to a monitored function in the profile of the current command.
+    /// `blocked_by` is the description of the policy that would have blocked the call.
+    pub(crate) fn record_call(
+        function: &str,
+        arguments: &[DecodedArgument],
+        blocked_by: Option<String>,
+    ) {
+        Self::update_current_command(|profile| {
+            profile.record(function, arguments);
+            profile.policy_violations.extend(blocked_by);
+        });
+    }
+
+    /// Record the policy that would have blocked a call when leaving the function
+    pub(crate) fn record_violation(blocked_by: Option<String>) {
+        if let Some(blocked_by) = blocked_by {
+            Self::update_current_command(|profile| {
+                profile.policy_violations.insert(blocked_by);
+            });
+        }
+    }
+
+    fn update_current_command(update: impl FnOnce(&mut CommandProfile)) {
+        let command = tauri_fuzz_policies::engine::current_command()
+            .unwrap_or(UNKNOWN_COMMAND)
+            .to_string();
+        let mut profile = PROFILE.lock().unwrap();
+        update(profile.0.commands.entry(command).or_default());
+        profile.1 = true;
+    }
+
+    /// Save the profile of the current process to `file` if it changed and was not saved for
+    /// [`SAVE_INTERVAL`].
+    /// The profile is also saved to `file` when the process exits normally, the executions since
+    /// the last save are lost if it crashes.
+    pub(crate) fn save_recorded_periodically(file: &Path) -> std::io::Result<()> {
+        if PROFILE_FILE.get().is_none() && PROFILE_FILE.set(file.to_path_buf()).is_ok() {
+            // SAFETY: `save_on_exit` takes no arguments and does not unwind
+            if unsafe { libc::atexit(save_on_exit) } != 0 {
+                log::warn!("The behavior profile won't be saved when the fuzzer exits");
+            }
+        }
+
+        let last_save = PROFILE.lock().unwrap().2;
+        if last_save.is_some_and(|last_save| last_save.elapsed() < SAVE_INTERVAL) {
+            return Ok(());
+        }
+        Self::save_recorded(file)
+    }
+
+    /// Save the profile of the current process to `file` if it changed since the last save
+    fn save_recorded(file: &Path) -> std::io::Result<()> {
+        let mut profile = PROFILE.lock().unwrap();
+        if !profile.1 {
+            return Ok(());
+        }
+        profile.0.save(file)?;
+        profile.1 = false;
+        profile.2 = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Write the profile as JSON
+    pub fn save(&self, file: &Path) -> std::io::Result<()> {
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(file, json)
+    }
+
+    /// Load a profile file, or merge all the profiles contained in a directory
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        if !path.is_dir() {
+            let json = std::fs::read_to_string(path)?;
+            return serde_json::from_str(&json).map_err(std::io::Error::other);
+        }
+        let mut profile = BehaviorProfile::default();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.extension().is_some_and(|ext| ext == "json") {
+                profile.merge(BehaviorProfile::load(&file)?);
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Add the behaviors of `other` to this profile
+    pub fn merge(&mut self, other: BehaviorProfile) {
+        for (command, command_profile) in other.commands {
+            self.commands
+                .entry(command)
+                .or_default()
+                .merge(command_profile);
+        }
+    }
+
+    /// Behaviors of this profile that are not in `baseline`, for example to compare a new
+    /// release of an app with the previous one.
+    /// Commands that behave like in `baseline` are omitted.
+    pub fn new_behaviors(&self, baseline: &BehaviorProfile) -> BehaviorProfile {
+        let empty = CommandProfile::default();
+        let commands = self
+            .commands
+            .iter()
+            .map(|(command, profile)| {
+                let baseline_profile = baseline.commands.get(command).unwrap_or(&empty);
+                (command.clone(), profile.new_behaviors(baseline_profile))
+            })
+            .filter(|(_, profile)| !profile.is_empty())
+            .collect();
+        BehaviorProfile { commands }
+    }
+}
+
+/// Hostname resolved or remote address contacted by a function such as `getaddrinfo` or
+/// `connect`
+#[cfg(not(target_env = "msvc"))]
+fn contacted_hosts(function: &str, registers: &[usize]) -> Option<String> {
+    network::resolved_host(function, registers).or_else(|| {
+        network::contacted_address(function, registers).map(|address| address.to_string())
+    })
+}
+
+#[cfg(target_env = "msvc")]
+fn contacted_hosts(_function: &str, _registers: &[usize]) -> Option<String> {
+    None
+}
+
+/// Save the profile when the process exits, the executions since the last periodic save would
+/// be lost otherwise
+extern "C" fn save_on_exit() {
+    let Some(file) = PROFILE_FILE.get() else {
+        return;
+    };
+    // The process may exit while a hook holds the profile, it is then not saved
+    let Ok(mut profile) = PROFILE.try_lock() else {
+        return;
+    };
+    if profile.1 {
+        if let Err(e) = profile.0.save(file) {
+            log::error!(
+                "Failed to save the behavior profile to {}: {e}",
+                file.display()
+            );
+        }
+        profile.1 = false;
+    }
+}
+
+/// File where the current process records its behavior profile, `None` if the audit mode is
+/// not enabled
+pub(crate) fn audit_profile_file(solutions_dir: &Path) -> Option<PathBuf> {
+    std::env::var_os(AUDIT_ENV_VAR)?;
+    let mut dir_name = solutions_dir
+        .file_name()
+        .map(std::ffi::OsStr::to_os_string)
+        .unwrap_or_default();
+    dir_name.push("_behavior_profiles");
+    let file_name = format!("{}.json", std::process::id());
+    Some(solutions_dir.with_file_name(dir_name).join(file_name))
+}
+
+/// Policy used by the fuzzer, every known hook is attached if [`AUDIT_ENV_VAR`] is set to
+/// [`AUDIT_ALL_HOOKS`]
+pub(crate) fn audited_policy(policy: FuzzPolicy) -> FuzzPolicy {
+    if std::env::var_os(AUDIT_ENV_VAR).is_some_and(|value| value == AUDIT_ALL_HOOKS) {
+        tauri_fuzz_policies::policies::all_known_hooks()
+    } else {
+        policy
+    }
+}
+
+// The resources are read with the policies of Linux and macOS
+#[cfg(all(test, not(target_env = "msvc")))]
+mod test {
+    use super::*;
+    use std::ffi::CString;
+
+    /// Argument pointing to `string`, it's leaked so that it can be read while recording
+    fn string_argument(string: &str) -> DecodedArgument {
+        let string = CString::new(string).unwrap();
+        DecodedArgument {
+            value: string.into_raw() as usize,
+            string: None,
+        }
+    }
+
+    #[test]
+    fn record_resources() {
+        let mut profile = CommandProfile::default();
+        let open_args = [string_argument("/etc/passwd"), string_argument("r")];
+        profile.record("fopen", &open_args);
+        profile.record("fopen", &[]);
+        profile.record("execve", &[string_argument("/usr/bin/curl")]);
+        profile.record("getaddrinfo", &[string_argument("example.com")]);
+
+        assert_eq!(profile.calls["fopen"], 2);
+        // Files are recorded as resolved by the path policies, `/etc` is a symlink on macOS
+        let passwd = std::fs::canonicalize("/etc/passwd").unwrap();
+        assert_eq!(
+            profile.files,
+            BTreeSet::from([passwd.to_string_lossy().into_owned()])
+        );
+        assert_eq!(
+            profile.binaries,
+            BTreeSet::from(["/usr/bin/curl".to_string()])
+        );
+        assert_eq!(profile.hosts, BTreeSet::from(["example.com".to_string()]));
+    }
+
+    #[test]
+    fn merge_and_compare() {
+        let mut old = CommandProfile::default();
+        old.record("open", &[string_argument("/app/foo.txt")]);
+        let mut new = old.clone();
+        new.record("open", &[string_argument("/app/bar.txt")]);
+        new.record("connect", &[]);
+
+        let baseline = BehaviorProfile {
+            commands: BTreeMap::from([("read".to_string(), old.clone())]),
+        };
+        let mut release = BehaviorProfile {
+            commands: BTreeMap::from([("read".to_string(), new)]),
+        };
+        release.merge(baseline.clone());
+        assert_eq!(release.commands["read"].calls["open"], 3);
+
+        let diff = release.new_behaviors(&baseline);
+        let read = &diff.commands["read"];
+        assert_eq!(read.files, BTreeSet::from(["/app/bar.txt".to_string()]));
+        assert_eq!(read.calls.keys().collect::<Vec<_>>(), ["connect"]);
+        assert!(baseline.new_behaviors(&release).commands.is_empty());
+    }
+}
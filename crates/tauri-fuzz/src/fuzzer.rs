@@ -16,13 +16,14 @@ use libafl::{
     events::{launcher::Launcher, llmp::LlmpRestartingEventManager, EventConfig},
     executors::{inprocess::InProcessExecutor, ExitKind, ShadowExecutor},
     feedback_or, feedback_or_fast,
-    feedbacks::{CrashFeedback, MaxMapFeedback, TimeFeedback, TimeoutFeedback},
+    feedbacks::{CrashFeedback, Feedback, MaxMapFeedback, TimeFeedback, TimeoutFeedback},
     fuzzer::{Fuzzer, StdFuzzer},
-    inputs::BytesInput,
+    inputs::{BytesInput, HasTargetBytes, Input},
     monitors::MultiMonitor,
     mutators::{
         scheduled::{havoc_mutations, tokens_mutations, StdScheduledMutator},
         token_mutations::I2SRandReplace,
+        MutatorsTuple,
     },
     observers::{HitcountsMapObserver, StdMapObserver, TimeObserver},
     schedulers::{IndexesLenTimeMinimizerScheduler, QueueScheduler},
@@ -36,7 +37,8 @@ use libafl_bolts::{
     current_nanos,
     rands::StdRand,
     shmem::{ShMemProvider, StdShMemProvider},
-    tuples::{tuple_list, Merge},
+    tuples::{tuple_list, tuple_list_type, Merge, NamedTuple},
+    HasLen,
 };
 
 use libafl_frida::helper::FridaRuntime;
@@ -49,10 +51,27 @@ use libafl_frida::{
 };
 use libafl_targets::cmplog::CmpLogObserver;
 use rangemap::RangeMap;
+use std::path::Path;
 use std::rc::Rc;
+use std::time::Duration;
 use tauri_fuzz_policies::engine::FuzzPolicy;
+use tauri_fuzz_policies::fuzz_target::MINIMIZE_ENV_VAR;
 
+use crate::behavior_profile::{audit_profile_file, audited_policy};
+use crate::json_mutations::{argument_names, json_mutations, JsonInput, JsonSeedsGenerator};
+use crate::policy_violation::{
+    policy_violations_dir, PolicyViolationFeedback, PolicyViolationObserver,
+};
+use crate::report::{install_panic_recorder, SolutionReportFeedback};
 use crate::runtime::FunctionListenerRuntime;
+use crate::sequence::SequenceGenerator;
+
+/// Harnesses waiting for async commands give up after the configured timeout and wait for the
+/// executor to interrupt them, see `tauri::invoke_command_and_wait`. The executor gives them this
+/// margin before interrupting executions that are blocked.
+const HARNESS_TIMEOUT_MARGIN: Duration = Duration::from_millis(500);
+/// Number of inputs generated when starting to fuzz without initial inputs
+const NB_INITIAL_INPUTS: usize = 8;
 
 /// The main fn, usually parsing parameters, and starting the fuzzer
 pub fn fuzz_main<H>(
@@ -64,13 +83,143 @@ pub fn fuzz_main<H>(
 ) where
     H: FnMut(&BytesInput) -> ExitKind,
 {
+    let inputs = InputStrategy {
+        generator: RandPrintablesGenerator::new(32),
+        nb_initial_inputs: NB_INITIAL_INPUTS,
+        mutations: || havoc_mutations().merge(tokens_mutations()),
+        cmplog_mutations: Some(i2s_mutations),
+        minimize_sequences: false,
+    };
+    run(
+        harness,
+        options,
+        tauri_cmd_address,
+        policy,
+        &inputs,
+        as_test,
+    );
+}
+
+/// Start the fuzzer with inputs that are the JSON body of the invoke request.
+/// The inputs are mutated structurally starting from the well-formed `seeds`, see
+/// [`crate::json_seeds`].
+pub fn fuzz_main_json<H>(
+    harness: H,
+    options: &FuzzerOptions,
+    tauri_cmd_address: usize,
+    policy: FuzzPolicy,
+    seeds: Vec<serde_json::Value>,
+    as_test: bool,
+) where
+    H: FnMut(&JsonInput) -> ExitKind,
+{
+    let keys = argument_names(&seeds);
+    let inputs = InputStrategy {
+        nb_initial_inputs: seeds.len().max(1),
+        generator: JsonSeedsGenerator::new(seeds),
+        mutations: || json_mutations(&keys),
+        // The comparisons logged by cmplog are made on the deserialized arguments, replacing
+        // bytes of the JSON text would mostly break its syntax
+        cmplog_mutations: None::<fn()>,
+        minimize_sequences: false,
+    };
+    run(
+        harness,
+        options,
+        tauri_cmd_address,
+        policy,
+        &inputs,
+        as_test,
+    );
+}
+
+/// Start the fuzzer with inputs that encode a sequence of command invocations, see
+/// [`crate::split_sequence`].
+/// When started with [`MINIMIZE_ENV_VAR`], the sequences that violate the policy are minimized to
+/// the invocations needed to reproduce the violation instead.
+pub fn fuzz_main_sequence<H>(
+    harness: H,
+    options: &FuzzerOptions,
+    tauri_cmd_address: usize,
+    policy: FuzzPolicy,
+    as_test: bool,
+) where
+    H: FnMut(&BytesInput) -> ExitKind,
+{
+    let inputs = InputStrategy {
+        // Short sequences of invocations of random commands
+        generator: SequenceGenerator::new(32),
+        nb_initial_inputs: NB_INITIAL_INPUTS,
+        mutations: || havoc_mutations().merge(tokens_mutations()),
+        cmplog_mutations: Some(i2s_mutations),
+        minimize_sequences: true,
+    };
+    run(
+        harness,
+        options,
+        tauri_cmd_address,
+        policy,
+        &inputs,
+        as_test,
+    );
+}
+
+/// How the inputs given to the harness are created and mutated
+pub struct InputStrategy<G, M, C> {
+    /// Generator of the initial inputs when no input corpus is given
+    pub generator: G,
+    /// Number of initial inputs generated
+    pub nb_initial_inputs: usize,
+    /// Creates the mutations of the inputs
+    pub mutations: M,
+    /// Creates the input-to-state mutations using the comparisons logged by cmplog.
+    /// `None` when they don't apply to the inputs, the cmplog stages are skipped.
+    pub cmplog_mutations: Option<C>,
+    /// Whether the inputs are sequences of invocations that can be minimized, see
+    /// [`MINIMIZE_ENV_VAR`]
+    pub minimize_sequences: bool,
+}
+
+/// Input-to-state mutations replacing the bytes compared during the execution
+fn i2s_mutations() -> tuple_list_type!(I2SRandReplace) {
+    tuple_list!(I2SRandReplace::new())
+}
+
+fn run<I, H, G, M, MT, C, CT>(
+    harness: H,
+    options: &FuzzerOptions,
+    tauri_cmd_address: usize,
+    policy: FuzzPolicy,
+    inputs: &InputStrategy<G, M, C>,
+    as_test: bool,
+) where
+    I: Input + HasTargetBytes + HasLen,
+    H: FnMut(&I) -> ExitKind,
+    G: Generator<I, FuzzState<I>> + Clone,
+    M: Fn() -> MT,
+    MT: MutatorsTuple<I, FuzzState<I>> + NamedTuple,
+    C: Fn() -> CT,
+    CT: MutatorsTuple<I, FuzzState<I>> + NamedTuple,
+{
+    let policy = audited_policy(policy);
+    let minimize_path = std::env::var_os(MINIMIZE_ENV_VAR).filter(|_| inputs.minimize_sequences);
     unsafe {
-        let res = if as_test {
-            fuzz_test(harness, options, tauri_cmd_address, policy)
+        let res = if let Some(minimize_path) = minimize_path {
+            crate::sequence::minimize_sequences(Path::new(&minimize_path))
+        } else if let Some(replay_path) = &options.replay {
+            crate::replay::replay(
+                harness,
+                std::path::Path::new(replay_path),
+                tauri_cmd_address,
+                policy,
+                audit_profile_file(&options.output),
+            )
+        } else if as_test {
+            fuzz_test(harness, options, tauri_cmd_address, policy, inputs)
         } else {
             color_backtrace::install();
             env_logger::init();
-            fuzz(harness, options, tauri_cmd_address, policy)
+            fuzz(harness, options, tauri_cmd_address, policy, inputs)
         };
         match res {
             Ok(()) | Err(Error::ShuttingDown) => println!("Finished fuzzing. Good bye."),
@@ -79,16 +228,95 @@ pub fn fuzz_main<H>(
     }
 }
 
+/// State of the fuzzer
+pub type FuzzState<I> = StdState<I, CachedOnDiskCorpus<I>, StdRand, OnDiskCorpus<I>>;
+
+/// Objectives of the fuzzer: policy violations, crashes and timeouts are stored in the
+/// `solutions_dir`, policy violations are also copied in their own directory.
+/// Every solution comes with a report, the report feedback is first to see every execution.
+fn objectives<I>(
+    violation_observer: &PolicyViolationObserver,
+    solutions_dir: &Path,
+) -> impl Feedback<FuzzState<I>>
+where
+    I: Input + HasTargetBytes,
+{
+    let violations_dir = policy_violations_dir(solutions_dir);
+    feedback_or_fast!(
+        SolutionReportFeedback::new(
+            violation_observer,
+            solutions_dir.to_path_buf(),
+            violations_dir.clone()
+        ),
+        PolicyViolationFeedback::new(
+            violation_observer,
+            violations_dir,
+            solutions_dir.to_path_buf()
+        ),
+        CrashFeedback::new(),
+        TimeoutFeedback::new()
+    )
+}
+
+/// Add the initial inputs created by the generator of the strategy to the corpus
+fn generate_initial_inputs<I, G, M, C>(
+    state: &mut FuzzState<I>,
+    inputs: &InputStrategy<G, M, C>,
+) -> Result<(), Error>
+where
+    I: Input,
+    G: Generator<I, FuzzState<I>> + Clone,
+{
+    let mut generator = inputs.generator.clone();
+    for _ in 0..inputs.nb_initial_inputs {
+        let input = generator.generate(state)?;
+        let _idx = state.corpus_mut().add(Testcase::new(input))?;
+    }
+    Ok(())
+}
+
+/// Run the fuzzing stages of the input strategy with `$fuzzer.$run`, either `fuzz_loop` or
+/// `fuzz_one`. The executor is wrapped to trace the comparisons when the strategy has
+/// input-to-state mutations.
+macro_rules! run_stages {
+    ($fuzzer:ident.$run:ident, $executor:ident, $state:ident, $mgr:ident, $inputs:ident) => {{
+        // Setup a mutational stage with the mutations of the inputs
+        let mutational = StdMutationalStage::new(StdScheduledMutator::new(($inputs.mutations)()));
+        if let Some(cmplog_mutations) = &$inputs.cmplog_mutations {
+            // Create an observation channel using cmplog map
+            let cmplog_observer = CmpLogObserver::new("cmplog", true);
+            let mut executor = ShadowExecutor::new($executor, tuple_list!(cmplog_observer));
+            let tracing = ShadowTracingStage::new(&mut executor);
+            // Setup a randomic Input2State stage
+            let i2s = StdMutationalStage::new(StdScheduledMutator::new(cmplog_mutations()));
+            // The order of the stages matter!
+            let mut stages = tuple_list!(tracing, i2s, mutational);
+            $fuzzer.$run(&mut stages, &mut executor, &mut $state, &mut $mgr)?;
+        } else {
+            let mut executor = $executor;
+            let mut stages = tuple_list!(mutational);
+            $fuzzer.$run(&mut stages, &mut executor, &mut $state, &mut $mgr)?;
+        }
+    }};
+}
+
 /// The actual fuzzer
 #[allow(clippy::too_many_lines, clippy::too_many_arguments, dead_code)]
-unsafe fn fuzz<H>(
+unsafe fn fuzz<I, H, G, M, MT, C, CT>(
     mut frida_harness: H,
     options: &FuzzerOptions,
     tauri_cmd_address: usize,
     policy: FuzzPolicy,
+    inputs: &InputStrategy<G, M, C>,
 ) -> Result<(), Error>
 where
-    H: FnMut(&BytesInput) -> ExitKind,
+    I: Input + HasTargetBytes + HasLen,
+    H: FnMut(&I) -> ExitKind,
+    G: Generator<I, FuzzState<I>> + Clone,
+    M: Fn() -> MT,
+    MT: MutatorsTuple<I, FuzzState<I>> + NamedTuple,
+    C: Fn() -> CT,
+    CT: MutatorsTuple<I, FuzzState<I>> + NamedTuple,
 {
     // 'While the stats are state, they are usually used in the broker - which is likely never restarted
     let monitor = MultiMonitor::new(|s| println!("{s}"));
@@ -101,9 +329,16 @@ where
         (|state: Option<_>, mut mgr: LlmpRestartingEventManager<_, _, _>, _core_id| {
             let gum = Gum::obtain();
 
+            // Record panics for the solution reports
+            install_panic_recorder();
+
             // Our function listener runtime
-            let mut function_listener_rt =
-                FunctionListenerRuntime::new(policy.clone(), tauri_cmd_address).unwrap();
+            let mut function_listener_rt = FunctionListenerRuntime::new(
+                policy.clone(),
+                tauri_cmd_address,
+                audit_profile_file(&options.output),
+            )
+            .unwrap();
             // We init it manually because it may be skipped by libafl_frida if Frida stalker is not enabled
             function_listener_rt.init(&gum, &RangeMap::default(), &Rc::new(ModuleMap::new(&gum)));
 
@@ -129,6 +364,9 @@ where
             // Create an observation channel to keep track of the execution time
             let time_observer = TimeObserver::new("time");
 
+            // Create an observation channel to keep track of the policy violations
+            let violation_observer = PolicyViolationObserver::new("policy_violation");
+
             // Feedback to rate the interestingness of an input
             // This one is composed by two Feedbacks in OR
             let mut feedback = feedback_or!(
@@ -140,7 +378,7 @@ where
                 TimeFeedback::new(&time_observer)
             );
 
-            let mut objective = feedback_or_fast!(CrashFeedback::new(), TimeoutFeedback::new());
+            let mut objective = objectives(&violation_observer, &options.output);
 
             // If not restarting, create a State from scratch
             let mut corpus_path = options.output.clone();
@@ -161,9 +399,6 @@ where
                 .unwrap()
             });
 
-            // Setup a basic mutator with a mutational stage
-            let mutator = StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations()));
-
             // A minimization+queue policy to get testcasess from the corpus
             let scheduler =
                 IndexesLenTimeMinimizerScheduler::new(&edges_observer, QueueScheduler::new());
@@ -171,17 +406,18 @@ where
             // A fuzzer with feedbacks and a corpus scheduler
             let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
 
-            let observers = tuple_list!(edges_observer, time_observer,);
+            let observers = tuple_list!(edges_observer, time_observer, violation_observer);
 
             // Create the executor for an in-process function with just one observer for edge coverage
             let mut executor = FridaInProcessExecutor::new(
                 &gum,
-                InProcessExecutor::new(
+                InProcessExecutor::with_timeout(
                     &mut frida_harness,
                     observers,
                     &mut fuzzer,
                     &mut state,
                     &mut mgr,
+                    options.timeout + HARNESS_TIMEOUT_MARGIN,
                 )?,
                 &mut frida_helper,
             );
@@ -189,25 +425,7 @@ where
             // In case the corpus is empty (on first run), reset
             if state.must_load_initial_inputs() {
                 if options.input.is_empty() {
-                    let mut generator = RandPrintablesGenerator::new(32);
-                    let nb_initial_inputs = 8;
-                    for _ in 0..nb_initial_inputs {
-                        let input = generator
-                            .generate(&mut state)
-                            .expect("Failed to generate random input");
-                        // let testcase = Testcase::<BytesInput>::new(input);
-                        let testcase = Testcase::new(input);
-                        let _idx = state.corpus_mut().add(testcase)?;
-                    }
-
-                // // Force execution of the fuzzer with generated inputs to have initial corpus
-                // let _ = state.generate_initial_inputs_forced(
-                //     &mut fuzzer,
-                //     &mut executor,
-                //     &mut generator,
-                //     &mut mgr,
-                //     8,
-                // );
+                    generate_initial_inputs(&mut state, inputs)?;
                 } else {
                     state
                         .load_initial_inputs(&mut fuzzer, &mut executor, &mut mgr, &options.input)
@@ -218,25 +436,7 @@ where
                 }
             }
 
-            // Create an observation channel using cmplog map
-            let cmplog_observer = CmpLogObserver::new("cmplog", true);
-
-            let mut executor = ShadowExecutor::new(executor, tuple_list!(cmplog_observer));
-
-            let tracing = ShadowTracingStage::new(&mut executor);
-
-            // Setup a randomic Input2State stage
-            let i2s = StdMutationalStage::new(StdScheduledMutator::new(tuple_list!(
-                I2SRandReplace::new()
-            )));
-
-            // Setup a basic mutator
-            let mutational = StdMutationalStage::new(mutator);
-
-            // The order of the stages matter!
-            let mut stages = tuple_list!(tracing, i2s, mutational);
-
-            fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)?;
+            run_stages!(fuzzer.fuzz_loop, executor, state, mgr, inputs);
 
             Ok(())
         })(state, mgr, core_id)
@@ -265,23 +465,36 @@ where
 /// `frida_gum::Gum::obtain()` is unsafe but the docs does not specify the safety conditions so I
 /// don't really know
 #[allow(dead_code)]
-pub unsafe fn fuzz_test<H>(
+pub unsafe fn fuzz_test<I, H, G, M, MT, C, CT>(
     mut frida_harness: H,
     options: &FuzzerOptions,
     tauri_cmd_address: usize,
     policy: FuzzPolicy,
+    inputs: &InputStrategy<G, M, C>,
 ) -> Result<(), Error>
 where
-    H: FnMut(&BytesInput) -> ExitKind,
+    I: Input + HasTargetBytes + HasLen,
+    H: FnMut(&I) -> ExitKind,
+    G: Generator<I, FuzzState<I>> + Clone,
+    M: Fn() -> MT,
+    MT: MutatorsTuple<I, FuzzState<I>> + NamedTuple,
+    C: Fn() -> CT,
+    CT: MutatorsTuple<I, FuzzState<I>> + NamedTuple,
 {
     let monitor = MultiMonitor::new(|s| println!("{s}"));
     let mut mgr = libafl::events::simple::SimpleEventManager::new(monitor);
 
     let gum = Gum::obtain();
+    // Record panics for the solution reports
+    install_panic_recorder();
     let coverage = CoverageRuntime::new();
     let cmplog = CmpLogRuntime::new();
-    let mut function_listener_rt =
-        FunctionListenerRuntime::new(policy.clone(), tauri_cmd_address).unwrap();
+    let mut function_listener_rt = FunctionListenerRuntime::new(
+        policy.clone(),
+        tauri_cmd_address,
+        audit_profile_file(&options.output),
+    )
+    .unwrap();
     // We init it manually because it may be skipped by libafl_frida if Frida stalker is not enabled
     function_listener_rt.init(&gum, &RangeMap::default(), &Rc::new(ModuleMap::new(&gum)));
 
@@ -304,6 +517,9 @@ where
     // Create an observation channel to keep track of the execution time
     let time_observer = TimeObserver::new("time");
 
+    // Create an observation channel to keep track of the policy violations
+    let violation_observer = PolicyViolationObserver::new("policy_violation");
+
     // Feedback to rate the interestingness of an input
     // This one is composed by two Feedbacks in OR
     let mut feedback = feedback_or!(
@@ -315,7 +531,7 @@ where
         TimeFeedback::new(&time_observer)
     );
 
-    let mut objective = feedback_or_fast!(CrashFeedback::new(), TimeoutFeedback::new());
+    let mut objective = objectives(&violation_observer, &options.output);
 
     // If not restarting, create a State from scratch
     let mut corpus_path = options.output.clone();
@@ -334,26 +550,24 @@ where
     )
     .unwrap();
 
-    // Setup a basic mutator with a mutational stage
-    let mutator = StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations()));
-
     // A minimization+queue policy to get testcasess from the corpus
     let scheduler = IndexesLenTimeMinimizerScheduler::new(&edges_observer, QueueScheduler::new());
 
     // A fuzzer with feedbacks and a corpus scheduler
     let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
 
-    let observers = tuple_list!(edges_observer, time_observer,);
+    let observers = tuple_list!(edges_observer, time_observer, violation_observer);
 
     // Create the executor for an in-process function with just one observer for edge coverage
     let mut executor = FridaInProcessExecutor::new(
         &gum,
-        InProcessExecutor::new(
+        InProcessExecutor::with_timeout(
             &mut frida_harness,
             observers,
             &mut fuzzer,
             &mut state,
             &mut mgr,
+            options.timeout + HARNESS_TIMEOUT_MARGIN,
         )?,
         &mut frida_helper,
     );
@@ -361,25 +575,7 @@ where
     // In case the corpus is empty (on first run)
     if state.must_load_initial_inputs() {
         if options.input.is_empty() {
-            let mut generator = RandPrintablesGenerator::new(32);
-            let nb_initial_inputs = 1;
-            for _ in 0..nb_initial_inputs {
-                let input = generator
-                    .generate(&mut state)
-                    .expect("Failed to generate random input");
-                // let testcase = Testcase::<BytesInput>::new(input);
-                let testcase = Testcase::new(input);
-                let _idx = state.corpus_mut().add(testcase)?;
-            }
-
-            // // Force execution of the fuzzer with generated inputs to have initial corpus
-            // let _ = state.generate_initial_inputs_forced(
-            //     &mut fuzzer,
-            //     &mut executor,
-            //     &mut generator,
-            //     &mut mgr,
-            //     8,
-            // );
+            generate_initial_inputs(&mut state, inputs)?;
         } else {
             state
                 .load_initial_inputs(&mut fuzzer, &mut executor, &mut mgr, &options.input)
@@ -390,24 +586,7 @@ where
         }
     }
 
-    // Create an observation channel using cmplog map
-    let cmplog_observer = CmpLogObserver::new("cmplog", true);
-
-    let mut executor = ShadowExecutor::new(executor, tuple_list!(cmplog_observer));
-
-    let tracing = ShadowTracingStage::new(&mut executor);
-
-    // Setup a randomic Input2State stage
-    let i2s = StdMutationalStage::new(StdScheduledMutator::new(tuple_list!(I2SRandReplace::new())));
-
-    // Setup a basic mutator
-    let mutational = StdMutationalStage::new(mutator);
-
-    // The order of the stages matter!
-    let mut stages = tuple_list!(tracing, i2s, mutational);
-
-    // fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)?;
-    fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)?;
+    run_stages!(fuzzer.fuzz_one, executor, state, mgr, inputs);
 
     Ok(())
 }
@@ -5,6 +5,20 @@ use libafl_bolts::bolts_prelude::Cores;
 use libafl_bolts::cli::FuzzerOptions;
 use serde::Deserialize;
 use std::path::PathBuf;
+use std::time::Duration;
+use tauri_fuzz_policies::engine::{load_policy_file, FuzzPolicy, PolicyFileError};
+pub use tauri_fuzz_policies::fuzz_target::REPLAY_ENV_VAR;
+
+/// How the fuzzed input is turned into the arguments of the Tauri command
+#[derive(Deserialize, Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    /// The input bytes are decoded into typed arguments with `FromRandomBytes`
+    #[default]
+    Bytes,
+    /// The input is the raw JSON body of the invoke request and is mutated structurally
+    Json,
+}
 
 /// A simplified configuration for the fuzzer
 #[derive(Deserialize, Eq, PartialEq, Debug)]
@@ -21,6 +35,19 @@ pub struct SimpleFuzzerConfig {
     with_coverage: bool,
     /// Port used by the fuzzer broker
     broker_port: u16,
+    /// Policy file used for fuzzing, relative to the fuzz directory
+    #[serde(default)]
+    policy_file: Option<PathBuf>,
+    /// How the fuzzed input is turned into the command arguments
+    #[serde(default)]
+    input_mode: InputMode,
+    /// Maximum duration of an execution in milliseconds, slower executions are timeouts
+    #[serde(default = "default_timeout_ms")]
+    timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
 }
 
 /// A simplified configuration to convert into LibAFL fuzzer configuration
@@ -34,6 +61,9 @@ impl SimpleFuzzerConfig {
     /// corpus_input = []
     /// with_coverage = true
     /// broker_port = 8888
+    /// policy_file = 'policy.toml' # optional
+    /// input_mode = 'json' # optional
+    /// timeout_ms = 5000 # optional
     ///
     pub fn from_toml(toml_file: PathBuf, command_name: &str, fuzz_dir: PathBuf) -> Self {
         let mut solutions_dir = fuzz_dir.clone();
@@ -45,8 +75,49 @@ impl SimpleFuzzerConfig {
         let mut config: SimpleFuzzerConfig = toml::from_str(&data)
             .unwrap_or_else(|_| panic!("Failed to deserialize {toml_file:#?}"));
         config.solutions_dir = solutions_dir;
+        config.policy_file = config.policy_file.map(|file| fuzz_dir.join(file));
         config
     }
+
+    /// Load the policy from the policy file specified in the configuration, if any
+    ///
+    /// # Errors
+    /// Fails if the policy file can't be loaded
+    pub fn load_policy(&self) -> Result<Option<FuzzPolicy>, PolicyFileError> {
+        self.policy_file.as_ref().map(load_policy_file).transpose()
+    }
+
+    /// Policy used to fuzz.
+    /// A policy file given in the configuration replaces the policy of the fuzz target, which is
+    /// then not created at all. The replacement is reported since the fuzz target code does not
+    /// show it.
+    ///
+    /// # Errors
+    /// Fails if the policy can't be loaded
+    pub fn policy_or(
+        &self,
+        target_policy: impl FnOnce() -> Result<FuzzPolicy, PolicyFileError>,
+    ) -> Result<FuzzPolicy, PolicyFileError> {
+        let Some(file) = &self.policy_file else {
+            return target_policy();
+        };
+        let policy = load_policy_file(file)?;
+        eprintln!(
+            "Using the policy file {file:?} of the fuzzer configuration instead of the policy of \
+             the fuzz target"
+        );
+        Ok(policy)
+    }
+
+    /// How the fuzzed input is turned into the command arguments
+    pub fn input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+
+    /// Maximum duration of an execution
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
 }
 
 impl From<SimpleFuzzerConfig> for FuzzerOptions {
@@ -59,6 +130,8 @@ impl From<SimpleFuzzerConfig> for FuzzerOptions {
             cores: Cores::from_cmdline(&format!("1-{}", simple.nb_of_cores)).unwrap(),
             broker_port: simple.broker_port,
             remote_broker_addr: None,
+            // Executions longer than this are reported as timeouts
+            timeout: Duration::from_millis(simple.timeout_ms),
 
             // Settings for Frida stalker
             harness: std::env::current_exe().ok(),
@@ -91,11 +164,11 @@ impl From<SimpleFuzzerConfig> for FuzzerOptions {
             iterations: 0,
             configuration: String::from("default configuration"),
             verbose: true,
-            timeout: std::time::Duration::from_secs(0),
             harness_function: String::new(),
             harness_args: vec![],
             tokens: vec![], // Certainly for input mutation
-            replay: None,
+            // Inputs to replay instead of fuzzing
+            replay: std::env::var(REPLAY_ENV_VAR).ok(),
             repeat: None,
         }
     }
@@ -117,8 +190,25 @@ mod tests {
                 nb_of_cores: 1,
                 corpus_input: vec![],
                 with_coverage: true,
-                broker_port: 8888
+                broker_port: 8888,
+                policy_file: None,
+                input_mode: InputMode::Bytes,
+                timeout_ms: 5000,
             }
         );
     }
+
+    #[test]
+    fn policy_file_replaces_target_policy() {
+        let toml_config = ["configuration", "toml_template.toml"].iter().collect();
+        let mut config = SimpleFuzzerConfig::from_toml(toml_config, "foo", PathBuf::new());
+        assert!(config.policy_or(|| Ok(vec![])).unwrap().is_empty());
+
+        // The target policy is not used when the policy file can't be loaded
+        config.policy_file = Some(PathBuf::from("missing_policy.toml"));
+        assert!(matches!(
+            config.policy_or(|| Ok(vec![])),
+            Err(PolicyFileError::Io(_))
+        ));
+    }
 }
@@ -0,0 +1,210 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Report of the hooks attached by the function listener runtime.
+//!
+//! The report lists for each function policy where its hooks were attached, or why they could
+//! not be, so that a fuzz target running without its policy is noticed.
+//! The fuzzer does not start when a function that is not optional can't be hooked. Optional
+//! functions that can't be found are not monitored, with the [`STRICT_HOOKS_ENV_VAR`]
+//! environment variable set the fuzzer also aborts in this case.
+
+use std::fmt::{Display, Formatter};
+
+use libafl::Error;
+use serde::{Deserialize, Serialize};
+pub use tauri_fuzz_policies::fuzz_target::STRICT_HOOKS_ENV_VAR;
+
+/// Outcome of attaching a hook on a function of the policy
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStatus {
+    /// The function was found and is monitored
+    Attached,
+    /// The library of the function is not loaded in the process
+    ModuleNotFound,
+    /// The library was found but not the function
+    SymbolNotFound,
+}
+
+impl Display for HookStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            HookStatus::Attached => "attached",
+            HookStatus::ModuleNotFound => "module not found",
+            HookStatus::SymbolNotFound => "symbol not found",
+        };
+        f.write_str(status)
+    }
+}
+
+/// Hook of one of the functions of the policy.
+/// A Rust function may have a hook per instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HookAttachment {
+    /// Name of the function in the policy
+    pub function: String,
+    /// Library of the function in the policy
+    pub lib: String,
+    /// Name of the loaded module matching the library
+    pub module: Option<String>,
+    /// Symbol the function was resolved to, demangled for Rust functions
+    pub symbol: Option<String>,
+    /// Address where the hook is attached
+    pub address: Option<usize>,
+    pub status: HookStatus,
+    /// If the function may be missing, see [`FunctionPolicy::is_optional`]
+    ///
+    /// [`FunctionPolicy::is_optional`]: tauri_fuzz_policies::engine::FunctionPolicy::is_optional
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl HookAttachment {
+    pub fn is_attached(&self) -> bool {
+        self.status == HookStatus::Attached
+    }
+}
+
+/// Hooks attached for a policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HookReport {
+    pub hooks: Vec<HookAttachment>,
+}
+
+impl HookReport {
+    /// Hooks that are attached
+    pub fn attached(&self) -> impl Iterator<Item = &HookAttachment> {
+        self.hooks.iter().filter(|hook| hook.is_attached())
+    }
+
+    /// Hooks that failed to attach
+    pub fn failed(&self) -> impl Iterator<Item = &HookAttachment> {
+        self.hooks.iter().filter(|hook| !hook.is_attached())
+    }
+
+    /// Check if every function of the policy is monitored
+    pub fn all_attached(&self) -> bool {
+        self.failed().next().is_none()
+    }
+
+    /// Check if `function` is monitored by at least one hook
+    pub fn is_attached(&self, function: &str) -> bool {
+        self.attached().any(|hook| hook.function == function)
+    }
+
+    /// Hooks of functions that are not optional and failed to attach
+    pub fn failed_required(&self) -> impl Iterator<Item = &HookAttachment> {
+        self.failed().filter(|hook| !hook.optional)
+    }
+
+    /// Fail if a function that is not optional could not be hooked
+    pub(crate) fn check_required_hooks(&self) -> Result<(), Error> {
+        let failed = describe(self.failed_required());
+        if failed.is_empty() {
+            return Ok(());
+        }
+        Err(Error::illegal_state(format!(
+            "Some functions monitored by the policy could not be hooked, the policy would not be \
+            enforced: {failed:?}"
+        )))
+    }
+
+    /// Fail if a hook failed to attach and the strict mode is enabled
+    pub(crate) fn check_strict_mode(&self) -> Result<(), Error> {
+        if std::env::var_os(STRICT_HOOKS_ENV_VAR).is_none() || self.all_attached() {
+            return Ok(());
+        }
+        let failed = describe(self.failed());
+        Err(Error::illegal_state(format!(
+            "Strict mode is enabled with {STRICT_HOOKS_ENV_VAR} and some hooks failed to attach: \
+            {failed:?}"
+        )))
+    }
+}
+
+fn describe<'a>(hooks: impl Iterator<Item = &'a HookAttachment>) -> Vec<String> {
+    hooks
+        .map(|hook| format!("{} ({}): {}", hook.function, hook.lib, hook.status))
+        .collect()
+}
+
+impl Display for HookReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Hook attachment report: {} attached, {} failed",
+            self.attached().count(),
+            self.failed().count()
+        )?;
+        for hook in &self.hooks {
+            write!(f, "  [{}] {}", hook.status, hook.function)?;
+            if hook.optional && !hook.is_attached() {
+                write!(f, " (optional)")?;
+            }
+            match &hook.module {
+                Some(module) => write!(f, " in {module}")?,
+                None => write!(f, " in {}", hook.lib)?,
+            }
+            if let Some(symbol) = &hook.symbol {
+                write!(f, " -> {symbol}")?;
+            }
+            if let Some(address) = hook.address {
+                write!(f, " @ {address:#x}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(function: &str, status: HookStatus, optional: bool) -> HookAttachment {
+        let attached = status == HookStatus::Attached;
+        HookAttachment {
+            function: function.into(),
+            lib: "libc.".into(),
+            module: (status != HookStatus::ModuleNotFound).then(|| "libc.so.6".into()),
+            symbol: attached.then(|| function.into()),
+            address: attached.then_some(0x1000),
+            status,
+            optional,
+        }
+    }
+
+    #[test]
+    fn report_lists_failed_hooks() {
+        let report = HookReport {
+            hooks: vec![
+                hook("open", HookStatus::Attached, false),
+                hook("renameat2", HookStatus::SymbolNotFound, true),
+            ],
+        };
+        assert!(!report.all_attached());
+        assert!(report.check_required_hooks().is_ok());
+        assert!(report.is_attached("open"));
+        assert!(!report.is_attached("renameat2"));
+
+        let printed = report.to_string();
+        assert!(printed.contains("1 attached, 1 failed"));
+        assert!(printed.contains("[attached] open in libc.so.6 -> open @ 0x1000"));
+        assert!(printed.contains("[symbol not found] renameat2 (optional) in libc.so.6\n"));
+    }
+
+    #[test]
+    fn missing_required_hooks_are_errors() {
+        let report = HookReport {
+            hooks: vec![
+                hook("open", HookStatus::Attached, false),
+                hook("openat", HookStatus::SymbolNotFound, false),
+                hook("renameat2", HookStatus::SymbolNotFound, true),
+            ],
+        };
+        let failed: Vec<_> = report.failed_required().map(|h| &h.function).collect();
+        assert_eq!(failed, ["openat"]);
+        assert!(report.check_required_hooks().is_err());
+    }
+}
@@ -0,0 +1,632 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Structure-aware mutations of the JSON body sent to a Tauri command.
+//!
+//! In JSON mode the fuzzed input is a [`JsonInput`], the JSON body of the `InvokeRequest` as a
+//! malicious webview could send it. It is stored as JSON text on disk so that the solutions can be
+//! read and replayed like the inputs of the other modes.
+//! The mutators mutate the JSON tree. They produce bodies that a regular frontend would never
+//! send: wrong types, missing or extra keys, huge numbers and deeply nested values.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use libafl::{
+    generators::Generator,
+    inputs::{HasTargetBytes, Input},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+use libafl_bolts::{
+    fs::write_file_atomic,
+    ownedref::OwnedSlice,
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    HasLen, Named,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+/// Maximum depth of the values generated by the mutators
+const MAX_GENERATED_DEPTH: usize = 3;
+/// Maximum number of nested arrays and objects in a JSON body.
+/// Deeper bodies are rejected by the recursion limit of `serde_json` before reaching the command.
+const MAX_DEPTH: usize = 127;
+/// Maximum number of copies of an array element made by a single mutation
+const MAX_ARRAY_COPIES: usize = 64;
+
+/// Strings that often trigger bugs in commands handling paths, urls, queries or shell commands
+const INTERESTING_STRINGS: [&str; 14] = [
+    "",
+    "../../../../../../etc/passwd",
+    "/etc/passwd",
+    "C:\\Windows\\System32\\drivers\\etc\\hosts",
+    "file:///etc/passwd",
+    "http://127.0.0.1:1/",
+    "' OR '1'='1' --",
+    "; ls -la",
+    "$(id)",
+    "%s%s%s%n",
+    "\0",
+    "\u{202e}\u{feff}",
+    "🦀🦀🦀",
+    "{\"__proto__\": {}}",
+];
+
+/// Input containing a JSON body.
+/// It is serialized as JSON text, the format of serde_json values can't be read back by the
+/// binary formats used by LibAFL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct JsonInput {
+    value: Value,
+}
+
+impl JsonInput {
+    /// Create an input containing the JSON body `value`
+    pub fn new(value: Value) -> Self {
+        JsonInput { value }
+    }
+
+    /// The JSON body
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// The JSON body as text, as sent by a webview
+    pub fn to_json(&self) -> String {
+        self.value.to_string()
+    }
+}
+
+impl From<JsonInput> for String {
+    fn from(input: JsonInput) -> Self {
+        input.to_json()
+    }
+}
+
+impl TryFrom<String> for JsonInput {
+    type Error = serde_json::Error;
+
+    fn try_from(json: String) -> Result<Self, Self::Error> {
+        serde_json::from_str(&json).map(JsonInput::new)
+    }
+}
+
+impl Input for JsonInput {
+    fn to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_file_atomic(path, self.to_json().as_bytes())
+    }
+
+    fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = std::fs::read(path)?;
+        serde_json::from_slice(&bytes)
+            .map(JsonInput::new)
+            .map_err(|e| Error::serialize(format!("Input is not valid JSON: {e}")))
+    }
+
+    fn generate_name(&self, _idx: usize) -> String {
+        format!("{:016x}", libafl_bolts::hash_std(self.to_json().as_bytes()))
+    }
+}
+
+impl HasTargetBytes for JsonInput {
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(self.to_json().into_bytes())
+    }
+}
+
+impl HasLen for JsonInput {
+    fn len(&self) -> usize {
+        self.to_json().len()
+    }
+}
+
+/// Generator of the initial inputs of the JSON mode, it cycles through the seeds
+#[derive(Debug, Clone)]
+pub struct JsonSeedsGenerator {
+    seeds: Vec<Value>,
+    next: usize,
+}
+
+impl JsonSeedsGenerator {
+    /// Generate the well-formed `seeds`, an empty object when there are none
+    pub fn new(seeds: Vec<Value>) -> Self {
+        JsonSeedsGenerator { seeds, next: 0 }
+    }
+}
+
+impl<S> Generator<JsonInput, S> for JsonSeedsGenerator {
+    fn generate(&mut self, _state: &mut S) -> Result<JsonInput, Error> {
+        let Some(seed) = self.seeds.get(self.next % self.seeds.len().max(1)) else {
+            return Ok(JsonInput::new(Value::Object(Map::new())));
+        };
+        self.next += 1;
+        Ok(JsonInput::new(seed.clone()))
+    }
+}
+
+/// Seeds of the JSON mode: the well-formed bodies built from a few byte patterns.
+/// `create_body` returns `None` when the bytes are too short to build a body.
+pub fn json_seeds(create_body: impl Fn(&[u8]) -> Option<Value>) -> Vec<Value> {
+    [&[][..], &[0u8; 64][..], &[b'A'; 64][..], &[0xffu8; 64][..]]
+        .into_iter()
+        .filter_map(create_body)
+        .collect()
+}
+
+/// Names of the arguments of a command, taken from the keys of the JSON seeds
+pub fn argument_names(seeds: &[Value]) -> Vec<String> {
+    let mut names: Vec<String> = seeds
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(Map::keys)
+        .cloned()
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Random number in `0..n`
+pub(crate) fn below<R: Rand>(rand: &mut R, n: usize) -> usize {
+    // Truncation and modulo bias don't matter for fuzzing
+    #[allow(clippy::cast_possible_truncation)]
+    let random = rand.next() as usize;
+    random % n
+}
+
+/// Number of nodes of the JSON tree
+fn nb_nodes(value: &Value) -> usize {
+    1 + match value {
+        Value::Array(array) => array.iter().map(nb_nodes).sum(),
+        Value::Object(object) => object.values().map(nb_nodes).sum(),
+        _ => 0,
+    }
+}
+
+/// The `n`-th node of the JSON tree in pre-order
+fn nth_node_mut(value: &mut Value, mut n: usize) -> Option<&mut Value> {
+    if n == 0 {
+        return Some(value);
+    }
+    n -= 1;
+    let children: Box<dyn Iterator<Item = &mut Value>> = match value {
+        Value::Array(array) => Box::new(array.iter_mut()),
+        Value::Object(object) => Box::new(object.values_mut()),
+        _ => return None,
+    };
+    for child in children {
+        let size = nb_nodes(child);
+        if n < size {
+            return nth_node_mut(child, n);
+        }
+        n -= size;
+    }
+    None
+}
+
+/// Number of nested arrays and objects of the JSON tree
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Array(array) => 1 + array.iter().map(depth).max().unwrap_or_default(),
+        Value::Object(object) => 1 + object.values().map(depth).max().unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+/// Pre-order indexes of the nodes matching `predicate`
+fn matching_nodes(value: &Value, predicate: fn(&Value) -> bool) -> Vec<usize> {
+    fn visit(
+        value: &Value,
+        predicate: fn(&Value) -> bool,
+        index: &mut usize,
+        out: &mut Vec<usize>,
+    ) {
+        if predicate(value) {
+            out.push(*index);
+        }
+        *index += 1;
+        match value {
+            Value::Array(array) => array.iter().for_each(|v| visit(v, predicate, index, out)),
+            Value::Object(object) => object
+                .values()
+                .for_each(|v| visit(v, predicate, index, out)),
+            _ => {}
+        }
+    }
+    let mut nodes = vec![];
+    visit(value, predicate, &mut 0, &mut nodes);
+    nodes
+}
+
+/// Choose a random node of the JSON tree matching `predicate`
+fn choose_node<'a, R: Rand>(
+    rand: &mut R,
+    value: &'a mut Value,
+    predicate: fn(&Value) -> bool,
+) -> Option<&'a mut Value> {
+    let nodes = matching_nodes(value, predicate);
+    if nodes.is_empty() {
+        return None;
+    }
+    let node = nodes[below(rand, nodes.len())];
+    nth_node_mut(value, node)
+}
+
+fn interesting_number<R: Rand>(rand: &mut R) -> Value {
+    #[allow(clippy::cast_precision_loss)]
+    let float = match below(rand, 6) {
+        0 => f64::MAX,
+        1 => f64::MIN,
+        2 => f64::MIN_POSITIVE,
+        3 => f64::EPSILON,
+        4 => -0.0,
+        _ => rand.next() as f64 / 3.0,
+    };
+    match below(rand, 12) {
+        0 => Value::from(0),
+        1 => Value::from(-1),
+        2 => Value::from(i64::MIN),
+        3 => Value::from(i64::MAX),
+        4 => Value::from(u64::MAX),
+        5 => Value::from(i64::from(i32::MIN) - 1),
+        6 => Value::from(u64::from(u32::MAX) + 1),
+        // Biggest integer that can't be represented in JavaScript
+        7 => Value::from(9_007_199_254_740_993_u64),
+        8 => Value::from(rand.next()),
+        _ => Number::from_f64(float).map_or(Value::Null, Value::Number),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn random_string<R: Rand>(rand: &mut R) -> String {
+    let len = below(rand, 16);
+    (0..len)
+        .map(|_| match below(rand, 8) {
+            // Any unicode character
+            0 => char::from_u32(below(rand, 0x11_0000) as u32).unwrap_or('\u{fffd}'),
+            // Printable ascii
+            _ => char::from(b' ' + below(rand, 95) as u8),
+        })
+        .collect()
+}
+
+/// Key of an object, either one of the expected argument names or a random one
+fn random_key<R: Rand>(rand: &mut R, keys: &[String]) -> String {
+    if !keys.is_empty() && below(rand, 2) == 0 {
+        keys[below(rand, keys.len())].clone()
+    } else {
+        random_string(rand)
+    }
+}
+
+/// Generate a random JSON value with at most `max_depth` levels of nesting
+fn random_value<R: Rand>(rand: &mut R, keys: &[String], max_depth: usize) -> Value {
+    let nb_kinds = if max_depth == 0 { 5 } else { 7 };
+    match below(rand, nb_kinds) {
+        0 => Value::Null,
+        1 => Value::Bool(below(rand, 2) == 0),
+        2 => interesting_number(rand),
+        3 => Value::String(random_string(rand)),
+        4 => Value::String(INTERESTING_STRINGS[below(rand, INTERESTING_STRINGS.len())].into()),
+        5 => Value::Array(
+            (0..below(rand, 4))
+                .map(|_| random_value(rand, keys, max_depth - 1))
+                .collect(),
+        ),
+        _ => Value::Object(
+            (0..below(rand, 4))
+                .map(|_| {
+                    (
+                        random_key(rand, keys),
+                        random_value(rand, keys, max_depth - 1),
+                    )
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Replace a node by a random value, usually of another type
+fn replace_value<R: Rand>(rand: &mut R, value: &mut Value, keys: &[String]) -> bool {
+    let new_value = random_value(rand, keys, MAX_GENERATED_DEPTH);
+    let Some(node) = choose_node(rand, value, |_| true) else {
+        return false;
+    };
+    *node = new_value;
+    true
+}
+
+/// Remove a key from an object, the command may expect it
+fn remove_key<R: Rand>(rand: &mut R, value: &mut Value, _keys: &[String]) -> bool {
+    let Some(Value::Object(object)) = choose_node(rand, value, |v| {
+        v.as_object().is_some_and(|o| !o.is_empty())
+    }) else {
+        return false;
+    };
+    let key = object.keys().nth(below(rand, object.len())).cloned();
+    key.and_then(|key| object.remove(&key)).is_some()
+}
+
+/// Add a key to an object, this also restores argument keys that were removed
+fn add_key<R: Rand>(rand: &mut R, value: &mut Value, keys: &[String]) -> bool {
+    let key = random_key(rand, keys);
+    let new_value = random_value(rand, keys, MAX_GENERATED_DEPTH);
+    let Some(Value::Object(object)) = choose_node(rand, value, Value::is_object) else {
+        return false;
+    };
+    object.insert(key, new_value);
+    true
+}
+
+/// Wrap a node in many levels of arrays or objects
+fn nest_value<R: Rand>(rand: &mut R, value: &mut Value, keys: &[String]) -> bool {
+    let max_nesting = MAX_DEPTH.saturating_sub(depth(value));
+    if max_nesting == 0 {
+        return false;
+    }
+    let nesting = 1 + below(rand, max_nesting);
+    let in_object = below(rand, 2) == 0;
+    let key = random_key(rand, keys);
+    let Some(node) = choose_node(rand, value, |_| true) else {
+        return false;
+    };
+    for _ in 0..nesting {
+        let nested = node.take();
+        *node = if in_object {
+            Value::Object(Map::from_iter([(key.clone(), nested)]))
+        } else {
+            Value::Array(vec![nested])
+        };
+    }
+    true
+}
+
+/// Remove an element of an array or make copies of it
+fn mutate_array<R: Rand>(rand: &mut R, value: &mut Value, _keys: &[String]) -> bool {
+    let Some(Value::Array(array)) =
+        choose_node(rand, value, |v| v.as_array().is_some_and(|a| !a.is_empty()))
+    else {
+        return false;
+    };
+    let index = below(rand, array.len());
+    if below(rand, 2) == 0 {
+        array.remove(index);
+    } else {
+        let element = array[index].clone();
+        let nb_copies = 1 + below(rand, MAX_ARRAY_COPIES);
+        array.extend(std::iter::repeat_n(element, nb_copies));
+    }
+    true
+}
+
+/// Replace a number with an interesting one such as a boundary of an integer type
+fn mutate_number<R: Rand>(rand: &mut R, value: &mut Value, _keys: &[String]) -> bool {
+    let new_number = interesting_number(rand);
+    let Some(node) = choose_node(rand, value, Value::is_number) else {
+        return false;
+    };
+    *node = new_number;
+    true
+}
+
+/// Modify the characters of a string or replace it with an interesting one
+fn mutate_string<R: Rand>(rand: &mut R, value: &mut Value, _keys: &[String]) -> bool {
+    let choice = below(rand, 4);
+    let inserted = random_string(rand);
+    let Some(Value::String(string)) = choose_node(rand, value, Value::is_string) else {
+        return false;
+    };
+    let mut chars: Vec<char> = string.chars().collect();
+    let position = below(rand, chars.len() + 1);
+    match choice {
+        0 => *string = INTERESTING_STRINGS[below(rand, INTERESTING_STRINGS.len())].into(),
+        1 => {
+            chars.splice(position..position, inserted.chars());
+            *string = chars.into_iter().collect();
+        }
+        2 if position < chars.len() => {
+            let end = position + below(rand, chars.len() - position) + 1;
+            chars.drain(position..end);
+            *string = chars.into_iter().collect();
+        }
+        // Long strings to trigger size limits
+        _ => *string = string.repeat(1 + below(rand, 256)),
+    }
+    true
+}
+
+/// Copy a node of the tree in place of another one
+fn splice_value<R: Rand>(rand: &mut R, value: &mut Value, _keys: &[String]) -> bool {
+    let Some(copied) = choose_node(rand, value, |_| true).cloned() else {
+        return false;
+    };
+    let Some(node) = choose_node(rand, value, |_| true) else {
+        return false;
+    };
+    *node = copied;
+    true
+}
+
+/// Apply `mutation` to the JSON body of `input`, bodies that become too deep are skipped
+fn mutate_json<S, F>(state: &mut S, input: &mut JsonInput, mutation: F) -> MutationResult
+where
+    S: HasRand,
+    F: FnOnce(&mut S::Rand, &mut Value) -> bool,
+{
+    let mut value = input.value.clone();
+    if !mutation(state.rand_mut(), &mut value) || depth(&value) > MAX_DEPTH {
+        return MutationResult::Skipped;
+    }
+    input.value = value;
+    MutationResult::Mutated
+}
+
+macro_rules! json_mutators {
+    ($($(#[$doc:meta])* $mutator:ident => $mutation:ident),+ $(,)?) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone)]
+            pub struct $mutator {
+                name: Cow<'static, str>,
+                /// Argument names expected by the command
+                keys: Vec<String>,
+            }
+
+            impl $mutator {
+                pub fn new(keys: Vec<String>) -> Self {
+                    $mutator {
+                        name: Cow::from(stringify!($mutator)),
+                        keys,
+                    }
+                }
+            }
+
+            impl Named for $mutator {
+                fn name(&self) -> &Cow<'static, str> {
+                    &self.name
+                }
+            }
+
+            impl<S: HasRand> Mutator<JsonInput, S> for $mutator {
+                fn mutate(
+                    &mut self,
+                    state: &mut S,
+                    input: &mut JsonInput,
+                ) -> Result<MutationResult, Error> {
+                    Ok(mutate_json(state, input, |rand, value| {
+                        $mutation(rand, value, &self.keys)
+                    }))
+                }
+            }
+        )+
+
+        /// Tuple of all the JSON mutators
+        pub type JsonMutationsType = tuple_list_type!($($mutator),+);
+
+        /// All the JSON mutators, `keys` are the argument names expected by the command
+        pub fn json_mutations(keys: &[String]) -> JsonMutationsType {
+            tuple_list!($($mutator::new(keys.to_vec())),+)
+        }
+    };
+}
+
+json_mutators!(
+    /// Replace a JSON value with a random one, usually of a different type
+    JsonReplaceValueMutator => replace_value,
+    /// Remove a key from a JSON object
+    JsonRemoveKeyMutator => remove_key,
+    /// Add a key to a JSON object
+    JsonAddKeyMutator => add_key,
+    /// Nest a JSON value deeply in arrays or objects
+    JsonNestMutator => nest_value,
+    /// Remove or duplicate the elements of a JSON array
+    JsonArrayMutator => mutate_array,
+    /// Replace a JSON number with an interesting one
+    JsonNumberMutator => mutate_number,
+    /// Modify a JSON string
+    JsonStringMutator => mutate_string,
+    /// Copy a JSON value in place of another one
+    JsonSpliceMutator => splice_value,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libafl_bolts::rands::StdRand;
+    use serde_json::json;
+
+    fn seed() -> Value {
+        json!({"path": "foo.txt", "size": 42, "options": {"recursive": true, "filters": ["*.rs"]}})
+    }
+
+    #[test]
+    fn nodes_are_indexed_in_pre_order() {
+        let mut value = seed();
+        assert_eq!(nb_nodes(&value), 7);
+        assert_eq!(nth_node_mut(&mut value, 0).cloned(), Some(seed()));
+        let strings = matching_nodes(&value, Value::is_string);
+        assert_eq!(strings.len(), 2);
+        for node in strings {
+            assert!(nth_node_mut(&mut value, node).is_some_and(|v| v.is_string()));
+        }
+        assert_eq!(nth_node_mut(&mut value, 7), None);
+    }
+
+    #[test]
+    fn mutations_keep_valid_json() {
+        type Mutation = fn(&mut StdRand, &mut Value, &[String]) -> bool;
+        let mutations: [Mutation; 8] = [
+            replace_value,
+            remove_key,
+            add_key,
+            nest_value,
+            mutate_array,
+            mutate_number,
+            mutate_string,
+            splice_value,
+        ];
+        let keys = argument_names(&[seed()]);
+        assert_eq!(keys, ["options", "path", "size"]);
+
+        let mut rand = StdRand::with_seed(1337);
+        for mutation in mutations {
+            let mut nb_mutated = 0;
+            for _ in 0..100 {
+                let mut value = seed();
+                if mutation(&mut rand, &mut value, &keys) {
+                    nb_mutated += 1;
+                }
+                // Floats may lose precision when serialized
+                let json = String::from(JsonInput::new(value));
+                assert!(JsonInput::try_from(json).is_ok());
+            }
+            assert!(nb_mutated > 0);
+        }
+    }
+
+    #[test]
+    fn inputs_are_stored_as_json_text() {
+        let input = JsonInput::new(seed());
+        let json = String::from(input.clone());
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), seed());
+        assert_eq!(JsonInput::try_from(json).unwrap(), input);
+        assert!(JsonInput::try_from("{\"path\": ".to_string()).is_err());
+    }
+
+    #[test]
+    fn mutations_without_target_are_skipped() {
+        let mut rand = StdRand::with_seed(0);
+        let mut value = json!(null);
+        assert!(!remove_key(&mut rand, &mut value, &[]));
+    }
+
+    #[test]
+    fn seeds_are_generated_in_turn() {
+        let seeds = json_seeds(|bytes| (!bytes.is_empty()).then(|| json!({"first": bytes[0]})));
+        assert_eq!(
+            seeds,
+            [
+                json!({"first": 0}),
+                json!({"first": 65}),
+                json!({"first": 255})
+            ]
+        );
+        let mut generator = JsonSeedsGenerator::new(seeds);
+        let generated: Vec<_> = (0..4)
+            .map(|_| generator.generate(&mut ()).unwrap().value().clone())
+            .collect();
+        assert_eq!(generated[0], generated[3]);
+        let mut generator = JsonSeedsGenerator::new(vec![]);
+        assert_eq!(generator.generate(&mut ()).unwrap().value(), &json!({}));
+    }
+}
@@ -14,11 +14,47 @@
     clippy::needless_pass_by_value,
     clippy::doc_markdown
 )]
+
+// Allow the derive macros to refer to this crate as `tauri_fuzz` in the tests
+#[cfg(test)]
+extern crate self as tauri_fuzz;
+
+mod behavior_profile;
 mod fuzzer;
 mod fuzzer_options;
+mod hook_report;
+mod json_mutations;
+mod policy_violation;
+mod replay;
+mod report;
+mod response_assertion;
 mod runtime;
-pub use crate::fuzzer::{fuzz_main, fuzz_test};
-pub use crate::fuzzer_options::SimpleFuzzerConfig;
+mod sequence;
+pub use crate::behavior_profile::{
+    BehaviorProfile, CommandProfile, AUDIT_ALL_HOOKS, AUDIT_ENV_VAR,
+};
+pub use crate::fuzzer::{
+    fuzz_main, fuzz_main_json, fuzz_main_sequence, fuzz_test, FuzzState, InputStrategy,
+};
+pub use crate::fuzzer_options::{InputMode, SimpleFuzzerConfig, REPLAY_ENV_VAR};
+pub use crate::hook_report::{HookAttachment, HookReport, HookStatus, STRICT_HOOKS_ENV_VAR};
+pub use crate::json_mutations::{
+    argument_names, json_mutations, json_seeds, JsonAddKeyMutator, JsonArrayMutator, JsonInput,
+    JsonMutationsType, JsonNestMutator, JsonNumberMutator, JsonRemoveKeyMutator,
+    JsonReplaceValueMutator, JsonSeedsGenerator, JsonSpliceMutator, JsonStringMutator,
+};
+pub use crate::policy_violation::{
+    DecodedArgument, PolicyViolation, PolicyViolationFeedback, PolicyViolationMetadata,
+    PolicyViolationObserver, ViolationContext,
+};
+pub use crate::report::{
+    report_path, Invocation, PanicReport, SolutionKind, SolutionReport, SolutionReportFeedback,
+};
+pub use crate::response_assertion::{FailedResponseAssertion, ResponseAssertion};
+pub use crate::runtime::resolve_hooks;
+pub use crate::sequence::{
+    encode_sequence, split_sequence, SequenceCall, SequenceGenerator, MAX_SEQUENCE_LEN,
+};
 
 #[cfg(feature = "tauri")]
 pub mod tauri;
@@ -0,0 +1,400 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Observer and feedback that report policy violations as their own objective.
+//!
+//! When a monitored function breaks the policy, the function listener records a
+//! [`PolicyViolation`] before stopping the execution of the fuzzed command through the crash
+//! path of the executor.
+//! The [`PolicyViolationObserver`] picks it up after the execution and the
+//! [`PolicyViolationFeedback`] makes the input an objective, it also keeps a copy of the input in
+//! a dedicated directory.
+//! This allows to tell apart a security violation from a regular crash or timeout of the command.
+//!
+//! Threads started by the fuzzed code can outlive the execution that started them. Their
+//! violations are attributed to the execution running when they are made. A violation made
+//! between two executions is reported with the input of the last execution when the next one
+//! ends, which is why the feedback keeps the inputs of the last executions.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use libafl::{
+    corpus::{Corpus, OnDiskCorpus, Testcase},
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::Feedback,
+    inputs::{HasTargetBytes, Input, UsesInput},
+    observers::{Observer, ObserversTuple},
+    state::{HasSolutions, State},
+    Error,
+};
+use libafl_bolts::{
+    impl_serdeany,
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+use tauri_fuzz_policies::engine::FunctionPolicy;
+
+use crate::report::{solution_name, SolutionKind, SolutionReport};
+use crate::runtime::current_execution;
+
+/// Number of executions whose input is kept to report the violations made after they ended
+const NB_RECENT_INPUTS: usize = 64;
+
+/// The policy violations recorded since the end of the last execution.
+/// Function listeners run in the middle of the fuzzed code so they can't reach the observers
+/// directly.
+static POLICY_VIOLATIONS: Mutex<Vec<PolicyViolation>> = Mutex::new(Vec::new());
+/// Handler called when a violation stops the execution, see [`set_violation_handler`]
+static VIOLATION_HANDLER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+/// A policy violation that happened while executing the fuzzed code
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PolicyViolation {
+    /// Name of the function that broke the policy
+    pub function: String,
+    /// Library of the function that broke the policy
+    pub lib: String,
+    /// Description of the policy that was broken
+    pub description: String,
+    /// Name of the sub-policy that blocked the call when the policy combines several policies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_policy: Option<String>,
+    /// Context of the function call when the policy was broken
+    pub context: ViolationContext,
+    /// Message explaining the policy infringement
+    pub message: String,
+    /// Symbolized backtrace of the call that broke the policy
+    pub backtrace: String,
+    /// Execution to which the violation is attributed, the one that was running when the policy
+    /// was broken
+    #[serde(skip)]
+    pub execution: u64,
+}
+
+/// Context of the function call that broke the policy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ViolationContext {
+    /// The policy was broken when entering the function
+    Entry { arguments: Vec<DecodedArgument> },
+    /// The policy was broken when leaving the function
+    Exit { return_value: usize },
+}
+
+/// Argument of a monitored function
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DecodedArgument {
+    /// Raw value of the register
+    pub value: usize,
+    /// String pointed by the register if it points to a readable C string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string: Option<String>,
+}
+
+impl PolicyViolation {
+    /// Record the violation of `policy` in the given `context`, attributed to `execution`
+    pub(crate) fn record(
+        policy: &FunctionPolicy,
+        context: ViolationContext,
+        message: String,
+        execution: u64,
+    ) {
+        let blocking_rule = policy.blocking_rule();
+        let violation = PolicyViolation {
+            function: policy.name.clone(),
+            lib: policy.lib.clone(),
+            description: blocking_rule.map_or_else(
+                || policy.description.clone(),
+                |rule| rule.description.clone(),
+            ),
+            sub_policy: blocking_rule.map(|rule| rule.policy.clone()),
+            context,
+            message,
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            execution,
+        };
+        POLICY_VIOLATIONS.lock().unwrap().push(violation);
+    }
+
+    /// Take all the violations recorded, in the order they happened
+    pub(crate) fn take_all() -> Vec<PolicyViolation> {
+        std::mem::take(&mut *POLICY_VIOLATIONS.lock().unwrap())
+    }
+}
+
+/// Handle the violations instead of the crash handler of the executor.
+/// This is used when replaying inputs without the fuzzer, the handler is not expected to return.
+pub(crate) fn set_violation_handler(handler: impl Fn() + Send + Sync + 'static) {
+    if VIOLATION_HANDLER.set(Box::new(handler)).is_err() {
+        log::warn!("A policy violation handler is already set");
+    }
+}
+
+/// Stop the execution of the fuzzed code after a violation was recorded.
+/// The function listeners run inside Frida hooks which can't be unwound, so the violation takes
+/// the crash path of the executor by raising `SIGABRT`.
+pub(crate) fn stop_execution() -> ! {
+    if let Some(handler) = VIOLATION_HANDLER.get() {
+        handler();
+    }
+    unsafe {
+        libc::raise(libc::SIGABRT);
+    }
+    // The crash handler of the executor exits the process
+    std::process::abort()
+}
+
+/// Testcase metadata describing the policy violation triggered by the input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyViolationMetadata {
+    pub violation: PolicyViolation,
+}
+
+impl_serdeany!(PolicyViolationMetadata);
+
+/// Observer of the policy violations that happened during an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyViolationObserver {
+    name: Cow<'static, str>,
+    violation: Option<PolicyViolation>,
+    earlier_violations: Vec<PolicyViolation>,
+}
+
+impl PolicyViolationObserver {
+    pub fn new(name: &'static str) -> Self {
+        PolicyViolationObserver {
+            name: Cow::from(name),
+            violation: None,
+            earlier_violations: Vec::new(),
+        }
+    }
+
+    /// The policy violation observed during the last execution
+    pub fn violation(&self) -> Option<&PolicyViolation> {
+        self.violation.as_ref()
+    }
+
+    /// The violations observed during the last execution that are attributed to previous
+    /// executions, made by threads those executions started
+    pub fn earlier_violations(&self) -> &[PolicyViolation] {
+        &self.earlier_violations
+    }
+}
+
+impl Named for PolicyViolationObserver {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<S> Observer<S> for PolicyViolationObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        // Violations recorded between executions are kept, they are attributed to the last
+        // execution
+        self.violation = None;
+        self.earlier_violations.clear();
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        let execution = current_execution();
+        let (violations, earlier_violations): (Vec<_>, Vec<_>) = PolicyViolation::take_all()
+            .into_iter()
+            .partition(|violation| violation.execution == execution);
+        // The first violation stopped the execution
+        self.violation = violations.into_iter().next();
+        self.earlier_violations = earlier_violations;
+        Ok(())
+    }
+}
+
+/// Objective feedback that is interesting when a policy violation was observed.
+/// Inputs that broke the policy are stored with their [`PolicyViolationMetadata`] in the
+/// solutions, a copy is also kept in their own directory.
+///
+/// The violations made between two executions by threads started by the fuzzed code are not
+/// blamed on the current input, the input of the previous execution is added to the solutions
+/// instead.
+#[derive(Debug)]
+pub struct PolicyViolationFeedback<I> {
+    observer_handle: Handle<PolicyViolationObserver>,
+    violations: OnDiskCorpus<I>,
+    violations_dir: PathBuf,
+    solutions_dir: PathBuf,
+    /// Inputs of the last executions with the number of their execution
+    recent_inputs: VecDeque<(u64, I)>,
+}
+
+impl<I> PolicyViolationFeedback<I>
+where
+    I: Input + HasTargetBytes,
+{
+    /// Create a feedback on the given observer that stores violations in `violations_dir`.
+    /// `solutions_dir` is where the solutions of the fuzzer are stored.
+    pub fn new(
+        observer: &PolicyViolationObserver,
+        violations_dir: PathBuf,
+        solutions_dir: PathBuf,
+    ) -> Self {
+        PolicyViolationFeedback {
+            observer_handle: observer.handle(),
+            violations: OnDiskCorpus::new(&violations_dir)
+                .expect("Failed to create the policy violations directory"),
+            violations_dir,
+            solutions_dir,
+            recent_inputs: VecDeque::with_capacity(NB_RECENT_INPUTS),
+        }
+    }
+
+    /// Report a violation attributed to a previous execution with the input of that execution
+    fn add_earlier_violation<S>(
+        &mut self,
+        state: &mut S,
+        violation: &PolicyViolation,
+    ) -> Result<(), Error>
+    where
+        S: HasSolutions<Input = I>,
+    {
+        let Some((_, input)) = self
+            .recent_inputs
+            .iter()
+            .find(|(execution, _)| *execution == violation.execution)
+        else {
+            log::error!(
+                "Policy violation at function [{}] made {} executions ago, its input is not \
+                 available anymore",
+                violation.function,
+                current_execution() - violation.execution
+            );
+            return Ok(());
+        };
+        log::error!(
+            "Policy violation at function [{}] made after a previous execution: {}",
+            violation.function,
+            violation.description
+        );
+
+        let name = solution_name(input);
+        let report = SolutionReport {
+            kind: SolutionKind::PolicyViolation,
+            command: None,
+            args: None,
+            sequence: Vec::new(),
+            policy_violation: Some(violation.clone()),
+            panic: None,
+            failed_assertion: None,
+        };
+        report.write(&self.solutions_dir, &name)?;
+        report.write(&self.violations_dir, &name)?;
+
+        let mut testcase = Testcase::new(input.clone());
+        *testcase.filename_mut() = Some(name);
+        testcase.add_metadata(PolicyViolationMetadata {
+            violation: violation.clone(),
+        });
+        self.violations.add(testcase.clone())?;
+        state.solutions_mut().add(testcase)?;
+        Ok(())
+    }
+}
+
+impl<I> Named for PolicyViolationFeedback<I> {
+    fn name(&self) -> &Cow<'static, str> {
+        self.observer_handle.name()
+    }
+}
+
+impl<S> Feedback<S> for PolicyViolationFeedback<S::Input>
+where
+    S: State + HasSolutions,
+    S::Input: HasTargetBytes,
+{
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .get(&self.observer_handle)
+            .ok_or_else(|| Error::illegal_state("PolicyViolationObserver is missing"))?;
+
+        if self.recent_inputs.len() == NB_RECENT_INPUTS {
+            self.recent_inputs.pop_front();
+        }
+        self.recent_inputs
+            .push_back((current_execution(), input.clone()));
+        for violation in observer.earlier_violations() {
+            self.add_earlier_violation(state, violation)?;
+        }
+
+        let Some(violation) = observer.violation() else {
+            return Ok(false);
+        };
+
+        log::error!(
+            "Policy violation at function [{}]: {}",
+            violation.function,
+            violation.description
+        );
+        let mut testcase = Testcase::new(input.clone());
+        // Named after the input so that it can be matched with its report
+        *testcase.filename_mut() = Some(solution_name(input));
+        testcase.add_metadata(PolicyViolationMetadata {
+            violation: violation.clone(),
+        });
+        self.violations.add(testcase)?;
+        Ok(true)
+    }
+
+    fn append_metadata<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+        EM: EventFirer<State = S>,
+    {
+        if let Some(violation) = observers
+            .get(&self.observer_handle)
+            .and_then(PolicyViolationObserver::violation)
+        {
+            testcase.add_metadata(PolicyViolationMetadata {
+                violation: violation.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Directory where the policy violations are stored, next to the solutions directory
+pub(crate) fn policy_violations_dir(solutions_dir: &std::path::Path) -> PathBuf {
+    let mut dir_name = solutions_dir
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    dir_name.push("_policy_violations");
+    solutions_dir.with_file_name(dir_name)
+}
@@ -0,0 +1,223 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Replay inputs found by the fuzzer without the fuzzing loop.
+//!
+//! The harness is executed once per input with the [`FunctionListenerRuntime`] attached.
+//! A policy violation stops the execution inside the function listeners which can't be unwound
+//! through Frida, so each input is replayed in its own process and the outcome is printed from the
+//! violation handler, or from the panic hook when the command crashes.
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use frida_gum::{Gum, ModuleMap};
+use libafl::{
+    executors::ExitKind,
+    inputs::{HasTargetBytes, Input},
+    Error,
+};
+use libafl_frida::helper::FridaRuntime;
+use rangemap::RangeMap;
+use tauri_fuzz_policies::engine::FuzzPolicy;
+use tauri_fuzz_policies::fuzz_target::{
+    CRASH_EXIT_CODE, FAILED_ASSERTION_EXIT_CODE, POLICY_VIOLATION_EXIT_CODE, REPLAY_ENV_VAR,
+    TIMEOUT_EXIT_CODE,
+};
+
+use crate::policy_violation::{set_violation_handler, PolicyViolation, ViolationContext};
+use crate::report::{set_timeout_handler, Invocation};
+use crate::response_assertion::FailedResponseAssertion;
+use crate::runtime::FunctionListenerRuntime;
+
+/// Replay a single input or all the inputs contained in a directory
+///
+/// # Safety
+///
+/// Same as [`crate::fuzz_main`], this obtains `frida_gum::Gum`
+pub(crate) unsafe fn replay<I, H>(
+    harness: H,
+    replay_path: &Path,
+    tauri_cmd_address: usize,
+    policy: FuzzPolicy,
+    audit_profile: Option<PathBuf>,
+) -> Result<(), Error>
+where
+    I: Input + HasTargetBytes,
+    H: FnMut(&I) -> ExitKind,
+{
+    if replay_path.is_dir() {
+        replay_dir(replay_path)
+    } else {
+        replay_input(
+            harness,
+            replay_path,
+            tauri_cmd_address,
+            policy,
+            audit_profile,
+        )
+    }
+}
+
+/// Replay each input of the directory in a new process.
+/// Exits with the code of the most severe outcome among the replays, a policy violation first.
+fn replay_dir(dir: &Path) -> Result<(), Error> {
+    let exe = std::env::current_exe()?;
+    let inputs = inputs_in_dir(dir)?;
+    let mut nb_violations = 0;
+    let mut nb_crashes = 0;
+    let mut nb_failed_assertions = 0;
+    let mut nb_timeouts = 0;
+    for input in &inputs {
+        let status = std::process::Command::new(&exe)
+            .env(REPLAY_ENV_VAR, input)
+            .status()?;
+        match status.code() {
+            Some(0) => {}
+            Some(POLICY_VIOLATION_EXIT_CODE) => nb_violations += 1,
+            Some(FAILED_ASSERTION_EXIT_CODE) => nb_failed_assertions += 1,
+            Some(TIMEOUT_EXIT_CODE) => nb_timeouts += 1,
+            _ => nb_crashes += 1,
+        }
+    }
+    println!(
+        "Replayed {} inputs: {nb_violations} policy violations, \
+        {nb_failed_assertions} failed response assertions, {nb_crashes} crashes, \
+        {nb_timeouts} timeouts",
+        inputs.len()
+    );
+    let exit_code = if nb_violations > 0 {
+        POLICY_VIOLATION_EXIT_CODE
+    } else if nb_crashes > 0 {
+        CRASH_EXIT_CODE
+    } else if nb_timeouts > 0 {
+        TIMEOUT_EXIT_CODE
+    } else if nb_failed_assertions > 0 {
+        FAILED_ASSERTION_EXIT_CODE
+    } else {
+        return Ok(());
+    };
+    std::process::exit(exit_code);
+}
+
+/// Inputs contained in a solutions directory, ignoring reports and the files used by LibAFL
+pub(crate) fn inputs_in_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut inputs = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_file() && !name.starts_with('.') && !name.ends_with(".report.json") {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// Replay a single input and exit with a code depending on the outcome
+unsafe fn replay_input<I, H>(
+    mut harness: H,
+    input_path: &Path,
+    tauri_cmd_address: usize,
+    policy: FuzzPolicy,
+    audit_profile: Option<PathBuf>,
+) -> Result<(), Error>
+where
+    I: Input + HasTargetBytes,
+    H: FnMut(&I) -> ExitKind,
+{
+    let input = I::from_file(input_path)?;
+
+    let gum = Gum::obtain();
+    let mut function_listener_rt =
+        FunctionListenerRuntime::new(policy, tauri_cmd_address, audit_profile)?;
+    function_listener_rt.init(&gum, &RangeMap::default(), &Rc::new(ModuleMap::new(&gum)));
+
+    // The function listeners stop the execution when the policy is violated, print the outcome
+    // from there
+    let replayed_path = input_path.to_path_buf();
+    set_violation_handler(move || {
+        print_invocations(&replayed_path, &Invocation::take_all());
+        if let Some(violation) = PolicyViolation::take_all().pop() {
+            print_violation(&violation);
+        }
+        std::process::exit(POLICY_VIOLATION_EXIT_CODE);
+    });
+    // The task of the command can't be cancelled, the replay ends here
+    let replayed_path = input_path.to_path_buf();
+    set_timeout_handler(move || {
+        print_invocations(&replayed_path, &Invocation::take_all());
+        println!("Policy was not violated");
+        println!("Command did not respond in time");
+        std::process::exit(TIMEOUT_EXIT_CODE);
+    });
+    let old_hook = std::panic::take_hook();
+    let replayed_path = input_path.to_path_buf();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        old_hook(panic_info);
+        print_invocations(&replayed_path, &Invocation::take_all());
+        println!("Policy was not violated");
+        println!("Command crashed: {panic_info}");
+        std::process::exit(CRASH_EXIT_CODE);
+    }));
+
+    function_listener_rt.pre_exec(&input)?;
+    let exit_kind = harness(&input);
+    function_listener_rt.post_exec(&input)?;
+
+    let invocations = Invocation::take_all();
+    let response = invocations.last().and_then(|i| i.response.clone());
+    print_invocations(input_path, &invocations);
+    println!("Policy was not violated");
+    match response {
+        Some(Ok(value)) => println!("Command returned: {value}"),
+        Some(Err(error)) => println!("Command returned an error: {error}"),
+        None => {
+            println!("No response received from the command, harness exited with {exit_kind:?}")
+        }
+    }
+    if let Some(failed) = FailedResponseAssertion::take() {
+        println!("Response assertion failed: {}", failed.assertion);
+        std::process::exit(FAILED_ASSERTION_EXIT_CODE);
+    }
+    Ok(())
+}
+
+fn print_invocations(input_path: &Path, invocations: &[Invocation]) {
+    println!("Replaying {}", input_path.display());
+    match invocations {
+        [] => println!("The harness did not record the command invocation"),
+        [invocation] => {
+            println!("Command: {}", invocation.command);
+            println!("Arguments: {}", invocation.args);
+        }
+        _ => {
+            println!("Sequence of {} commands:", invocations.len());
+            for (i, invocation) in invocations.iter().enumerate() {
+                println!("  #{i}: {} {}", invocation.command, invocation.args);
+            }
+        }
+    }
+}
+
+fn print_violation(violation: &PolicyViolation) {
+    println!(
+        "Policy was violated at function [{}] from {}",
+        violation.function, violation.lib
+    );
+    println!("Description: {}", violation.description);
+    match &violation.context {
+        ViolationContext::Entry { arguments } => {
+            println!("On entry with arguments:");
+            for (i, argument) in arguments.iter().enumerate() {
+                match &argument.string {
+                    Some(string) => println!("  #{i}: {:#x} -> {string:?}", argument.value),
+                    None => println!("  #{i}: {:#x}", argument.value),
+                }
+            }
+        }
+        ViolationContext::Exit { return_value } => {
+            println!("On exit with return value: {return_value:#x}");
+        }
+    }
+}
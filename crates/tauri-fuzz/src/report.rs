@@ -0,0 +1,305 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Machine-readable reports written next to the solutions found by the fuzzer.
+//!
+//! Each solution `<name>` stored in the solutions directory or in the policy violations
+//! directory comes with a `<name>.report.json` file describing the command invocation and why
+//! the input is a solution.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use libafl::{
+    corpus::Testcase, events::EventFirer, executors::ExitKind, feedbacks::Feedback,
+    inputs::HasTargetBytes, observers::ObserversTuple, state::State, Error,
+};
+use libafl_bolts::{
+    tuples::{Handle, Handled, MatchNameRef},
+    AsSlice, Named,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::policy_violation::{PolicyViolation, PolicyViolationObserver};
+use crate::response_assertion::FailedResponseAssertion;
+
+/// The commands invoked during the current execution, in order
+static INVOCATIONS: Mutex<Vec<Invocation>> = Mutex::new(Vec::new());
+/// The last panic that happened during the current execution
+static LAST_PANIC: Mutex<Option<PanicReport>> = Mutex::new(None);
+/// Called when a command did not respond in time, set when replaying inputs
+static TIMEOUT_HANDLER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+/// Serializes the tests that invoke commands since the invocations are recorded globally
+#[cfg(test)]
+static INVOCATIONS_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// Tauri command invocation made by the harness
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Invocation {
+    /// Name of the invoked command
+    pub command: String,
+    /// Arguments of the command as they were sent to Tauri
+    pub args: serde_json::Value,
+    /// Value or error returned by the command if the harness received it
+    pub response: Option<Result<serde_json::Value, serde_json::Value>>,
+}
+
+impl Invocation {
+    /// Record a command invoked by the harness so that it appears in the reports
+    pub fn record(command: impl Into<String>, args: serde_json::Value) {
+        INVOCATIONS.lock().unwrap().push(Invocation {
+            command: command.into(),
+            args,
+            response: None,
+        });
+    }
+
+    /// Record the response of the last command invoked
+    pub fn record_response(response: Result<serde_json::Value, serde_json::Value>) {
+        if let Some(invocation) = INVOCATIONS.lock().unwrap().last_mut() {
+            invocation.response = Some(response);
+        }
+    }
+
+    /// Take all the invocations recorded during the current execution
+    pub fn take_all() -> Vec<Invocation> {
+        std::mem::take(&mut *INVOCATIONS.lock().unwrap())
+    }
+
+    /// Lock held by the tests that invoke commands or check response assertions, the recorded
+    /// invocations and failed assertions start empty
+    #[cfg(test)]
+    pub(crate) fn lock_for_test() -> std::sync::MutexGuard<'static, ()> {
+        let guard = INVOCATIONS_TEST_LOCK
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        Invocation::take_all();
+        FailedResponseAssertion::take();
+        guard
+    }
+}
+
+/// Set the function called when a command did not respond in time, before the execution stops
+pub(crate) fn set_timeout_handler(handler: impl Fn() + Send + Sync + 'static) {
+    if TIMEOUT_HANDLER.set(Box::new(handler)).is_err() {
+        log::warn!("A timeout handler is already set");
+    }
+}
+
+/// Stop an execution where a command did not respond in time.
+/// The task of an async command can't be cancelled and would keep running during the next
+/// executions, so the process has to stop. The replay exits from its timeout handler, when fuzzing
+/// this waits for the timeout of the executor which restarts the fuzzer without the task.
+pub(crate) fn stop_timed_out_execution() -> ! {
+    if let Some(handler) = TIMEOUT_HANDLER.get() {
+        handler();
+    }
+    log::warn!("Command timed out, waiting for the executor to stop the execution");
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Panic that happened during an execution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PanicReport {
+    /// Message of the panic including its location
+    pub message: String,
+    /// Symbolized backtrace of the panic
+    pub backtrace: String,
+}
+
+/// Record panics so that they appear in the reports.
+/// This must be called before creating the executor since the executor panic hook handles the
+/// solution after calling the previous hooks.
+pub(crate) fn install_panic_recorder() {
+    let old_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        *LAST_PANIC.lock().unwrap() = Some(PanicReport {
+            message: panic_info.to_string(),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        });
+        old_hook(panic_info);
+    }));
+}
+
+/// Reason why an input is a solution
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SolutionKind {
+    PolicyViolation,
+    /// A response of the command broke one of the [`crate::ResponseAssertion`]
+    FailedResponseAssertion,
+    Crash,
+    Timeout,
+    Oom,
+}
+
+/// Report describing a solution found by the fuzzer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SolutionReport {
+    pub kind: SolutionKind,
+    /// Name of the invoked command, `None` if the harness did not record it
+    pub command: Option<String>,
+    /// Arguments of the invoked command, `None` if the harness did not record them
+    pub args: Option<serde_json::Value>,
+    /// All the commands invoked when the input is a sequence of invocations.
+    /// `command` and `args` describe the last one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequence: Vec<Invocation>,
+    /// The policy violation if the solution broke the policy
+    pub policy_violation: Option<PolicyViolation>,
+    /// The panic that happened during the execution if any
+    pub panic: Option<PanicReport>,
+    /// The response assertion that failed during the execution if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_assertion: Option<FailedResponseAssertion>,
+}
+
+impl SolutionReport {
+    /// Write the report for the solution `name` in `dir`
+    pub(crate) fn write(&self, dir: &Path, name: &str) -> Result<(), Error> {
+        std::fs::create_dir_all(dir)?;
+        let report = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::serialize(format!("Failed to serialize report: {e}")))?;
+        std::fs::write(report_path(dir, name), report)?;
+        Ok(())
+    }
+}
+
+/// Path of the report of the solution `name` stored in `dir`
+pub fn report_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.report.json"))
+}
+
+/// Name of the file where a solution is stored, derived from the input content
+pub(crate) fn solution_name<I: HasTargetBytes>(input: &I) -> String {
+    format!(
+        "{:016x}",
+        libafl_bolts::hash_std(input.target_bytes().as_slice())
+    )
+}
+
+/// Feedback that writes a [`SolutionReport`] for each solution.
+/// It is never interesting by itself and must be the first of the objectives so that it can
+/// observe every execution.
+#[derive(Debug)]
+pub struct SolutionReportFeedback {
+    name: Cow<'static, str>,
+    violation_observer: Handle<PolicyViolationObserver>,
+    solutions_dir: PathBuf,
+    violations_dir: PathBuf,
+    /// Report of the last execution
+    report: Option<SolutionReport>,
+}
+
+impl SolutionReportFeedback {
+    pub fn new(
+        violation_observer: &PolicyViolationObserver,
+        solutions_dir: PathBuf,
+        violations_dir: PathBuf,
+    ) -> Self {
+        SolutionReportFeedback {
+            name: Cow::from("solution_report"),
+            violation_observer: violation_observer.handle(),
+            solutions_dir,
+            violations_dir,
+            report: None,
+        }
+    }
+}
+
+impl Named for SolutionReportFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<S> Feedback<S> for SolutionReportFeedback
+where
+    S: State,
+    S::Input: HasTargetBytes,
+{
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        input: &S::Input,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        // Always take the records so that they don't leak into the next execution
+        let mut invocations = Invocation::take_all();
+        let panic = LAST_PANIC.lock().unwrap().take();
+        let failed_assertion = FailedResponseAssertion::take();
+        let policy_violation = observers
+            .get(&self.violation_observer)
+            .and_then(PolicyViolationObserver::violation)
+            .cloned();
+
+        let kind = match (&policy_violation, exit_kind) {
+            (Some(_), _) => SolutionKind::PolicyViolation,
+            // The harness reports failed assertions as crashes
+            (None, ExitKind::Crash) if failed_assertion.is_some() => {
+                SolutionKind::FailedResponseAssertion
+            }
+            (None, ExitKind::Timeout) => SolutionKind::Timeout,
+            (None, ExitKind::Oom) => SolutionKind::Oom,
+            (None, ExitKind::Ok) => {
+                self.report = None;
+                return Ok(false);
+            }
+            (None, _) => SolutionKind::Crash,
+        };
+        let (command, args) = invocations
+            .last()
+            .map(|i| (i.command.clone(), i.args.clone()))
+            .unzip();
+        // A single invocation is already described by `command` and `args`
+        if invocations.len() == 1 {
+            invocations.clear();
+        }
+        let report = SolutionReport {
+            kind,
+            command,
+            args,
+            sequence: invocations,
+            policy_violation,
+            panic,
+            failed_assertion,
+        };
+
+        // The copy of the policy violations kept in their own directory also gets the report
+        if kind == SolutionKind::PolicyViolation {
+            report.write(&self.violations_dir, &solution_name(input))?;
+        }
+        self.report = Some(report);
+        Ok(false)
+    }
+
+    fn append_metadata<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+        EM: EventFirer<State = S>,
+    {
+        let (Some(report), Some(input)) = (self.report.take(), testcase.input()) else {
+            return Ok(());
+        };
+        // Name the solution after the input so that it can be matched with its report
+        let name = solution_name(input);
+        report.write(&self.solutions_dir, &name)?;
+        *testcase.filename_mut() = Some(name);
+        Ok(())
+    }
+}
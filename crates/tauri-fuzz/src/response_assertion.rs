@@ -0,0 +1,213 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Assertions on the responses that the fuzzed commands return to the frontend.
+//!
+//! Policies only inspect the native functions called by a command. A [`ResponseAssertion`]
+//! inspects what the command sends back through IPC, for example to make sure that it never
+//! leaks a secret. The harness checks the assertions on each response and an input that breaks
+//! one of them is reported as a solution.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The last response assertion that failed during the current execution
+static LAST_FAILED_ASSERTION: Mutex<Option<FailedResponseAssertion>> = Mutex::new(None);
+
+type Predicate = Box<dyn Fn(&Result<Value, Value>) -> bool + Send + Sync>;
+
+/// Named predicate that must hold for every response of a command.
+/// The response is the JSON value returned by the command, or the JSON of its `InvokeError`.
+pub struct ResponseAssertion {
+    name: String,
+    predicate: Predicate,
+}
+
+impl ResponseAssertion {
+    /// Assertion that holds when `predicate` returns `true`.
+    /// `name` describes the assertion in the reports.
+    pub fn new(
+        name: impl Into<String>,
+        predicate: impl Fn(&Result<Value, Value>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        ResponseAssertion {
+            name: name.into(),
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// The response, value or error, never contains `pattern` in any of its strings or keys
+    pub fn never_contains(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        ResponseAssertion::new(format!("response never contains {pattern:?}"), move |res| {
+            let value = match res {
+                Ok(value) | Err(value) => value,
+            };
+            !any_string(value, &|s| s.contains(&pattern))
+        })
+    }
+
+    /// Absolute paths in the response are all located in one of the `allowed_dirs`.
+    /// The paths are normalized first so that `..` can't escape the allowed directories.
+    pub fn no_absolute_path_outside(allowed_dirs: Vec<PathBuf>) -> Self {
+        let name = format!("response has no absolute path outside of {allowed_dirs:?}");
+        let allowed_dirs: Vec<PathBuf> = allowed_dirs.iter().map(|dir| normalize(dir)).collect();
+        ResponseAssertion::new(name, move |res| {
+            let value = match res {
+                Ok(value) | Err(value) => value,
+            };
+            !any_string(value, &|s| {
+                let path = Path::new(s);
+                path.is_absolute() && {
+                    let path = normalize(path);
+                    !allowed_dirs.iter().any(|dir| path.starts_with(dir))
+                }
+            })
+        })
+    }
+
+    /// Errors returned by the command satisfy `predicate`, such as matching a JSON schema.
+    /// Successful responses are not checked.
+    pub fn error_matches(
+        name: impl Into<String>,
+        predicate: impl Fn(&Value) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        ResponseAssertion::new(name, move |res| match res {
+            Ok(_) => true,
+            Err(error) => predicate(error),
+        })
+    }
+
+    /// Name describing the assertion
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the assertion holds on `response`, without recording anything
+    pub fn holds(&self, response: &Result<Value, Value>) -> bool {
+        (self.predicate)(response)
+    }
+
+    /// Check the assertion on `response`, a failure is recorded to be reported as a solution
+    pub fn check(&self, response: &Result<Value, Value>) -> bool {
+        let holds = self.holds(response);
+        if !holds {
+            log::error!("Response assertion failed: {}", self.name);
+            *LAST_FAILED_ASSERTION.lock().unwrap() = Some(FailedResponseAssertion {
+                assertion: self.name.clone(),
+                response: response.clone(),
+            });
+        }
+        holds
+    }
+}
+
+impl std::fmt::Debug for ResponseAssertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseAssertion")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Lexically normalize `path`, removing the `.` components and resolving the `..` ones.
+/// The file system is not accessed, symbolic links are not resolved.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `..` can't go above the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                Some(Component::CurDir | Component::ParentDir) | None => {
+                    normalized.push(component);
+                }
+            },
+            Component::Prefix(_) | Component::RootDir | Component::Normal(_) => {
+                normalized.push(component);
+            }
+        }
+    }
+    normalized
+}
+
+/// Whether a string or a key of `value` satisfies `predicate`
+fn any_string(value: &Value, predicate: &dyn Fn(&str) -> bool) -> bool {
+    match value {
+        Value::String(s) => predicate(s),
+        Value::Array(values) => values.iter().any(|v| any_string(v, predicate)),
+        Value::Object(map) => map
+            .iter()
+            .any(|(key, v)| predicate(key) || any_string(v, predicate)),
+        Value::Null | Value::Bool(_) | Value::Number(_) => false,
+    }
+}
+
+/// A response assertion that failed during an execution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailedResponseAssertion {
+    /// Name of the assertion that failed
+    pub assertion: String,
+    /// Response on which the assertion failed
+    pub response: Result<Value, Value>,
+}
+
+impl FailedResponseAssertion {
+    /// Take the last failed assertion recorded if any
+    pub(crate) fn take() -> Option<FailedResponseAssertion> {
+        LAST_FAILED_ASSERTION.lock().unwrap().take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn never_contains() {
+        let assertion = ResponseAssertion::never_contains("SECRET");
+        assert!(assertion.holds(&Ok(json!({"user": "alice", "ids": [1, 2]}))));
+        assert!(!assertion.holds(&Ok(json!({"token": ["xSECRETx"]}))));
+        assert!(!assertion.holds(&Err(json!({"SECRET": null}))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn no_absolute_path_outside() {
+        let assertion = ResponseAssertion::no_absolute_path_outside(vec!["/app/data".into()]);
+        assert!(assertion.holds(&Ok(json!(["/app/data/foo.txt", "relative/path"]))));
+        assert!(assertion.holds(&Ok(json!("/app/data/./sub/../foo.txt"))));
+        assert!(!assertion.holds(&Ok(json!({"path": "/etc/passwd"}))));
+        assert!(!assertion.holds(&Ok(json!("/app/data/../../etc/passwd"))));
+        assert!(!assertion.holds(&Ok(json!("/app/database"))));
+    }
+
+    #[test]
+    fn error_matches() {
+        let assertion =
+            ResponseAssertion::error_matches("error has a code", |error| error["code"].is_u64());
+        assert!(assertion.holds(&Ok(json!("no error"))));
+        assert!(assertion.holds(&Err(json!({"code": 2}))));
+        assert!(!assertion.holds(&Err(json!("untyped error"))));
+    }
+
+    #[test]
+    fn failed_check_is_recorded() {
+        let _lock = crate::Invocation::lock_for_test();
+        let assertion = ResponseAssertion::never_contains("SECRET");
+        assert!(assertion.check(&Ok(json!("public"))));
+        assert_eq!(FailedResponseAssertion::take(), None);
+        assert!(!assertion.check(&Ok(json!("SECRET"))));
+        let failed = FailedResponseAssertion::take().unwrap();
+        assert_eq!(failed.assertion, assertion.name());
+        assert_eq!(failed.response, Ok(json!("SECRET")));
+    }
+}
@@ -2,7 +2,9 @@
 // SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
 
 mod func_call_listener_rt;
-pub use func_call_listener_rt::FunctionListenerRuntime;
+pub(crate) use func_call_listener_rt::current_execution;
+pub use func_call_listener_rt::{resolve_hooks, FunctionListenerRuntime};
 #[cfg(unix)]
 #[cfg(feature = "instr_listener")]
 mod instruction_listener_rt;
+mod symbols;
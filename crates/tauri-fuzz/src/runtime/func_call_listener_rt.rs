@@ -5,9 +5,16 @@
 
 #[allow(unused_imports)]
 use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet},
+    ffi::c_void,
     fmt::{Debug, Formatter},
+    path::PathBuf,
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use frida_gum::{
@@ -22,7 +29,12 @@ use libafl::{
 use libafl_frida::helper::FridaRuntime;
 
 use rangemap::RangeMap;
-use tauri_fuzz_policies::engine::{Context, FunctionPolicy, FuzzPolicy};
+use tauri_fuzz_policies::engine::{ArgType, Arguments, Context, FunctionPolicy, FuzzPolicy};
+
+use super::symbols::{demangle, RustFunctionPattern};
+use crate::behavior_profile::BehaviorProfile;
+use crate::hook_report::{HookAttachment, HookReport, HookStatus};
+use crate::policy_violation::{stop_execution, DecodedArgument, PolicyViolation, ViolationContext};
 
 /// `Frida`-based binary-only instrumentation that intercepts calls to system calls
 pub struct FunctionListenerRuntime {
@@ -30,45 +42,140 @@ pub struct FunctionListenerRuntime {
     // harness_listener: HarnessListener,
     /// Listeners to all the libc functions that are being monitored
     function_listeners: Vec<FunctionListener>,
-    /// Flag to indicate when the runtime is activated
-    switch: Arc<Mutex<InterceptionSwitch>>,
+    /// Listener to the function creating threads, if it was found
+    thread_spawn_listener: Option<ThreadSpawnListener>,
     /// Pointer to the harness code
     harness_pointer: NativePointer,
+    /// File where the behavior profile is saved in audit mode, `None` if the policy is enforced
+    audit_profile: Option<PathBuf>,
+    /// Hooks attached for the policy
+    hook_report: HookReport,
     /// Flag to avoid initializing twice
     is_init: Arc<Mutex<bool>>,
 }
 
-#[derive(Debug)]
-struct InterceptionSwitch {
-    flag: bool,
+/// Number of the current execution, incremented before each execution.
+/// No execution is numbered `0` so that it can mark the threads that are not attributed.
+static EXECUTION: AtomicU64 = AtomicU64::new(0);
+
+/// If an execution is running, it's not the case between the end of an execution and the start
+/// of the next one
+static EXECUTION_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Number of the current execution
+pub(crate) fn current_execution() -> u64 {
+    EXECUTION.load(Ordering::SeqCst)
 }
 
-impl InterceptionSwitch {
-    fn activate(&mut self) {
-        self.flag = true;
-    }
+/// Attribution of the threads started by the fuzzed code, see [`ATTRIBUTED_EXECUTION`]
+const SPAWNED_THREAD: u64 = u64::MAX;
+
+thread_local! {
+    /// Execution to which the calls made by the current thread are attributed, `0` if they are
+    /// not attributed to the fuzzed code.
+    /// The thread running the harness is attributed to the current execution while it is in the
+    /// harness. The threads started from an attributed thread are marked with [`SPAWNED_THREAD`]
+    /// during their whole lifetime: they may be reused by the next executions, such as the workers
+    /// of an async runtime, so their calls are attributed to the execution running when they are
+    /// made.
+    /// Other threads such as the fuzzer ones are never monitored.
+    static ATTRIBUTED_EXECUTION: Cell<u64> = const { Cell::new(0) };
+}
 
-    fn deactivate(&mut self) {
-        self.flag = false;
+fn attributed_execution() -> u64 {
+    match ATTRIBUTED_EXECUTION.with(Cell::get) {
+        SPAWNED_THREAD => current_execution(),
+        execution => execution,
     }
+}
 
-    fn is_active(&self) -> bool {
-        self.flag
-    }
+fn is_attributed_thread() -> bool {
+    attributed_execution() != 0
+}
+
+fn set_attributed_execution(execution: u64) {
+    ATTRIBUTED_EXECUTION.with(|cell| cell.set(execution));
 }
 
 impl InvocationListener for FunctionListenerRuntime {
-    /// When entering the fuzzed code set the flag to true
+    /// When entering the fuzzed code attribute the current thread to the current execution
     fn on_enter(&mut self, _context: InvocationContext) {
-        self.switch.lock().unwrap().activate()
+        set_attributed_execution(current_execution());
     }
 
-    /// When leaving the fuzzed code set the flag to true
+    /// When leaving the fuzzed code stop attributing the current thread to it
     fn on_leave(&mut self, _context: InvocationContext) {
-        self.switch.lock().unwrap().deactivate()
+        set_attributed_execution(0);
     }
 }
 
+/// Name of the function used to create threads, `std::thread` and the async runtimes use it
+#[cfg(unix)]
+const THREAD_SPAWN_FUNCTION: &str = "pthread_create";
+#[cfg(windows)]
+const THREAD_SPAWN_FUNCTION: &str = "CreateThread";
+
+/// Index of the start routine and of its argument in the parameters of [`THREAD_SPAWN_FUNCTION`]
+const THREAD_START_ROUTINE_ARG: u32 = 2;
+const THREAD_START_PARAMETER_ARG: u32 = 3;
+
+/// Listener to the creation of threads.
+/// Threads created by an attributed thread execute [`attributed_thread_start`] first so that
+/// they are attributed to the fuzzed code.
+#[derive(Debug)]
+struct ThreadSpawnListener {
+    /// Pointer to [`THREAD_SPAWN_FUNCTION`]
+    function_pointer: NativePointer,
+}
+
+/// Start routine of a thread created by the fuzzed code and its parameter
+struct ThreadStart {
+    routine: usize,
+    parameter: usize,
+}
+
+impl InvocationListener for ThreadSpawnListener {
+    fn on_enter(&mut self, mut context: InvocationContext) {
+        if is_attributed_thread() {
+            // Leaked if the thread creation fails, this is a small struct in a rare case
+            let start = Box::new(ThreadStart {
+                routine: context.arg(THREAD_START_ROUTINE_ARG),
+                parameter: context.arg(THREAD_START_PARAMETER_ARG),
+            });
+            context.set_arg(THREAD_START_ROUTINE_ARG, attributed_thread_start as usize);
+            context.set_arg(THREAD_START_PARAMETER_ARG, Box::into_raw(start) as usize);
+        }
+    }
+
+    fn on_leave(&mut self, _context: InvocationContext) {}
+}
+
+/// Start routine of the threads created by the fuzzed code.
+/// Attributes the new thread to the fuzzed code then runs its original start routine.
+#[cfg(unix)]
+extern "C" fn attributed_thread_start(start: *mut c_void) -> *mut c_void {
+    // The pointer was created by `ThreadSpawnListener` and is only used by this thread
+    let start = unsafe { Box::from_raw(start.cast::<ThreadStart>()) };
+    set_attributed_execution(SPAWNED_THREAD);
+    let routine = unsafe {
+        std::mem::transmute::<usize, extern "C" fn(*mut c_void) -> *mut c_void>(start.routine)
+    };
+    routine(start.parameter as *mut c_void)
+}
+
+/// Start routine of the threads created by the fuzzed code.
+/// Attributes the new thread to the fuzzed code then runs its original start routine.
+#[cfg(windows)]
+extern "system" fn attributed_thread_start(start: *mut c_void) -> u32 {
+    // The pointer was created by `ThreadSpawnListener` and is only used by this thread
+    let start = unsafe { Box::from_raw(start.cast::<ThreadStart>()) };
+    set_attributed_execution(SPAWNED_THREAD);
+    let routine = unsafe {
+        std::mem::transmute::<usize, unsafe extern "system" fn(*mut c_void) -> u32>(start.routine)
+    };
+    unsafe { routine(start.parameter as *mut c_void) }
+}
+
 /// The listener to one of the function which access is targeted
 #[derive(Debug)]
 struct FunctionListener {
@@ -78,10 +185,8 @@ struct FunctionListener {
     function_pointer: NativePointer,
     /// Policy applied to this function
     policy: FunctionPolicy,
-    /// A flag to only trigger analysis when the targeted function is called from fuzzed code
-    /// Otherwise we would also trigger the analysis for calls from the fuzzer code which we want
-    /// to avoid.
-    interception_switch: Arc<Mutex<InterceptionSwitch>>,
+    /// Calls are recorded in the behavior profile instead of being blocked
+    audit: bool,
 }
 
 use frida_gum::interceptor::PointCut;
@@ -90,7 +195,7 @@ impl FunctionListener {
         match invoc_context.point_cut() {
             PointCut::Enter => {
                 let mut parameters = vec![];
-                for i in 0..self.policy.nb_parameters {
+                for i in 0..self.policy.nb_parameters() {
                     parameters.push(invoc_context.arg(i));
                 }
                 Context::EntryContext(parameters)
@@ -103,11 +208,114 @@ impl FunctionListener {
         let policy_context = self.policy_context_from_invoc_context(invoc_context);
         self.policy.should_block(&policy_context)
     }
+
+    /// Record the violation of the policy and stop the execution.
+    /// The fuzzer reports it as a policy violation and not as a regular crash.
+    ///
+    /// A thread started by the fuzzed code may make the call between two executions, there is no
+    /// execution to stop then. The violation is reported with the input of the last execution
+    /// when the next one ends and the call is not blocked, so that the thread is not lost for the
+    /// next executions.
+    fn block_call(&self, invoc_context: &InvocationContext) {
+        let policy_context = self.policy_context_from_invoc_context(invoc_context);
+        let message = self.policy.policy_infringement_message(&policy_context);
+        log::error!("Intercepting call to [{}].\n{message}", self.function_name);
+        let execution = attributed_execution();
+        PolicyViolation::record(
+            &self.policy,
+            violation_context(&policy_context, &self.policy.parameters),
+            message,
+            execution,
+        );
+        if execution == current_execution() && EXECUTION_RUNNING.load(Ordering::SeqCst) {
+            // The calls made while the execution is torn down are not attributed to the fuzzed
+            // code
+            set_attributed_execution(0);
+            stop_execution()
+        }
+        log::error!(
+            "The call was made between two executions, it is not blocked and is reported with \
+            the input of execution #{execution}"
+        );
+    }
+
+    /// Record the call in the behavior profile with the policy that would have blocked it
+    fn audit_call(&mut self, invoc_context: &InvocationContext) {
+        let policy_context = self.policy_context_from_invoc_context(invoc_context);
+        let blocked_by = self.policy.should_block(&policy_context).then(|| {
+            log::warn!(
+                "Audit mode, not blocking: {}",
+                self.policy.policy_infringement_message(&policy_context)
+            );
+            self.policy.blocking_rule().map_or_else(
+                || self.policy.description.clone(),
+                |rule| rule.description.clone(),
+            )
+        });
+        match &policy_context {
+            Context::EntryContext(parameters) => {
+                let arguments = decode_arguments(parameters, &self.policy.parameters);
+                BehaviorProfile::record_call(&self.function_name, &arguments, blocked_by);
+            }
+            // The call was recorded on entry
+            Context::LeaveContext(_) => BehaviorProfile::record_violation(blocked_by),
+        }
+    }
+}
+
+/// Maximum length of the strings decoded from the arguments of unknown type
+const MAX_DECODED_STRING_LENGTH: usize = 256;
+
+/// Decode the arguments of a monitored function according to its signature.
+/// Arguments of unknown type are decoded as strings if they look like one.
+fn decode_arguments(parameters: &[usize], signature: &[ArgType]) -> Vec<DecodedArgument> {
+    let args = Arguments::new(parameters);
+    parameters
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| {
+            let string = match signature.get(index).copied().unwrap_or(ArgType::Unknown) {
+                ArgType::Str | ArgType::Path => args.string(index).ok().flatten(),
+                #[cfg(not(target_env = "msvc"))]
+                ArgType::SockAddr => args
+                    .sockaddr(index)
+                    .ok()
+                    .flatten()
+                    .map(|address| address.to_string()),
+                ArgType::Unknown => args
+                    .c_str(index)
+                    .ok()
+                    .flatten()
+                    .and_then(|string| string.into_string().ok())
+                    .filter(|string| {
+                        !string.is_empty()
+                            && string.len() <= MAX_DECODED_STRING_LENGTH
+                            && !string.chars().any(char::is_control)
+                    }),
+                _ => None,
+            };
+            DecodedArgument { value, string }
+        })
+        .collect()
+}
+
+/// Convert the policy context into a context that can be reported
+fn violation_context(context: &Context, signature: &[ArgType]) -> ViolationContext {
+    match context {
+        Context::EntryContext(parameters) => ViolationContext::Entry {
+            arguments: decode_arguments(parameters, signature),
+        },
+        Context::LeaveContext(return_value) => ViolationContext::Exit {
+            return_value: *return_value,
+        },
+    }
 }
 
 impl InvocationListener for FunctionListener {
     fn on_enter(&mut self, context: InvocationContext) {
-        if self.interception_switch.lock().unwrap().is_active() {
+        // Only analyze calls from the fuzzed code and the threads it started.
+        // Otherwise we would also trigger the analysis for calls from the fuzzer code.
+        if is_attributed_thread() {
             // Check the deny rules of the function
             log::info!("#{} Entering: {:?}", context.thread_id(), self);
             // We drop the flag before function that may panic.
@@ -115,34 +323,26 @@ impl InvocationListener for FunctionListener {
             // Otherwise we'd have a deadlock
             // drop(flag);
 
-            if self.policy_should_block(&context) {
-                panic!(
-                    "Intercepting call to [{}].\n{}",
-                    self.function_name,
-                    self.policy.policy_infringement_message(
-                        &self.policy_context_from_invoc_context(&context)
-                    )
-                );
+            if self.audit {
+                self.audit_call(&context);
+            } else if self.policy_should_block(&context) {
+                self.block_call(&context);
             }
         }
     }
 
     fn on_leave(&mut self, context: InvocationContext) {
-        if self.interception_switch.lock().unwrap().is_active() {
+        if is_attributed_thread() {
             // Check the deny rules of the function
             log::info!("#{} Leaving: {:?}", context.thread_id(), self);
             // We drop the flag before function that may panic.
             // The fuzzer panic_hook will need to access it.
             // Otherwise we'd have a deadlock
             // drop(flag);
-            if self.policy_should_block(&context) {
-                panic!(
-                    "Intercepting returning function [{}].\n{}",
-                    self.function_name,
-                    self.policy.policy_infringement_message(
-                        &self.policy_context_from_invoc_context(&context)
-                    )
-                );
+            if self.audit {
+                self.audit_call(&context);
+            } else if self.policy_should_block(&context) {
+                self.block_call(&context);
             }
         }
     }
@@ -187,27 +387,36 @@ impl FridaRuntime for FunctionListenerRuntime {
         for listener in self.function_listeners.iter_mut() {
             interceptor.attach(listener.function_pointer, listener);
         }
+        // Track the threads started by the fuzzed code
+        if let Some(listener) = self.thread_spawn_listener.as_mut() {
+            interceptor.attach(listener.function_pointer, listener);
+        }
 
         // NOTE this is not the ideal way but seems to work
-        // We modify the panic hook so that the `SyscallIsolationRuntime`
-        // is deactivated when crashing and does not continue monitoring
-        // the fuzzer code
+        // We modify the panic hook so that the panicking thread is not
+        // attributed to the fuzzed code anymore and the fuzzer code
+        // handling the crash is not monitored
         let old_hook = std::panic::take_hook();
-        let switch = self.switch.clone();
         std::panic::set_hook(Box::new(move |panic_info| {
-            switch.lock().unwrap().deactivate();
+            set_attributed_execution(0);
             old_hook(panic_info);
         }));
     }
 
     fn pre_exec<I: Input + HasTargetBytes>(&mut self, _input: &I) -> Result<(), Error> {
+        EXECUTION.fetch_add(1, Ordering::SeqCst);
+        EXECUTION_RUNNING.store(true, Ordering::SeqCst);
         Ok(())
     }
 
     fn deinit(&mut self, _gum: &Gum) {}
 
     fn post_exec<I: Input + HasTargetBytes>(&mut self, _input: &I) -> Result<(), Error> {
-        self.switch.lock().unwrap().deactivate();
+        EXECUTION_RUNNING.store(false, Ordering::SeqCst);
+        set_attributed_execution(0);
+        if let Some(profile_file) = &self.audit_profile {
+            BehaviorProfile::save_recorded_periodically(profile_file)?;
+        }
         Ok(())
     }
 }
@@ -216,7 +425,13 @@ impl FunctionListenerRuntime {
     /// Creates a [`SyscallIsolationRuntime`]
     /// Setup listeners for the monitored libc functions provided.
     /// Setup listener for the tauri command being fuzzed.
-    pub fn new(fuzz_policy: FuzzPolicy, harness_address: usize) -> Result<Self, Error> {
+    /// With an `audit_profile` file the calls breaking the policy are not blocked, all the
+    /// calls to the monitored functions are recorded in the behavior profile saved in this file.
+    pub fn new(
+        fuzz_policy: FuzzPolicy,
+        harness_address: usize,
+        audit_profile: Option<PathBuf>,
+    ) -> Result<Self, Error> {
         log::debug!("{:#?}", modules_info());
 
         // println!("{:#?}", modules_info());
@@ -237,97 +452,148 @@ impl FunctionListenerRuntime {
         // println!("{:#?}", exports_in_module(&lib.name));
 
         let mut listeners: Vec<FunctionListener> = vec![];
-        let switch = Arc::new(Mutex::new(InterceptionSwitch { flag: false }));
+        let mut hook_report = HookReport::default();
 
         // Create function listeners from the fuzz policy received
         for function_policy in fuzz_policy {
-            // Get the function lib
-            let func_ptr = find_symbol_in_modules(&function_policy);
-
-            if let Some(func_ptr) = func_ptr {
-                // Create listener
+            let hooks = find_functions_in_modules(&function_policy);
+            // A Rust function may have several instances, each of them is monitored
+            for address in hooks.iter().filter_map(|hook| hook.address) {
                 let listener = FunctionListener {
                     function_name: function_policy.name.clone(),
-                    policy: function_policy,
-                    function_pointer: func_ptr,
-                    interception_switch: switch.clone(),
+                    policy: function_policy.clone(),
+                    function_pointer: NativePointer(address as *mut c_void),
+                    audit: audit_profile.is_some(),
                 };
 
                 log::info!("listener: {:?}", listener);
                 listeners.push(listener);
             }
+            hook_report.hooks.extend(hooks);
+        }
+
+        // Policies running without some of their hooks must be noticed
+        if hook_report.all_attached() {
+            log::info!("{hook_report}");
+        } else if hook_report.failed_required().next().is_none() {
+            log::warn!("{hook_report}");
+        } else {
+            log::error!("{hook_report}");
+        }
+        hook_report.check_required_hooks()?;
+        hook_report.check_strict_mode()?;
+
+        let thread_spawn_listener = Module::find_export_by_name(None, THREAD_SPAWN_FUNCTION)
+            .filter(|func_ptr| !func_ptr.is_null())
+            .map(|function_pointer| ThreadSpawnListener { function_pointer });
+        if thread_spawn_listener.is_none() {
+            log::warn!(
+                "Function {THREAD_SPAWN_FUNCTION} was not found, \
+                threads started by the fuzzed code will not be monitored"
+            );
         }
 
         let res = FunctionListenerRuntime {
             harness_pointer: NativePointer(harness_address as *mut core::ffi::c_void),
             function_listeners: listeners,
-            switch,
+            thread_spawn_listener,
+            audit_profile,
+            hook_report,
             is_init: Arc::new(Mutex::new(false)),
         };
 
         Ok(res)
     }
+
+    /// Hooks attached for the policy
+    pub fn hook_report(&self) -> &HookReport {
+        &self.hook_report
+    }
 }
 
-fn find_symbol_in_modules(policy: &FunctionPolicy) -> Option<NativePointer> {
-    let lib = Module::enumerate_modules()
+/// Resolve the functions monitored by `policy` without attaching hooks on them.
+/// This gives the same report as the one emitted when the fuzzer starts.
+pub fn resolve_hooks(policy: &FuzzPolicy) -> HookReport {
+    // Modules and symbols are enumerated with Frida
+    let _gum = Gum::obtain();
+    HookReport {
+        hooks: policy.iter().flat_map(find_functions_in_modules).collect(),
+    }
+}
+
+/// Find the functions monitored by `policy`.
+/// A Rust function may resolve to several functions, such as the monomorphized instances of a
+/// generic function.
+fn find_functions_in_modules(policy: &FunctionPolicy) -> Vec<HookAttachment> {
+    let failed = |module: Option<String>, status| HookAttachment {
+        function: policy.name.clone(),
+        lib: policy.lib.clone(),
+        module,
+        symbol: None,
+        address: None,
+        status,
+        optional: policy.is_optional,
+    };
+
+    let Some(lib) = Module::enumerate_modules()
         .into_iter()
         .find(|m| m.path.contains(&policy.lib))
-        .unwrap_or_else(|| panic!("Failed to find library for policy {policy:#?}"));
-
-    let function_name = if policy.is_rust_function {
-        // If the function is a Rust we have to find it among mangled names
-        let parsed_tokens = policy.name.split("::");
-
-        let mut symbols: Vec<String> = Module::enumerate_symbols(&lib.name)
-            .into_iter()
-            .map(|symbol| symbol.name)
-            .collect();
-        // Add the export symbols to look for
-        let export_names = Module::enumerate_exports(&lib.name)
-            .into_iter()
-            .map(|export| export.name);
-        symbols.extend(export_names);
-
-        for token in parsed_tokens {
-            symbols.retain(|symbol| symbol.contains(token));
-        }
-        // Remove the $got version of the function we are searching
-        symbols.retain(|symbol| !symbol.contains("$got"));
-        // Remove the $GT version of the function we are searching
-        symbols.retain(|symbol| !symbol.contains("$GT"));
-
-        match symbols.len() {
-            // It's possible that certain functions are required by policy
-            // but not loaded in binary because they were not used
-            0 => {
-                return None;
-            }
-
-            // We have found exactly one matching symbol
-            1 => symbols.first().unwrap().clone(),
+    else {
+        log::error!(
+            "Library {} of function {} was not found, it will not be monitored",
+            policy.lib,
+            policy.name
+        );
+        return vec![failed(None, HookStatus::ModuleNotFound)];
+    };
 
-            // Multiple symbols have been found that should not be possible
-            _ => {
-                panic!("Multiple symbol found for {}: {:?}", policy.name, symbols);
-            }
-        }
+    let functions = if policy.is_rust_function {
+        find_rust_functions(policy, &lib.name)
     } else {
-        policy.name.clone()
+        find_function(policy, &lib.name).into_iter().collect()
     };
+    if functions.is_empty() {
+        return vec![failed(Some(lib.name), HookStatus::SymbolNotFound)];
+    }
+    functions
+        .into_iter()
+        .map(|(symbol, address)| HookAttachment {
+            function: policy.name.clone(),
+            lib: policy.lib.clone(),
+            module: Some(lib.name.clone()),
+            symbol: Some(symbol),
+            address: Some(address),
+            status: HookStatus::Attached,
+            optional: policy.is_optional,
+        })
+        .collect()
+}
 
-    let func_ptr =
-        // Get the function pointer
-        // Search in the exports first
-        Module::find_export_by_name(Some(&lib.name), &function_name).unwrap_or_else(|| {
-            // Else search in the symbols
-            Module::find_symbol_by_name(&lib.name, &function_name).unwrap_or_else(|| {
-                panic!(
-                    "Failed to find init interceptor for function {}",
-                    policy.name
-                )
-            })
-        }) ;
+/// Find the function monitored by `policy` by its symbol name, returns the symbol and its address
+fn find_function(policy: &FunctionPolicy, module_name: &str) -> Option<(String, usize)> {
+    // Get the function pointer
+    // Search in the exports first, else search in the symbols
+    let Some(func_ptr) = Module::find_export_by_name(Some(module_name), &policy.name)
+        .or_else(|| Module::find_symbol_by_name(module_name, &policy.name))
+    else {
+        // Policies hook whole families of libc functions and some of them are only provided by
+        // certain libc implementations or versions, such as `renameat2` or `statx`.
+        // Functions that are not optional make the runtime creation fail
+        if policy.is_optional {
+            log::warn!(
+                "Optional function {} was not found in lib {}, it will not be monitored",
+                policy.name,
+                policy.lib
+            );
+        } else {
+            log::error!(
+                "Function {} was not found in lib {}",
+                policy.name,
+                policy.lib
+            );
+        }
+        return None;
+    };
 
     if func_ptr.is_null() {
         panic!(
@@ -335,7 +601,67 @@ fn find_symbol_in_modules(policy: &FunctionPolicy) -> Option<NativePointer> {
             policy.name, policy.lib
         );
     }
-    Some(func_ptr)
+    Some((policy.name.clone(), func_ptr.0 as usize))
+}
+
+/// Find the instances of the Rust function monitored by `policy` by matching its path against
+/// the demangled symbols and exports of the module.
+/// Returns the demangled symbols and their addresses.
+fn find_rust_functions(policy: &FunctionPolicy, module_name: &str) -> Vec<(String, usize)> {
+    let pattern = RustFunctionPattern::new(&policy.name);
+    let symbols = Module::enumerate_symbols(module_name)
+        .into_iter()
+        .map(|symbol| (symbol.name, symbol.address))
+        .chain(
+            Module::enumerate_exports(module_name)
+                .into_iter()
+                .map(|export| (export.name, export.address)),
+        )
+        // Skip the GOT entries of the functions
+        .filter(|(name, address)| !name.contains("$got") && *address != 0)
+        .filter_map(|(name, address)| demangle(&name).map(|path| (path, address)));
+
+    // A symbol and an export may refer to the same function
+    let mut functions = BTreeMap::new();
+    let mut candidates = BTreeSet::new();
+    let same_name_suffix = format!("::{}", pattern.function_name());
+    for (path, address) in symbols {
+        if pattern.matches(&path) {
+            functions.insert(address, path);
+        } else if path.ends_with(&same_name_suffix) {
+            candidates.insert(path);
+        }
+    }
+
+    if functions.is_empty() {
+        // Functions of the policy may not be in the binary because they are not used, but this
+        // is also the symptom of a typo in the path of the function.
+        // Functions that are not optional make the runtime creation fail
+        let candidates = candidates.iter().take(10).collect::<Vec<_>>();
+        if policy.is_optional {
+            log::warn!(
+                "Optional Rust function {} was not found in {module_name}, it will not be \
+                monitored. Functions with the same name: {candidates:?}",
+                policy.name
+            );
+        } else {
+            log::error!(
+                "Rust function {} was not found in {module_name}. Functions with the same name: \
+                {candidates:?}",
+                policy.name
+            );
+        }
+    } else {
+        log::debug!(
+            "Rust function {} resolved to {} instances",
+            policy.name,
+            functions.len()
+        );
+    }
+    functions
+        .into_iter()
+        .map(|(address, path)| (path, address))
+        .collect()
 }
 
 #[allow(dead_code)]
@@ -415,7 +741,7 @@ fn export_details_to_string(e: &ExportDetails) -> String {
 impl Debug for FunctionListenerRuntime {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut dbg_me = f.debug_struct("SyscallIsolationRuntime");
-        dbg_me.field("activated", &self.switch.lock().unwrap().is_active());
+        dbg_me.field("attributed_execution", &attributed_execution());
         dbg_me.field(
             "function_listeners",
             &self
@@ -427,3 +753,37 @@ impl Debug for FunctionListenerRuntime {
         dbg_me.finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    // A worker of a thread pool started during the first execution runs the tasks of the next
+    // ones, their calls are attributed to the execution that is running
+    #[test]
+    fn spawned_threads_are_attributed_to_the_running_execution() {
+        let (task_sender, tasks) = mpsc::channel::<Box<dyn FnOnce() + Send>>();
+        let worker = std::thread::spawn(move || {
+            // Done by `attributed_thread_start` for the threads started by the fuzzed code
+            set_attributed_execution(SPAWNED_THREAD);
+            for task in tasks {
+                task();
+            }
+        });
+        for _ in 0..3 {
+            let execution = EXECUTION.fetch_add(1, Ordering::SeqCst) + 1;
+            let (result_sender, result) = mpsc::channel();
+            task_sender
+                .send(Box::new(move || {
+                    result_sender.send(attributed_execution()).unwrap();
+                }))
+                .unwrap();
+            assert_eq!(result.recv().unwrap(), execution);
+        }
+        drop(task_sender);
+        worker.join().unwrap();
+        // Other threads are not attributed
+        assert_eq!(attributed_execution(), 0);
+    }
+}
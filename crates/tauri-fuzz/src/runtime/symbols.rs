@@ -0,0 +1,208 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Resolution of the Rust functions monitored by a policy.
+//!
+//! Symbols are demangled with both the legacy and the v0 mangling schemes and compared with the
+//! full path of the function given in the policy.
+//! The two schemes don't render paths the same way, `<std::process::Command>::spawn` in v0 is
+//! `std::process::Command::spawn` in legacy, so demangled paths and patterns are normalized
+//! before being compared.
+
+/// Pattern on the path of a Rust function.
+///
+/// `*` matches any sequence of characters. Generic arguments are ignored unless the pattern
+/// specifies some, so that all the monomorphized instances of a generic function match.
+/// For example `core::ptr::drop_in_place` matches every instance of `drop_in_place` while
+/// `core::ptr::drop_in_place::<std::process::*>` only matches the instances on types of
+/// `std::process`.
+#[derive(Debug, Clone)]
+pub(crate) struct RustFunctionPattern {
+    pattern: String,
+    with_generics: bool,
+}
+
+impl RustFunctionPattern {
+    pub(crate) fn new(path: &str) -> Self {
+        let pattern = normalize(path);
+        let with_generics = pattern.contains('<');
+        RustFunctionPattern {
+            pattern,
+            with_generics,
+        }
+    }
+
+    /// Check if the demangled and normalized `path` matches the pattern
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        if self.with_generics {
+            wildcard_match(&self.pattern, path)
+        } else {
+            wildcard_match(&self.pattern, &strip_generics(path))
+        }
+    }
+
+    /// Last segment of the path in the pattern, such as `spawn` for
+    /// `std::process::Command::spawn`
+    pub(crate) fn function_name(&self) -> String {
+        let path = strip_generics(&self.pattern);
+        path.rsplit("::").next().unwrap_or_default().to_string()
+    }
+}
+
+/// Demangle a Rust symbol mangled with the legacy or the v0 scheme.
+/// The path is normalized and has no hash. Returns `None` if the symbol is not a Rust symbol.
+pub(crate) fn demangle(symbol: &str) -> Option<String> {
+    let demangled = rustc_demangle::try_demangle(symbol).ok()?;
+    Some(normalize(&format!("{demangled:#}")))
+}
+
+/// Normalize a demangled path or a pattern so that paths demangled with the legacy and the v0
+/// schemes can be compared.
+/// The turbofish is removed from generic arguments and the self type of inherent methods is
+/// unwrapped.
+fn normalize(path: &str) -> String {
+    let path = path.replace("::<", "<");
+    // `<std::process::Command>::spawn` is the v0 form of `std::process::Command::spawn`, trait
+    // methods such as `<std::fs::File as std::io::Read>::read` are kept as they are
+    if let Some(rest) = path.strip_prefix('<') {
+        if let Some(end) = closing_bracket(rest) {
+            let (self_type, method) = (&rest[..end], &rest[end + 1..]);
+            if !self_type.contains(" as ") && method.starts_with("::") {
+                return format!("{self_type}{method}");
+            }
+        }
+    }
+    path
+}
+
+/// Index of the `>` closing a `<` that was just opened
+fn closing_bracket(path: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut previous = None;
+    for (index, c) in path.char_indices() {
+        match c {
+            '<' => depth += 1,
+            // The arrow of the return type of a function pointer is not a bracket
+            '>' if previous != Some('-') => {
+                if depth == 0 {
+                    return Some(index);
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+/// Remove the generic arguments from a normalized path.
+/// Brackets that are not preceded by a name, such as the ones of a trait method, are kept.
+fn strip_generics(path: &str) -> String {
+    let mut stripped = String::with_capacity(path.len());
+    let mut depth = 0usize;
+    let mut previous = None;
+    for c in path.chars() {
+        match c {
+            '<' if depth > 0 => depth += 1,
+            '<' if stripped.ends_with(|p: char| p.is_alphanumeric() || p == '_') => depth = 1,
+            '>' if depth > 0 && previous != Some('-') => depth -= 1,
+            _ if depth > 0 => {}
+            _ => stripped.push(c),
+        }
+        previous = Some(c);
+    }
+    stripped
+}
+
+/// Match `text` against `pattern` where `*` matches any sequence of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and of the text it was matched against
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` match one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_SPAWN: &str = "_ZN3std7process7Command5spawn17h0123456789abcdefE";
+    const V0_SPAWN: &str = "_RNvMs_NtCs4fqI2P2rA04_3std7processNtB4_7Command5spawn";
+    const V0_DROP_STRING: &str =
+        "_RINvNtCs1234_4core3ptr13drop_in_placeNtNtCs5678_5alloc6string6StringEB4_";
+
+    #[test]
+    fn demangle_both_schemes() {
+        assert_eq!(
+            demangle(LEGACY_SPAWN).unwrap(),
+            "std::process::Command::spawn"
+        );
+        assert_eq!(
+            demangle(&format!("{LEGACY_SPAWN}.llvm.1234")).unwrap(),
+            "std::process::Command::spawn"
+        );
+        assert_eq!(demangle(V0_SPAWN).unwrap(), "std::process::Command::spawn");
+        assert_eq!(
+            demangle(V0_DROP_STRING).unwrap(),
+            "core::ptr::drop_in_place<alloc::string::String>"
+        );
+        assert_eq!(demangle("open"), None);
+    }
+
+    #[test]
+    fn match_full_paths() {
+        let spawn = RustFunctionPattern::new("std::process::Command::spawn");
+        assert!(spawn.matches(&demangle(LEGACY_SPAWN).unwrap()));
+        assert!(spawn.matches(&demangle(V0_SPAWN).unwrap()));
+        assert!(!spawn.matches("std::process::Command::spawn::{closure#0}"));
+        assert!(!spawn.matches("my_app::Command::spawn"));
+        assert_eq!(spawn.function_name(), "spawn");
+
+        let inherent = RustFunctionPattern::new("<std::process::Command>::spawn");
+        assert!(inherent.matches(&demangle(V0_SPAWN).unwrap()));
+
+        let wildcard = RustFunctionPattern::new("std::process::Child::*wait*");
+        assert!(wildcard.matches("std::process::Child::try_wait"));
+        assert!(wildcard.matches("std::process::Child::wait_with_output"));
+        assert!(!wildcard.matches("std::process::Child::kill"));
+    }
+
+    #[test]
+    fn match_generic_instances() {
+        let drop = RustFunctionPattern::new("core::ptr::drop_in_place");
+        assert!(drop.matches(&demangle(V0_DROP_STRING).unwrap()));
+        assert!(drop.matches("core::ptr::drop_in_place<std::rt::lang_start<()>::{{closure}}>"));
+
+        let drop_string = RustFunctionPattern::new("core::ptr::drop_in_place::<alloc::*>");
+        assert!(drop_string.matches(&demangle(V0_DROP_STRING).unwrap()));
+        assert!(!drop_string.matches("core::ptr::drop_in_place<std::process::Child>"));
+
+        let push = RustFunctionPattern::new("alloc::vec::Vec::push");
+        assert!(push.matches("alloc::vec::Vec<T,A>::push"));
+        assert!(push.matches(&normalize("<alloc::vec::Vec<u8>>::push")));
+        assert!(push.matches("alloc::vec::Vec<fn() -> u8>::push"));
+
+        let read = RustFunctionPattern::new("<std::fs::File as std::io::Read>::read");
+        assert!(read.matches(&normalize("<std::fs::File as std::io::Read>::read")));
+        assert!(!read.matches("std::fs::File::read"));
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Inputs encoding a sequence of command invocations.
+//!
+//! A sequence input is a list of calls. Each call is made of a selector byte choosing the invoked
+//! command, the length of its arguments as a little-endian `u16` and the bytes used to create
+//! the arguments. The last call is truncated at the end of the input.
+//! Calls don't depend on each other so removing a call leaves the other calls unchanged.
+//!
+//! When a sequence breaks the policy, [`minimize_sequences`] removes the calls that are not needed
+//! to reproduce the violation. A policy violation can't be recovered from so every candidate is
+//! replayed in its own process. This takes time and runs after fuzzing, when the fuzz target is
+//! started with [`MINIMIZE_ENV_VAR`].
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use libafl::{generators::Generator, inputs::BytesInput, state::HasRand, Error};
+use libafl_bolts::rands::Rand;
+use tauri_fuzz_policies::fuzz_target::{
+    MINIMIZE_ENV_VAR, POLICY_VIOLATION_EXIT_CODE, REPLAY_ENV_VAR,
+};
+
+use crate::json_mutations::below;
+use crate::replay::inputs_in_dir;
+
+/// Maximum number of calls decoded from a sequence input
+pub const MAX_SEQUENCE_LEN: usize = 16;
+/// Size of the selector and of the arguments length that start each call
+const CALL_HEADER_SIZE: usize = 3;
+/// Maximum number of calls in the generated sequences
+const MAX_GENERATED_LEN: usize = 4;
+/// Maximum duration of the replay of a candidate during the minimization
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A call of a sequence input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceCall<'a> {
+    /// Chooses the invoked command among the fuzzed commands
+    pub selector: u8,
+    /// Bytes used to create the arguments of the command
+    pub args: &'a [u8],
+    /// Position of the call in the input
+    range: Range<usize>,
+}
+
+impl SequenceCall<'_> {
+    /// Index of the invoked command among `nb_commands` commands
+    pub fn command_index(&self, nb_commands: usize) -> usize {
+        usize::from(self.selector) % nb_commands
+    }
+}
+
+/// Split a sequence input into its calls.
+/// Bytes after the first [`MAX_SEQUENCE_LEN`] calls are ignored.
+pub fn split_sequence(bytes: &[u8]) -> Vec<SequenceCall<'_>> {
+    let mut calls = vec![];
+    let mut start = 0;
+    while start < bytes.len() && calls.len() < MAX_SEQUENCE_LEN {
+        let args_start = (start + CALL_HEADER_SIZE).min(bytes.len());
+        let args_len = match bytes[start..args_start] {
+            [_, low, high] => usize::from(u16::from_le_bytes([low, high])),
+            _ => 0,
+        };
+        let end = (args_start + args_len).min(bytes.len());
+        calls.push(SequenceCall {
+            selector: bytes[start],
+            args: &bytes[args_start..end],
+            range: start..end,
+        });
+        start = end;
+    }
+    calls
+}
+
+/// Encode calls made of a selector and the bytes of the arguments into a sequence input.
+/// Arguments longer than `u16::MAX` are truncated.
+pub fn encode_sequence<'a>(calls: impl IntoIterator<Item = (u8, &'a [u8])>) -> Vec<u8> {
+    let mut bytes = vec![];
+    for (selector, args) in calls {
+        let args = &args[..args.len().min(usize::from(u16::MAX))];
+        let args_len = u16::try_from(args.len()).unwrap_or(u16::MAX);
+        bytes.push(selector);
+        bytes.extend_from_slice(&args_len.to_le_bytes());
+        bytes.extend_from_slice(args);
+    }
+    bytes
+}
+
+/// Sequence input without its call at `index`
+fn without_call(bytes: &[u8], index: usize) -> Vec<u8> {
+    split_sequence(bytes)
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .flat_map(|(_, call)| &bytes[call.range.clone()])
+        .copied()
+        .collect()
+}
+
+/// Remove the calls of `bytes` that are not needed to reproduce the policy violation, as checked by
+/// `reproduces`. Returns `None` if every call is needed.
+fn remove_unneeded_calls<F>(bytes: &[u8], mut reproduces: F) -> Result<Option<Vec<u8>>, Error>
+where
+    F: FnMut(&[u8]) -> Result<bool, Error>,
+{
+    let mut bytes = bytes.to_vec();
+    let mut nb_removed = 0;
+    // Start from the last calls, the first ones usually prepare the state of the app
+    for index in (0..split_sequence(&bytes).len()).rev() {
+        let candidate = without_call(&bytes, index);
+        if reproduces(&candidate)? {
+            bytes = candidate;
+            nb_removed += 1;
+        }
+    }
+    Ok((nb_removed > 0).then_some(bytes))
+}
+
+/// Minimize the sequence stored at `input_path`, or every sequence of the directory.
+/// The minimized sequences are written next to the inputs with the `min` extension.
+pub(crate) fn minimize_sequences(path: &Path) -> Result<(), Error> {
+    let inputs = if path.is_dir() {
+        // Skip the results of previous minimizations
+        inputs_in_dir(path)?
+            .into_iter()
+            .filter(|input| {
+                input
+                    .extension()
+                    .is_none_or(|ext| ext != "min" && ext != "candidate")
+            })
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    for input in &inputs {
+        match minimize_sequence(input) {
+            Ok(Some(minimized)) => println!("Minimized sequence: {}", minimized.display()),
+            Ok(None) => println!("Every call of {} is needed", input.display()),
+            Err(e) => println!("Failed to minimize {}: {e}", input.display()),
+        }
+    }
+    Ok(())
+}
+
+/// Remove the calls of the sequence stored at `input_path` that are not needed to break the
+/// policy.
+/// The minimized sequence is written next to the input with the `min` extension and its path is
+/// returned, `None` if every call is needed.
+fn minimize_sequence(input_path: &Path) -> Result<Option<PathBuf>, Error> {
+    let exe = std::env::current_exe()?;
+    if !violates_policy(&exe, input_path)? {
+        return Err(Error::illegal_argument(
+            "the input does not violate the policy",
+        ));
+    }
+    let candidate_path = input_path.with_extension("candidate");
+    let bytes = std::fs::read(input_path)?;
+    let minimized = remove_unneeded_calls(&bytes, |candidate| {
+        std::fs::write(&candidate_path, candidate)?;
+        violates_policy(&exe, &candidate_path)
+    });
+    std::fs::remove_file(&candidate_path)?;
+
+    let Some(minimized) = minimized? else {
+        return Ok(None);
+    };
+    let minimized_path = input_path.with_extension("min");
+    std::fs::write(&minimized_path, minimized)?;
+    Ok(Some(minimized_path))
+}
+
+/// Replay the input in a new process and check whether it breaks the policy
+fn violates_policy(exe: &Path, input_path: &Path) -> Result<bool, Error> {
+    let mut child = Command::new(exe)
+        .env(REPLAY_ENV_VAR, input_path)
+        .env_remove(MINIMIZE_ENV_VAR)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.code() == Some(POLICY_VIOLATION_EXIT_CODE));
+        }
+        if start.elapsed() > REPLAY_TIMEOUT {
+            // A candidate that hangs does not reproduce the violation
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(false);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Generates short sequences of calls with random selectors and arguments
+#[derive(Debug, Clone)]
+pub struct SequenceGenerator {
+    max_args_size: usize,
+}
+
+impl SequenceGenerator {
+    /// Create a generator whose calls have arguments of at most `max_args_size` bytes
+    pub fn new(max_args_size: usize) -> Self {
+        SequenceGenerator { max_args_size }
+    }
+}
+
+impl<S> Generator<BytesInput, S> for SequenceGenerator
+where
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<BytesInput, Error> {
+        let rand = state.rand_mut();
+        let nb_calls = 1 + below(rand, MAX_GENERATED_LEN);
+        let calls: Vec<(u8, Vec<u8>)> = (0..nb_calls)
+            .map(|_| {
+                let args_size = below(rand, self.max_args_size + 1);
+                // Truncation is intended, any byte is a valid selector or argument byte
+                #[allow(clippy::cast_possible_truncation)]
+                let selector = rand.next() as u8;
+                #[allow(clippy::cast_possible_truncation)]
+                let args = (0..args_size).map(|_| rand.next() as u8).collect();
+                (selector, args)
+            })
+            .collect();
+        Ok(BytesInput::new(encode_sequence(
+            calls
+                .iter()
+                .map(|(selector, args)| (*selector, args.as_slice())),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_encoded_sequence() {
+        let bytes = encode_sequence([(0, &b"login"[..]), (3, &b""[..]), (1, &b"export"[..])]);
+        let calls = split_sequence(&bytes);
+        let decoded: Vec<(u8, &[u8])> = calls.iter().map(|c| (c.selector, c.args)).collect();
+        assert_eq!(
+            decoded,
+            vec![(0, &b"login"[..]), (3, &b""[..]), (1, &b"export"[..])]
+        );
+        assert_eq!(calls[2].command_index(2), 1);
+    }
+
+    #[test]
+    fn split_truncated_sequence() {
+        // The length of the last call exceeds the input and the second header is incomplete
+        let calls = split_sequence(&[7, 10, 0, b'a', b'b']);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].args, b"ab");
+        assert_eq!(split_sequence(&[7, 0, 0, 2, 1]).len(), 2);
+        assert!(split_sequence(&[]).is_empty());
+
+        let too_long = encode_sequence(std::iter::repeat_n((0, &b"x"[..]), MAX_SEQUENCE_LEN + 1));
+        assert_eq!(split_sequence(&too_long).len(), MAX_SEQUENCE_LEN);
+    }
+
+    #[test]
+    fn only_needed_calls_are_kept() {
+        let bytes = encode_sequence([
+            (0, &b"login"[..]),
+            (1, &b"open"[..]),
+            (2, &b"log"[..]),
+            (3, &b"export"[..]),
+        ]);
+        // The violation needs a login followed by an export
+        let reproduces = |candidate: &[u8]| {
+            let selectors: Vec<u8> = split_sequence(candidate)
+                .iter()
+                .map(|c| c.selector)
+                .collect();
+            let login = selectors.iter().position(|s| *s == 0);
+            let export = selectors.iter().position(|s| *s == 3);
+            Ok(matches!((login, export), (Some(l), Some(e)) if l < e))
+        };
+        assert_eq!(
+            remove_unneeded_calls(&bytes, reproduces).unwrap(),
+            Some(encode_sequence([(0, &b"login"[..]), (3, &b"export"[..])]))
+        );
+
+        let needed = encode_sequence([(0, &b"login"[..]), (3, &b"export"[..])]);
+        assert_eq!(remove_unneeded_calls(&needed, reproduces).unwrap(), None);
+    }
+
+    #[test]
+    fn remove_call_keeps_other_calls() {
+        let bytes = encode_sequence([(0, &b"login"[..]), (1, &b"open"[..]), (2, &b"export"[..])]);
+        let removed = without_call(&bytes, 1);
+        assert_eq!(
+            removed,
+            encode_sequence([(0, &b"login"[..]), (2, &b"export"[..])])
+        );
+    }
+}
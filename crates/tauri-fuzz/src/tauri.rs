@@ -3,11 +3,29 @@
 
 //! Module with utilities functions to fuzz Tauri applications
 
+mod acl;
+mod events;
 mod from_random_bytes;
 mod macros;
+mod mock_app;
+mod protocol;
+mod sequence;
 mod test_helpers;
 mod utils;
 
-pub use from_random_bytes::FromRandomBytes;
+pub use acl::{
+    invoke_command_from_origin, load_acl_manifests, load_capabilities, load_window_labels,
+    mock_context_with_app_acl, setup_context_with_app_acl, AclOracle, InvokeOrigin,
+};
+pub use events::{emit_event, event_payload, mock_context_allowing_emit};
+pub use from_random_bytes::{FromRandomBytes, FromRandomBytesError, RandomBytes};
+pub use mock_app::{MockAppHarness, MockAppHarnessBuilder};
+pub use protocol::{
+    handle_async_protocol_request, handle_protocol_request, protocol_request, ProtocolContext,
+    ProtocolResponder, ProtocolResponse,
+};
+pub use sequence::{invoke_sequence, invoke_sequence_and_check, SequenceCommand};
+/// Derive [`FromRandomBytes`] for structs and enums
+pub use tauri_fuzz_derive::FromRandomBytes;
 pub use test_helpers::*;
 pub use utils::*;
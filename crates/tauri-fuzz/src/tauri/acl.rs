@@ -0,0 +1,621 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Access control of the fuzzed Tauri application.
+//!
+//! Plugin commands such as `plugin:fs|read_file` are only reachable through the capabilities
+//! granted to the app. Fuzzing them with the app's own capabilities and the permissions of its
+//! plugins shows what an attacker controlling the frontend can actually reach.
+//!
+//! The [`AclOracle`] checks that the app enforces its access control: a command invoked from a
+//! window or an origin that the capabilities don't allow must be rejected.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use libafl::executors::ExitKind;
+use tauri::ipc::{Origin, RuntimeAuthority};
+use tauri::test::{mock_context, noop_assets, MockRuntime};
+use tauri::webview::InvokeRequest;
+use tauri::{Context, Runtime, Url};
+use tauri_utils::acl::capability::{Capability, CapabilityFile};
+use tauri_utils::acl::manifest::Manifest;
+use tauri_utils::acl::resolved::Resolved;
+use tauri_utils::acl::APP_ACL_KEY;
+
+use super::utils::{invoke_command_and_check, plugin_acl, TAURI_PROTOCOL_URL};
+use super::{MockAppHarness, RandomBytes};
+use crate::ResponseAssertion;
+
+/// Permission manifests of the app and its plugins, relative to the Tauri directory.
+/// This file is generated by `tauri-build` when building the app.
+const ACL_MANIFESTS_FILE: &str = "gen/schemas/acl-manifests.json";
+/// Directory containing the capability files, relative to the Tauri directory
+const CAPABILITIES_DIR: &str = "capabilities";
+/// Configuration of the app, relative to the Tauri directory
+const CONFIG_FILE: &str = "tauri.conf.json";
+/// Label Tauri gives to a window configured without one. This is also the main window of the
+/// app when neither its configuration nor its capabilities declare a window.
+pub(crate) const DEFAULT_WINDOW_LABEL: &str = "main";
+
+/// Load the permission manifests of the app and its plugins, indexed by plugin name.
+/// `tauri_dir` is the directory of the app containing `tauri.conf.json`.
+pub fn load_acl_manifests(tauri_dir: impl AsRef<Path>) -> BTreeMap<String, Manifest> {
+    let path = tauri_dir.as_ref().join(ACL_MANIFESTS_FILE);
+    let manifests = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!("Failed to read {path:?}, build the Tauri app to generate it: {e}")
+    });
+    serde_json::from_str(&manifests).unwrap_or_else(|e| panic!("Failed to parse {path:?}: {e}"))
+}
+
+/// Load all the capabilities declared in the capability files of the app, indexed by identifier.
+/// `tauri_dir` is the directory of the app containing `tauri.conf.json`.
+pub fn load_capabilities(tauri_dir: impl AsRef<Path>) -> BTreeMap<String, Capability> {
+    let mut files = vec![];
+    capability_files(&tauri_dir.as_ref().join(CAPABILITIES_DIR), &mut files);
+    files.sort();
+
+    let mut capabilities = BTreeMap::new();
+    for path in files {
+        let content = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read capability file {path:?}: {e}"));
+        let capability_file: CapabilityFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+            _ => serde_json::from_str(&content).map_err(|e| e.to_string()),
+        }
+        .unwrap_or_else(|e| panic!("Failed to parse capability file {path:?}: {e}"));
+
+        let file_capabilities = match capability_file {
+            CapabilityFile::Capability(capability) => vec![capability],
+            CapabilityFile::List(capabilities) | CapabilityFile::NamedList { capabilities } => {
+                capabilities
+            }
+        };
+        for capability in file_capabilities {
+            capabilities.insert(capability.identifier.clone(), capability);
+        }
+    }
+    capabilities
+}
+
+/// Load the labels of the windows declared in the configuration of the app, in their order.
+/// `tauri_dir` is the directory of the app containing `tauri.conf.json`.
+/// Windows created by the code of the app are not known, the list is empty if the app has no
+/// configuration file.
+pub fn load_window_labels(tauri_dir: impl AsRef<Path>) -> Vec<String> {
+    let path = tauri_dir.as_ref().join(CONFIG_FILE);
+    let Ok(config) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let config: serde_json::Value =
+        serde_json::from_str(&config).unwrap_or_else(|e| panic!("Failed to parse {path:?}: {e}"));
+    config["app"]["windows"]
+        .as_array()
+        .map(|windows| {
+            windows
+                .iter()
+                .map(|window| {
+                    window["label"]
+                        .as_str()
+                        .unwrap_or(DEFAULT_WINDOW_LABEL)
+                        .to_string()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Capability files contained in `dir` and its subdirectories
+fn capability_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read capabilities directory {dir:?}: {e}"));
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            // Schemas generated by `tauri-build` are not capabilities
+            if !path.ends_with("schemas") {
+                capability_files(&path, files);
+            }
+        } else if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("json" | "toml")
+        ) {
+            files.push(path);
+        }
+    }
+}
+
+/// Setup a Tauri application context with the access control of the real app located in
+/// `tauri_dir`: its capability files and the permission manifests of its plugins.
+/// Plugin commands invoked on an app built with this context are accepted or rejected like in
+/// the real app.
+pub fn setup_context_with_app_acl<R: Runtime>(
+    context: &mut Context<R>,
+    tauri_dir: impl AsRef<Path>,
+) {
+    let tauri_dir = tauri_dir.as_ref();
+    set_runtime_authority(
+        context,
+        load_acl_manifests(tauri_dir),
+        load_capabilities(tauri_dir),
+    );
+}
+
+/// Mock context with the access control of the real app located in `tauri_dir`,
+/// see [`setup_context_with_app_acl`]
+pub fn mock_context_with_app_acl(tauri_dir: impl AsRef<Path>) -> Context<MockRuntime> {
+    let mut context = mock_context(noop_assets());
+    setup_context_with_app_acl(&mut context, tauri_dir);
+    context
+}
+
+/// Resolve the `capabilities` against the permissions in `acl` and make them the access control
+/// of the `context`
+pub(crate) fn set_runtime_authority<R: Runtime>(
+    context: &mut Context<R>,
+    acl: BTreeMap<String, Manifest>,
+    capabilities: BTreeMap<String, Capability>,
+) {
+    let resolved = Resolved::resolve(&acl, capabilities, tauri_utils::platform::Target::current())
+        .expect("Failed to resolve the capabilities against the permissions");
+    *context.runtime_authority_mut() = RuntimeAuthority::new(acl, resolved);
+}
+
+/// Label of a window that is not declared in any capability
+const UNTRUSTED_LABEL: &str = "untrusted";
+/// Remote URL that is not declared in any capability
+const UNTRUSTED_URL: &str = "https://untrusted.example.com";
+/// Maximum length of the labels, subdomains and paths generated from the input
+const MAX_GENERATED_LEN: usize = 16;
+/// Characters of the labels, subdomains and paths generated from the input
+const GENERATED_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_";
+/// Schemes that a generated URL can use instead of the scheme of a known URL
+const URL_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// Window and URL from which a command is invoked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvokeOrigin {
+    /// Label of the window and of its webview
+    pub label: String,
+    /// URL loaded in the webview
+    pub url: Url,
+}
+
+impl InvokeOrigin {
+    /// The window `label` loading the local frontend of the app
+    pub fn local(label: impl Into<String>) -> Self {
+        InvokeOrigin {
+            label: label.into(),
+            url: TAURI_PROTOCOL_URL.parse().unwrap(),
+        }
+    }
+
+    /// Origin of the IPC call as seen by the access control.
+    /// The frontend is local when served by the Tauri protocol.
+    fn ipc_origin(&self) -> Origin {
+        if self.url.scheme() == "tauri" || self.url.domain() == Some("tauri.localhost") {
+            Origin::Local
+        } else {
+            Origin::Remote {
+                url: self.url.clone(),
+            }
+        }
+    }
+}
+
+/// Oracle that flags commands that run while the capabilities of the app deny them.
+///
+/// It resolves the same capabilities and permissions as the app under test, independently
+/// from the app. A denied invocation must be rejected by the app with an ACL error.
+/// The oracle also creates the origins to fuzz from the input, starting from the known origins:
+/// the windows and remote URLs declared in the capabilities, the windows of the app and an
+/// untrusted window and URL.
+pub struct AclOracle {
+    authority: RuntimeAuthority,
+    /// Whether the app restricts its own commands, otherwise they are always allowed
+    has_app_acl: bool,
+    /// Label of the main window of the app
+    main_label: String,
+    labels: Vec<String>,
+    urls: Vec<Url>,
+    origins: Vec<InvokeOrigin>,
+}
+
+impl AclOracle {
+    /// Oracle for the capabilities resolved against the permissions in `acl`.
+    /// The main window is the first window declared in the capabilities.
+    pub fn new(
+        acl: BTreeMap<String, Manifest>,
+        capabilities: BTreeMap<String, Capability>,
+    ) -> Self {
+        AclOracle::with_windows(acl, capabilities, &[])
+    }
+
+    /// Same as [`AclOracle::new`] for an app with the windows labelled `windows`, the first one
+    /// is its main window
+    pub fn with_windows(
+        acl: BTreeMap<String, Manifest>,
+        capabilities: BTreeMap<String, Capability>,
+        windows: &[String],
+    ) -> Self {
+        // Glob patterns are not labels
+        let capability_labels: Vec<String> = capabilities
+            .values()
+            .flat_map(|capability| capability.windows.iter().chain(&capability.webviews))
+            .filter(|label| !label.contains(['*', '?', '[']))
+            .cloned()
+            .collect();
+        let main_label = windows
+            .first()
+            .or(capability_labels.first())
+            .map_or(DEFAULT_WINDOW_LABEL, String::as_str)
+            .to_string();
+
+        let mut labels = BTreeSet::from([main_label.clone(), UNTRUSTED_LABEL.to_string()]);
+        labels.extend(windows.iter().cloned());
+        labels.extend(capability_labels);
+        let mut urls = BTreeSet::from([TAURI_PROTOCOL_URL.to_string(), UNTRUSTED_URL.to_string()]);
+        for capability in capabilities.values() {
+            if let Some(remote) = &capability.remote {
+                // A URL matching the pattern is also matched by its variations generated from the
+                // input
+                urls.extend(
+                    remote
+                        .urls
+                        .iter()
+                        .map(|url| url.replace('*', "fuzz"))
+                        .filter(|url| Url::parse(url).is_ok()),
+                );
+            }
+        }
+        let labels: Vec<String> = labels.into_iter().collect();
+        let urls: Vec<Url> = urls.iter().map(|url| url.parse().unwrap()).collect();
+        let origins = labels
+            .iter()
+            .flat_map(|label| {
+                urls.iter().map(|url| InvokeOrigin {
+                    label: label.clone(),
+                    url: url.clone(),
+                })
+            })
+            .collect();
+
+        let has_app_acl = acl.contains_key(APP_ACL_KEY);
+        let resolved =
+            Resolved::resolve(&acl, capabilities, tauri_utils::platform::Target::current())
+                .expect("Failed to resolve the capabilities against the permissions");
+        AclOracle {
+            authority: RuntimeAuthority::new(acl, resolved),
+            has_app_acl,
+            main_label,
+            labels,
+            urls,
+            origins,
+        }
+    }
+
+    /// Oracle for the real app located in `tauri_dir`, see [`setup_context_with_app_acl`].
+    /// The main window is the first window of the configuration of the app, or the first one of
+    /// its capabilities.
+    pub fn for_app(tauri_dir: impl AsRef<Path>) -> Self {
+        let tauri_dir = tauri_dir.as_ref();
+        AclOracle::with_windows(
+            load_acl_manifests(tauri_dir),
+            load_capabilities(tauri_dir),
+            &load_window_labels(tauri_dir),
+        )
+    }
+
+    /// Oracle for the same access control as [`super::setup_context_with_plugin`]
+    pub fn for_plugin(plugin_name: &str, permission_file_toml: &str, capability: &str) -> Self {
+        let (acl, capabilities) = plugin_acl(plugin_name, permission_file_toml, capability);
+        AclOracle::new(acl, capabilities)
+    }
+
+    /// Whether the capabilities allow `command` to be invoked from `origin`.
+    /// `command` is the name used in the IPC request such as `plugin:fs|read_file`.
+    pub fn is_allowed(&self, command: &str, origin: &InvokeOrigin) -> bool {
+        // Commands of the app are only restricted when the app defines its own permissions
+        if !command.starts_with("plugin:") && !self.has_app_acl {
+            return true;
+        }
+        self.authority
+            .resolve_access(command, &origin.label, &origin.label, &origin.ipc_origin())
+            .is_some()
+    }
+
+    /// Assertion on the response of `command` invoked from `origin`: if the capabilities deny
+    /// the invocation, the command must have been rejected by the app
+    pub fn assertion(&self, command: &str, origin: &InvokeOrigin) -> ResponseAssertion {
+        let allowed = self.is_allowed(command, origin);
+        let name = format!(
+            "{command} is rejected when invoked from window {:?} at {}",
+            origin.label, origin.url
+        );
+        let command = command.to_string();
+        ResponseAssertion::new(name, move |response| {
+            allowed || matches!(response, Err(error) if is_acl_rejection(error, &command))
+        })
+    }
+
+    /// Label of the main window of the app
+    pub fn main_label(&self) -> &str {
+        &self.main_label
+    }
+
+    /// The main window of the app loading its local frontend
+    pub fn main_origin(&self) -> InvokeOrigin {
+        InvokeOrigin::local(&self.main_label)
+    }
+
+    /// Known origins from which the commands are fuzzed, every known window with every known URL
+    pub fn origins(&self) -> &[InvokeOrigin] {
+        &self.origins
+    }
+
+    /// Create the window and URL invoking a command from the input.
+    /// The label is one of the known windows or is generated. The URL is one of the known URLs,
+    /// possibly with another scheme, subdomain, port or path so that the URL patterns of the
+    /// capabilities are also fuzzed.
+    pub fn origin_from_bytes(&self, input: &mut RandomBytes) -> InvokeOrigin {
+        let label = match self.labels.get(input.choose_index(self.labels.len() + 1)) {
+            Some(label) => label.clone(),
+            None => generated_part(input),
+        };
+        let mut url = self.urls[input.choose_index(self.urls.len())].clone();
+        // Changes that the URL does not support are ignored
+        match input.choose_index(5) {
+            0 => {}
+            1 => {
+                let _ = url.set_scheme(URL_SCHEMES[input.choose_index(URL_SCHEMES.len())]);
+            }
+            2 => {
+                if let Some(host) = url.host_str() {
+                    let host = format!("{}.{host}", generated_part(input));
+                    let _ = url.set_host(Some(&host));
+                }
+            }
+            3 => {
+                let port = u16::try_from(input.choose_index(usize::from(u16::MAX) + 1))
+                    .unwrap_or_default();
+                let _ = url.set_port(Some(port));
+            }
+            _ => url.set_path(&generated_part(input)),
+        }
+        InvokeOrigin { label, url }
+    }
+
+    /// Create the origin invoking a command from its JSON body.
+    /// The JSON inputs have no bytes left for the origin, it is derived from the hash of the
+    /// body so that the same input is always invoked from the same origin.
+    pub fn origin_from_json(&self, body: &serde_json::Value) -> InvokeOrigin {
+        let hash = libafl_bolts::hash_std(body.to_string().as_bytes()).to_le_bytes();
+        self.origin_from_bytes(&mut RandomBytes::new(&hash))
+    }
+}
+
+impl std::fmt::Debug for AclOracle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AclOracle")
+            .field("has_app_acl", &self.has_app_acl)
+            .field("main_label", &self.main_label)
+            .field("origins", &self.origins)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Label, subdomain or path of at most [`MAX_GENERATED_LEN`] characters taken from the input
+fn generated_part(input: &mut RandomBytes) -> String {
+    let size = 1 + input.choose_index(MAX_GENERATED_LEN);
+    input
+        .take(size)
+        .iter()
+        .map(|byte| char::from(GENERATED_CHARS[usize::from(*byte) % GENERATED_CHARS.len()]))
+        .collect()
+}
+
+/// Whether `error` is the error returned by Tauri when its access control rejects `command`.
+/// Release builds of Tauri only name the command, debug builds name the permission key and
+/// the command and explain why it is not allowed.
+fn is_acl_rejection(error: &serde_json::Value, command: &str) -> bool {
+    let Some(message) = error.as_str() else {
+        return false;
+    };
+    if message == format!("Command {command} not allowed by ACL") {
+        return true;
+    }
+    let (key, command_name) = command
+        .strip_prefix("plugin:")
+        .and_then(|command| command.split_once('|'))
+        .unwrap_or((APP_ACL_KEY, command));
+    message
+        .strip_prefix(&format!("{key}.{command_name} "))
+        .is_some_and(|reason| reason.starts_with("not allowed") || reason.starts_with("denied"))
+}
+
+/// Invoke a command from the window and URL of `origin` and wait for its response.
+/// The `oracle` checks that the command was rejected if the capabilities deny it, the input is
+/// reported as a solution otherwise. The other `assertions` are also checked on the response,
+/// see [`invoke_command_and_check`].
+pub fn invoke_command_from_origin(
+    app: &mut MockAppHarness,
+    origin: &InvokeOrigin,
+    mut request: InvokeRequest,
+    timeout: Duration,
+    oracle: &AclOracle,
+    assertions: &[ResponseAssertion],
+) -> ExitKind {
+    let webview = app.webview_at(origin);
+    request.url = origin.url.clone();
+    let acl_assertion = oracle.assertion(&request.cmd, origin);
+    invoke_command_and_check(
+        webview,
+        request,
+        timeout,
+        std::iter::once(&acl_assertion).chain(assertions),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tauri_dir() -> PathBuf {
+        PathBuf::from(std::env!("CARGO_MANIFEST_DIR")).join("assets/acl_app")
+    }
+
+    #[test]
+    fn capabilities_are_loaded() {
+        let capabilities = load_capabilities(tauri_dir());
+        assert_eq!(
+            capabilities.keys().collect::<Vec<_>>(),
+            ["default", "read-only", "remove-other-window"]
+        );
+    }
+
+    #[test]
+    fn manifests_are_loaded() {
+        let acl = load_acl_manifests(tauri_dir());
+        let fs = acl.get("fs").unwrap();
+        assert!(fs.permissions.contains_key("allow-read-file"));
+    }
+
+    #[test]
+    fn plugin_commands_follow_app_acl() {
+        use crate::tauri::{create_invoke_request, invoke_command, CommandArgs};
+        use tauri_plugin_fs::FsExt;
+
+        let app = tauri::test::mock_builder()
+            .plugin(tauri_plugin_fs::init())
+            .build(mock_context_with_app_acl(tauri_dir()))
+            .unwrap();
+        let foo = PathBuf::from(std::env!("CARGO_MANIFEST_DIR")).join("assets/foo.txt");
+        let _ = app.fs_scope().allow_file(&foo);
+        let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+
+        let mut args = CommandArgs::new();
+        args.insert("path", foo.to_string_lossy().into_owned());
+        let request = create_invoke_request(Some("fs".into()), "read_file", args);
+        let res: Result<Vec<u8>, String> = invoke_command(&webview, request);
+        assert!(res.is_ok());
+
+        // Only the other window may remove files
+        let missing_file = foo.with_file_name("missing.txt");
+        let mut args = CommandArgs::new();
+        args.insert("path", missing_file.to_string_lossy().into_owned());
+        let request = create_invoke_request(Some("fs".into()), "remove", args);
+        let res: Result<(), String> = invoke_command(&webview, request);
+        let error = serde_json::Value::String(res.unwrap_err());
+        assert!(is_acl_rejection(&error, "plugin:fs|remove"), "{error}");
+        assert!(!is_acl_rejection(&error, "plugin:fs|read_file"));
+    }
+
+    fn origin(label: &str, url: &str) -> InvokeOrigin {
+        InvokeOrigin {
+            label: label.to_string(),
+            url: url.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn oracle_follows_capabilities() {
+        let oracle = AclOracle::for_app(tauri_dir());
+        assert_eq!(oracle.main_label(), "main");
+        let main = oracle.main_origin();
+        assert!(oracle.is_allowed("plugin:fs|read_file", &main));
+        assert!(!oracle.is_allowed("plugin:fs|remove", &main));
+        assert!(oracle.is_allowed("plugin:fs|remove", &origin("other", TAURI_PROTOCOL_URL)));
+        assert!(!oracle.is_allowed(
+            "plugin:fs|read_file",
+            &origin(UNTRUSTED_LABEL, TAURI_PROTOCOL_URL)
+        ));
+        assert!(!oracle.is_allowed("plugin:fs|read_file", &origin("main", UNTRUSTED_URL)));
+        // The app does not restrict its own commands
+        assert!(oracle.is_allowed("greet", &origin(UNTRUSTED_LABEL, UNTRUSTED_URL)));
+
+        // No capability is given to the settings window of the configuration
+        assert!(!oracle.is_allowed(
+            "plugin:fs|read_file",
+            &origin("settings", TAURI_PROTOCOL_URL)
+        ));
+
+        let labels: BTreeSet<_> = oracle.origins().iter().map(|o| o.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            BTreeSet::from(["main", "other", "settings", UNTRUSTED_LABEL])
+        );
+    }
+
+    #[test]
+    fn window_labels_are_loaded() {
+        assert_eq!(load_window_labels(tauri_dir()), ["main", "settings"]);
+        assert!(load_window_labels(tauri_dir().join("capabilities")).is_empty());
+    }
+
+    #[test]
+    fn origins_are_generated_from_bytes() {
+        let oracle = AclOracle::for_app(tauri_dir());
+        // A known window and URL
+        let origin = oracle.origin_from_bytes(&mut RandomBytes::new(&[0, 0, 0]));
+        assert_eq!(origin, oracle.origins()[0]);
+        // A generated label of 3 characters
+        let generated_label = u8::try_from(oracle.labels.len()).unwrap();
+        let origin =
+            oracle.origin_from_bytes(&mut RandomBytes::new(&[generated_label, 2, 0, 1, 2]));
+        assert_eq!(origin.label, "abc");
+        // A generated path of 2 characters
+        let origin = oracle.origin_from_bytes(&mut RandomBytes::new(&[0, 0, 4, 1, 7, 8]));
+        assert!(origin.url.path().ends_with("hi"), "{}", origin.url);
+        // The same JSON body is always invoked from the same origin
+        let body = serde_json::json!({"path": "/etc/passwd"});
+        assert_eq!(
+            oracle.origin_from_json(&body),
+            oracle.origin_from_json(&body)
+        );
+    }
+
+    #[test]
+    fn acl_rejections_are_recognized() {
+        let release = serde_json::json!("Command plugin:fs|remove not allowed by ACL");
+        assert!(is_acl_rejection(&release, "plugin:fs|remove"));
+        assert!(!is_acl_rejection(&release, "plugin:fs|read_file"));
+        let debug = serde_json::json!("fs.remove not allowed on window \"main\", webview \"main\"");
+        assert!(is_acl_rejection(&debug, "plugin:fs|remove"));
+        let denied = serde_json::json!(format!("{APP_ACL_KEY}.greet denied on origin remote"));
+        assert!(is_acl_rejection(&denied, "greet"));
+        // Errors returned by the command itself are not rejections
+        let error = serde_json::json!("failed to remove the file: operation not allowed");
+        assert!(!is_acl_rejection(&error, "plugin:fs|remove"));
+    }
+
+    #[test]
+    fn denied_commands_are_rejected() {
+        let _lock = crate::Invocation::lock_for_test();
+        use crate::tauri::{create_invoke_request, CommandArgs};
+
+        let oracle = AclOracle::for_app(tauri_dir());
+        let mut app =
+            MockAppHarness::builder(|| tauri::test::mock_builder().plugin(tauri_plugin_fs::init()))
+                .context(|| mock_context_with_app_acl(tauri_dir()))
+                .build();
+
+        let foo = PathBuf::from(std::env!("CARGO_MANIFEST_DIR")).join("assets/foo.txt");
+        for origin in oracle.origins() {
+            let mut args = CommandArgs::new();
+            args.insert("path", foo.to_string_lossy().into_owned());
+            let request = create_invoke_request(Some("fs".into()), "read_file", args);
+            let exit_kind = invoke_command_from_origin(
+                &mut app,
+                origin,
+                request,
+                Duration::from_secs(5),
+                &oracle,
+                &[],
+            );
+            assert_eq!(exit_kind, ExitKind::Ok, "{origin:?}");
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Fuzz the handlers of events emitted by the frontend.
+//!
+//! Backend code registered with `app.listen` reacts to the events emitted by the frontend and
+//! receives their payload as JSON. The events are emitted through the IPC like the frontend
+//! does, the listeners run while the harness is running.
+
+use std::time::{Duration, Instant};
+
+use libafl::executors::ExitKind;
+use serde::Serialize;
+use tauri::test::{mock_context, noop_assets, MockRuntime};
+use tauri::{Context, WebviewWindow};
+
+use super::acl::{set_runtime_authority, DEFAULT_WINDOW_LABEL};
+use super::utils::plugin_acl;
+use super::{create_invoke_request_with_body, invoke_command_and_wait};
+
+/// Plugin of Tauri handling the events emitted by the frontend and its key in the access control
+const EVENT_PLUGIN: &str = "event";
+const EVENT_ACL_KEY: &str = "core:event";
+/// Command of the event plugin called by the frontend `emit`
+const EMIT_COMMAND: &str = "emit";
+/// Permission of the event plugin allowing the frontend to emit events
+const EMIT_PERMISSION: &str = r#"
+[[permission]]
+identifier = "allow-emit"
+description = "Enables the emit command without any pre-configured scope."
+commands.allow = ["emit", "emit_to"]
+"#;
+/// Interval at which the harness checks whether the tasks spawned by the listeners are finished
+const TASKS_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Emit `event` with `payload` from `webview` as the frontend does with `emit`.
+/// The event goes through the `plugin:event|emit` IPC command so that the access control of the
+/// app applies, an app whose capabilities don't allow the window to emit events rejects it.
+///
+/// Listeners registered with `listen` are called before the command responds, the async tasks
+/// they spawn are then waited for. Listeners that spawn work on other threads are monitored
+/// through the threads they start.
+/// If the command or the tasks are not finished after `timeout` the execution is stopped as a
+/// timeout, see [`invoke_command_and_wait`].
+pub fn emit_event(
+    webview: WebviewWindow<MockRuntime>,
+    event: &str,
+    payload: impl Serialize,
+    timeout: Duration,
+) -> ExitKind {
+    let Some(payload) = event_payload(payload) else {
+        log::error!("Failed to serialize the payload of event {event}");
+        return ExitKind::Ok;
+    };
+    let deadline = Instant::now() + timeout;
+    let nb_tasks = nb_alive_tasks();
+    let body = serde_json::json!({ "event": event, "payload": payload });
+    let request = create_invoke_request_with_body(Some(EVENT_PLUGIN.into()), EMIT_COMMAND, body);
+    let exit_kind = invoke_command_and_wait(webview, request, timeout);
+    if exit_kind != ExitKind::Ok {
+        return exit_kind;
+    }
+
+    // Tasks spawned before the event are not waited for, they belong to previous executions
+    while nb_alive_tasks() > nb_tasks {
+        if Instant::now() >= deadline {
+            crate::report::stop_timed_out_execution();
+        }
+        std::thread::sleep(TASKS_POLL_INTERVAL);
+    }
+    ExitKind::Ok
+}
+
+/// Number of tasks alive on the async runtime of Tauri
+fn nb_alive_tasks() -> usize {
+    tauri::async_runtime::handle()
+        .inner()
+        .metrics()
+        .num_alive_tasks()
+}
+
+/// Payload of an event as received by the listeners, `None` if it can't be serialized
+pub fn event_payload(payload: impl Serialize) -> Option<serde_json::Value> {
+    serde_json::to_value(payload).ok()
+}
+
+/// Mock context whose only capability allows the main window of the mock app to emit events.
+/// This is used to fuzz the listeners of an app without its own access control, see
+/// [`crate::tauri::mock_context_with_app_acl`] to use the access control of the real app.
+pub fn mock_context_allowing_emit() -> Context<MockRuntime> {
+    let capability = serde_json::json!({
+        "identifier": "fuzz-emit",
+        "description": "Allow the fuzzed window to emit events",
+        "windows": [DEFAULT_WINDOW_LABEL],
+        "permissions": [format!("{EVENT_ACL_KEY}:allow-emit")],
+    });
+    let (acl, capabilities) = plugin_acl(EVENT_ACL_KEY, EMIT_PERMISSION, &capability.to_string());
+    let mut context = mock_context(noop_assets());
+    set_runtime_authority(&mut context, acl, capabilities);
+    context
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tauri::test::mock_builder;
+    use tauri::{App, Listener};
+
+    /// App with a listener of `fuzzed-event` that records the payloads, from a spawned task if
+    /// `spawn_task` is set
+    fn app_with_listener(
+        context: Context<MockRuntime>,
+        spawn_task: bool,
+    ) -> (App<MockRuntime>, Arc<Mutex<Vec<String>>>) {
+        let app = mock_builder().build(context).unwrap();
+        let received = Arc::new(Mutex::new(vec![]));
+        let listener_received = received.clone();
+        app.listen("fuzzed-event", move |event| {
+            let received = listener_received.clone();
+            let payload = event.payload().to_string();
+            if spawn_task {
+                tauri::async_runtime::spawn(async move {
+                    std::thread::sleep(Duration::from_millis(50));
+                    received.lock().unwrap().push(payload);
+                });
+            } else {
+                received.lock().unwrap().push(payload);
+            }
+        });
+        (app, received)
+    }
+
+    fn emit(app: &App<MockRuntime>) -> ExitKind {
+        let webview =
+            tauri::WebviewWindowBuilder::new(app, DEFAULT_WINDOW_LABEL, Default::default())
+                .build()
+                .unwrap();
+        emit_event(
+            webview,
+            "fuzzed-event",
+            serde_json::json!({"name": "foo"}),
+            Duration::from_secs(5),
+        )
+    }
+
+    #[test]
+    fn listeners_receive_payload() {
+        let _lock = crate::Invocation::lock_for_test();
+        let (app, received) = app_with_listener(mock_context_allowing_emit(), false);
+        assert_eq!(emit(&app), ExitKind::Ok);
+        assert_eq!(*received.lock().unwrap(), [r#"{"name":"foo"}"#]);
+    }
+
+    #[test]
+    fn async_listeners_are_waited_for() {
+        let _lock = crate::Invocation::lock_for_test();
+        let (app, received) = app_with_listener(mock_context_allowing_emit(), true);
+        assert_eq!(emit(&app), ExitKind::Ok);
+        assert_eq!(*received.lock().unwrap(), [r#"{"name":"foo"}"#]);
+    }
+
+    #[test]
+    fn denied_events_are_not_received() {
+        let _lock = crate::Invocation::lock_for_test();
+        // The capabilities don't allow the window to emit events
+        let (app, received) = app_with_listener(mock_context(noop_assets()), false);
+        assert_eq!(emit(&app), ExitKind::Ok);
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
@@ -2,13 +2,118 @@
 // SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
 
 use std::array::TryFromSliceError;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::hash::{BuildHasher, Hash};
 use std::{str::Utf8Error, string::FromUtf8Error};
 
 /// Construct a type from arbitrary random bytes.
+///
+/// Use `#[derive(FromRandomBytes)]` to implement it for structs and enums whose fields implement
+/// it.
 pub trait FromRandomBytes<'a> {
     type Output;
     type Error;
     fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error>;
+
+    /// Construct the type from the front of `input` and consume the bytes that were used.
+    /// This is used to construct several values from the same input.
+    /// The default implementation consumes all the remaining bytes.
+    fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+        Self::from_random_bytes(input.take_rest())
+    }
+}
+
+/// Random bytes that are consumed while constructing values with [`FromRandomBytes`].
+/// Each value takes the bytes it needs from the front of the input so that the arguments of a
+/// command are constructed from different bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomBytes<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> RandomBytes<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        RandomBytes { bytes }
+    }
+
+    /// Number of bytes left
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Take `size` bytes, or less if the input is exhausted
+    pub fn take(&mut self, size: usize) -> &'a [u8] {
+        let (taken, rest) = self.bytes.split_at(size.min(self.bytes.len()));
+        self.bytes = rest;
+        taken
+    }
+
+    /// Take all the remaining bytes
+    pub fn take_rest(&mut self) -> &'a [u8] {
+        self.take(self.bytes.len())
+    }
+
+    /// Size of a variable-length value such as a string, bounded by the number of bytes left
+    pub fn byte_size(&mut self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        let size = self.bounded_int(self.len());
+        size % (self.len() + 1)
+    }
+
+    /// Whether a collection being constructed should get another element.
+    /// This is always false once the input is exhausted.
+    pub fn keep_going(&mut self) -> bool {
+        self.take(1).first().is_some_and(|byte| byte & 1 == 1)
+    }
+
+    /// Choose an index in `0..nb_choices`, this is used to choose an enum variant
+    pub fn choose_index(&mut self, nb_choices: usize) -> usize {
+        assert!(nb_choices > 0, "Can't choose among 0 choices");
+        self.bounded_int(nb_choices - 1) % nb_choices
+    }
+
+    /// Read an integer using only the bytes needed to represent `max`
+    fn bounded_int(&mut self, max: usize) -> usize {
+        let nb_bytes = if u8::try_from(max).is_ok() {
+            1
+        } else if u16::try_from(max).is_ok() {
+            2
+        } else {
+            4
+        };
+        self.take(nb_bytes)
+            .iter()
+            .fold(0, |int, byte| (int << 8) | usize::from(*byte))
+    }
+}
+
+/// Error of a value constructed from several values with [`FromRandomBytes`]
+#[derive(Debug)]
+pub struct FromRandomBytesError(Box<dyn std::error::Error + Send + Sync>);
+
+impl FromRandomBytesError {
+    pub fn new(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        FromRandomBytesError(error.into())
+    }
+}
+
+impl std::fmt::Display for FromRandomBytesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to construct value from random bytes: {}", self.0)
+    }
+}
+
+impl std::error::Error for FromRandomBytesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.0.as_ref())
+    }
 }
 
 impl FromRandomBytes<'_> for String {
@@ -20,6 +125,11 @@ impl FromRandomBytes<'_> for String {
     fn from_random_bytes(bytes: &[u8]) -> Result<Self::Output, Self::Error> {
         Ok(String::from_utf8_lossy(bytes).to_string())
     }
+
+    fn from_random_input(input: &mut RandomBytes<'_>) -> Result<Self::Output, Self::Error> {
+        let size = input.byte_size();
+        Self::from_random_bytes(input.take(size))
+    }
 }
 
 impl<'a> FromRandomBytes<'a> for &str {
@@ -29,6 +139,38 @@ impl<'a> FromRandomBytes<'a> for &str {
     fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error> {
         std::str::from_utf8(bytes)
     }
+
+    fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+        let size = input.byte_size();
+        Self::from_random_bytes(input.take(size))
+    }
+}
+
+impl FromRandomBytes<'_> for bool {
+    type Output = bool;
+    type Error = Infallible;
+
+    fn from_random_bytes(bytes: &[u8]) -> Result<Self::Output, Self::Error> {
+        Ok(bytes.first().is_some_and(|byte| byte & 1 == 1))
+    }
+
+    fn from_random_input(input: &mut RandomBytes<'_>) -> Result<Self::Output, Self::Error> {
+        Self::from_random_bytes(input.take(1))
+    }
+}
+
+impl FromRandomBytes<'_> for char {
+    type Output = char;
+    type Error = Infallible;
+
+    fn from_random_bytes(bytes: &[u8]) -> Result<Self::Output, Self::Error> {
+        let code = u32::from_random_bytes(bytes).unwrap_or_default();
+        Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn from_random_input(input: &mut RandomBytes<'_>) -> Result<Self::Output, Self::Error> {
+        Self::from_random_bytes(input.take(std::mem::size_of::<u32>()))
+    }
 }
 
 macro_rules! impl_int {
@@ -50,9 +192,193 @@ macro_rules! impl_int {
                     let first_bytes = bytes_array[0..nb_bytes].try_into().unwrap();
                     Ok(<$int>::from_be_bytes(first_bytes))
                 }
+
+                fn from_random_input(input: &mut RandomBytes<'_>) -> Result<Self::Output, Self::Error> {
+                    Self::from_random_bytes(input.take(std::mem::size_of::<$int>()))
+                }
             }
         )*
     };
 }
 
 impl_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+impl<'a, T: FromRandomBytes<'a>> FromRandomBytes<'a> for Option<T> {
+    type Output = Option<T::Output>;
+    type Error = T::Error;
+
+    fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error> {
+        Self::from_random_input(&mut RandomBytes::new(bytes))
+    }
+
+    fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+        if input.keep_going() {
+            T::from_random_input(input).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<'a, T: FromRandomBytes<'a>> FromRandomBytes<'a> for Vec<T> {
+    type Output = Vec<T::Output>;
+    type Error = T::Error;
+
+    fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error> {
+        Self::from_random_input(&mut RandomBytes::new(bytes))
+    }
+
+    fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+        let mut elements = Vec::new();
+        while input.keep_going() {
+            elements.push(T::from_random_input(input)?);
+        }
+        Ok(elements)
+    }
+}
+
+impl<'a, K, V, S> FromRandomBytes<'a> for HashMap<K, V, S>
+where
+    K: FromRandomBytes<'a>,
+    K::Output: Eq + Hash,
+    K::Error: std::error::Error + Send + Sync + 'static,
+    V: FromRandomBytes<'a>,
+    V::Error: std::error::Error + Send + Sync + 'static,
+    S: BuildHasher + Default,
+{
+    type Output = HashMap<K::Output, V::Output, S>;
+    type Error = FromRandomBytesError;
+
+    fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error> {
+        Self::from_random_input(&mut RandomBytes::new(bytes))
+    }
+
+    fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+        let mut map = HashMap::default();
+        while input.keep_going() {
+            let key = K::from_random_input(input).map_err(FromRandomBytesError::new)?;
+            let value = V::from_random_input(input).map_err(FromRandomBytesError::new)?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<'a, K, V> FromRandomBytes<'a> for BTreeMap<K, V>
+where
+    K: FromRandomBytes<'a>,
+    K::Output: Ord,
+    K::Error: std::error::Error + Send + Sync + 'static,
+    V: FromRandomBytes<'a>,
+    V::Error: std::error::Error + Send + Sync + 'static,
+{
+    type Output = BTreeMap<K::Output, V::Output>;
+    type Error = FromRandomBytesError;
+
+    fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error> {
+        Self::from_random_input(&mut RandomBytes::new(bytes))
+    }
+
+    fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+        let mut map = BTreeMap::new();
+        while input.keep_going() {
+            let key = K::from_random_input(input).map_err(FromRandomBytesError::new)?;
+            let value = V::from_random_input(input).map_err(FromRandomBytesError::new)?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($elem:ident),+) => {
+        impl<'a, $($elem),+> FromRandomBytes<'a> for ($($elem,)+)
+        where
+            $(
+                $elem: FromRandomBytes<'a>,
+                $elem::Error: std::error::Error + Send + Sync + 'static,
+            )+
+        {
+            type Output = ($($elem::Output,)+);
+            type Error = FromRandomBytesError;
+
+            fn from_random_bytes(bytes: &'a [u8]) -> Result<Self::Output, Self::Error> {
+                Self::from_random_input(&mut RandomBytes::new(bytes))
+            }
+
+            fn from_random_input(input: &mut RandomBytes<'a>) -> Result<Self::Output, Self::Error> {
+                Ok(($(
+                    $elem::from_random_input(input).map_err(FromRandomBytesError::new)?,
+                )+))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tauri::FromRandomBytes;
+
+    #[derive(Debug, PartialEq, FromRandomBytes)]
+    struct Dto {
+        id: u16,
+        name: String,
+        tags: Vec<u8>,
+        kind: Kind,
+    }
+
+    #[derive(Debug, PartialEq, FromRandomBytes)]
+    enum Kind {
+        Empty,
+        Sized(u8),
+        Named { flag: bool },
+    }
+
+    #[test]
+    fn arguments_consume_different_bytes() {
+        let mut input = RandomBytes::new(&[0, 1, 2, b'a', b'b', 3]);
+        assert_eq!(u16::from_random_input(&mut input).unwrap(), 1);
+        assert_eq!(String::from_random_input(&mut input).unwrap(), "ab");
+        assert_eq!(u8::from_random_input(&mut input).unwrap(), 3);
+        assert!(input.is_empty());
+        // Exhausted input still constructs values
+        assert_eq!(String::from_random_input(&mut input).unwrap(), "");
+        assert_eq!(Vec::<u8>::from_random_input(&mut input).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn collections_and_tuples() {
+        let bytes = [1, 7, 1, 8, 0, 1, 9];
+        assert_eq!(Vec::<u8>::from_random_bytes(&bytes).unwrap(), vec![7, 8]);
+        assert_eq!(
+            <(Option<u8>, Vec<u8>)>::from_random_bytes(&bytes).unwrap(),
+            (Some(7), vec![8])
+        );
+        let map = HashMap::<u8, bool>::from_random_bytes(&[1, 4, 1, 1, 5, 0]).unwrap();
+        assert_eq!(map, HashMap::from([(4, true), (5, false)]));
+    }
+
+    #[test]
+    fn derive_struct_and_enum() {
+        let bytes = [0, 42, 1, b'x', 1, 5, 0, 2, 1];
+        assert_eq!(
+            Dto::from_random_bytes(&bytes).unwrap(),
+            Dto {
+                id: 42,
+                name: "x".to_string(),
+                tags: vec![5],
+                kind: Kind::Named { flag: true },
+            }
+        );
+        assert_eq!(Kind::from_random_bytes(&[1, 3]).unwrap(), Kind::Sized(3));
+        assert_eq!(Kind::from_random_bytes(&[]).unwrap(), Kind::Empty);
+    }
+}
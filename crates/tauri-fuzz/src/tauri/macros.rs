@@ -1,58 +1,619 @@
+/// Definitions shared by the fuzz targets written with the `fuzz_tauri_*` macros:
+/// - `main` which reads `fuzzer_config.toml` and starts fuzzing the `harness` function, or the
+///   `json_harness` function in JSON mode when `fuzz: json(create_body)` is given
+/// - `exec_timeout()`, the maximum duration of an execution set from the fuzzer configuration
+/// - `MOCK_APP`, the mock app built on the first execution and reused by the next ones
+///
+/// It also provides `@policy_file` to load a policy file relative to the fuzz directory,
+/// `@optional` to turn an optional macro argument into an `Option` and `@args` to create the
+/// arguments of a command from random bytes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fuzz_harness {
+    (@policy_file $policy_file:literal) => {
+        ::tauri_fuzz_policies::engine::load_policy_file(
+            ::std::path::PathBuf::from(::std::env!("CARGO_MANIFEST_DIR")).join($policy_file)
+        )?
+    };
+
+    (@optional) => { None };
+    (@optional $value:expr) => { Some($value) };
+
+    (@args $bytes:expr, $($param:ident : $param_type:ty),*) => {{
+        #[allow(unused_mut)]
+        let mut params = ::tauri_fuzz::tauri::CommandArgs::new();
+        // Each parameter consumes its own part of the bytes
+        #[allow(unused_variables, unused_mut)]
+        let mut input = ::tauri_fuzz::tauri::RandomBytes::new($bytes);
+
+        $(
+            let param: $param_type = <$param_type as ::tauri_fuzz::tauri::FromRandomBytes>::from_random_input(&mut input)
+                .map_err(::tauri_fuzz::tauri::FromRandomBytesError::new)?;
+            params.insert(stringify!($param).to_string(), param);
+        )*
+
+        Ok(params)
+    }};
+
+    (@fuzz bytes, $input_mode:ident, $options:ident, $policy:ident) => {
+        // The inputs have no JSON representation
+        let _ = $input_mode;
+        ::tauri_fuzz::fuzz_main(harness, &$options, harness as *const () as usize, $policy, false);
+    };
+    (@fuzz sequence, $input_mode:ident, $options:ident, $policy:ident) => {
+        // Sequences are always decoded from bytes
+        let _ = $input_mode;
+        ::tauri_fuzz::fuzz_main_sequence(harness, &$options, harness as *const () as usize, $policy, false);
+    };
+    (@fuzz json($create_body:expr), $input_mode:ident, $options:ident, $policy:ident) => {
+        match $input_mode {
+            ::tauri_fuzz::InputMode::Bytes => {
+                ::tauri_fuzz::fuzz_main(harness, &$options, harness as *const () as usize, $policy, false);
+            }
+            ::tauri_fuzz::InputMode::Json => {
+                // The initial JSON inputs are built like the ones of the bytes mode
+                let seeds = ::tauri_fuzz::json_seeds($create_body);
+                ::tauri_fuzz::fuzz_main_json(json_harness, &$options, json_harness as *const () as usize, $policy, seeds, false);
+            }
+        }
+    };
+
+    (
+        name: $name:expr,
+        policy: $policy:expr,
+        app: $app:expr,
+        fuzz: $kind:ident $(($create_body:expr))? $(,)?
+    ) => {
+        // Maximum duration of an execution, set from the fuzzer configuration
+        static EXEC_TIMEOUT: ::std::sync::OnceLock<::std::time::Duration> = ::std::sync::OnceLock::new();
+
+        fn main() -> Result<(), ::tauri_fuzz_policies::engine::PolicyFileError> {
+            let fuzz_dir = ::std::path::PathBuf::from(::std::env!("CARGO_MANIFEST_DIR"));
+            let fuzz_config_file = fuzz_dir.join("fuzzer_config.toml");
+            let config = ::tauri_fuzz::SimpleFuzzerConfig::from_toml(fuzz_config_file, $name, fuzz_dir);
+            // A policy file given in the fuzzer configuration takes precedence
+            let policy = config.policy_or(|| Ok($policy))?;
+            let _ = EXEC_TIMEOUT.set(config.timeout());
+            let input_mode = config.input_mode();
+            let options = config.into();
+            ::tauri_fuzz::__fuzz_harness!(@fuzz $kind $(($create_body))?, input_mode, options, policy);
+            Ok(())
+        }
+
+        fn exec_timeout() -> ::std::time::Duration {
+            *EXEC_TIMEOUT.get().expect("The timeout is set before fuzzing")
+        }
+
+        thread_local! {
+            // The mock app is built on the first execution and reused by the next ones
+            static MOCK_APP: ::std::cell::RefCell<::tauri_fuzz::tauri::MockAppHarness> =
+                ::std::cell::RefCell::new($app);
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! fuzz_tauri_command {
+    // The policy is given as a policy file relative to the fuzz directory
     (
         command: $command:literal,
         path: $path:path,
+        $(tauri_dir: $tauri_dir:literal,)?
         parameters: {
-            $($param:ident : $param_type:ty),+ $(,)?
+            $($param:ident : $param_type:ty),* $(,)?
         },
+        $(assertions: [$($assertion:expr),* $(,)?],)?
+        policy_file: $policy_file:literal $(,)?
+    ) => {
+        ::tauri_fuzz::fuzz_tauri_command! {
+            command: $command,
+            path: $path,
+            $(tauri_dir: $tauri_dir,)?
+            parameters: {
+                $($param : $param_type),*
+            },
+            $(assertions: [$($assertion),*],)?
+            policy: ::tauri_fuzz::__fuzz_harness!(@policy_file $policy_file),
+        }
+    };
+
+    (
+        command: $command:literal,
+        path: $path:path,
+        $(tauri_dir: $tauri_dir:literal,)?
+        parameters: {
+            $($param:ident : $param_type:ty),* $(,)?
+        },
+        $(assertions: [$($assertion:expr),* $(,)?],)?
         policy: $policy:expr $(,)?
     ) => {
-        use tauri_fuzz::tauri::{create_invoke_request, invoke_command_minimal, CommandArgs};
-        use tauri_fuzz::SimpleFuzzerConfig;
+        use tauri_fuzz::tauri::{
+            create_invoke_request, create_invoke_request_with_body, invoke_command_and_check,
+            invoke_command_from_origin, mock_context_with_app_acl, AclOracle, CommandArgs,
+            MockAppHarness, RandomBytes,
+        };
         use libafl::inputs::{BytesInput, HasMutatorBytes};
         use libafl::prelude::ExitKind;
-        use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+        use tauri::test::mock_builder;
         use tauri::webview::InvokeRequest;
-        use tauri::WebviewWindow;
 
+        const COMMAND_NAME: &str = $command;
+        // Assertions checked on every response of the command
+        static ASSERTIONS: ::std::sync::LazyLock<Vec<::tauri_fuzz::ResponseAssertion>> =
+            ::std::sync::LazyLock::new(|| vec![$($($assertion),*)?]);
+
+        ::tauri_fuzz::__fuzz_harness! {
+            name: COMMAND_NAME,
+            policy: $policy,
+            app: {
+                let app_harness = MockAppHarness::builder(|| {
+                    mock_builder().invoke_handler(tauri::generate_handler![$path])
+                });
+                let main_label = ACL_ORACLE.with(|oracle| {
+                    oracle.as_ref().map(|oracle| oracle.main_label().to_string())
+                });
+                match (tauri_dir(), main_label) {
+                    (Some(tauri_dir), Some(main_label)) => app_harness
+                        .context(move || mock_context_with_app_acl(&tauri_dir))
+                        .main_window(main_label),
+                    _ => app_harness,
+                }
+                .build()
+            },
+            fuzz: json(|bytes| create_args(bytes).ok().map(Into::into)),
+        }
+
+        thread_local! {
+            // With the access control of the app, checks that the command is rejected when the
+            // capabilities deny it
+            static ACL_ORACLE: Option<AclOracle> = tauri_dir().map(AclOracle::for_app);
+        }
+
+        /// Directory of the app containing `tauri.conf.json` if the command is fuzzed with the
+        /// access control of the app
+        fn tauri_dir() -> Option<::std::path::PathBuf> {
+            ::tauri_fuzz::__fuzz_harness!(@optional $(
+                ::std::path::PathBuf::from(::std::env!("CARGO_MANIFEST_DIR")).join($tauri_dir)
+            )?)
+        }
+
+        fn harness(input: &BytesInput) -> ExitKind {
+            let mut input = RandomBytes::new(input.bytes());
+            // With the access control of the app the window and URL invoking the command are
+            // chosen from the first bytes
+            let origin = ACL_ORACLE.with(|oracle| {
+                oracle.as_ref().map(|oracle| oracle.origin_from_bytes(&mut input))
+            });
+            // Inputs that can't be decoded into the arguments are skipped
+            let Ok(args) = create_args(input.take_rest()) else {
+                return ExitKind::Ok;
+            };
+            let request = create_invoke_request(None, COMMAND_NAME, args);
+            invoke(origin, request)
+        }
+
+        /// Harness used in JSON mode, the input is the JSON body of the request
+        fn json_harness(input: &::tauri_fuzz::JsonInput) -> ExitKind {
+            let body = input.value().clone();
+            let origin = ACL_ORACLE.with(|oracle| {
+                oracle.as_ref().map(|oracle| oracle.origin_from_json(&body))
+            });
+            invoke(origin, create_invoke_request_with_body(None, COMMAND_NAME, body))
+        }
+
+        /// Invoke the command from `origin`, or from the main window without the access control
+        /// of the app, and wait for its response so that async commands are monitored until they
+        /// finish
+        fn invoke(origin: Option<::tauri_fuzz::tauri::InvokeOrigin>, request: InvokeRequest) -> ExitKind {
+            MOCK_APP.with_borrow_mut(|app| {
+                let webview = app.start_execution();
+                ACL_ORACLE.with(|oracle| match (oracle, origin) {
+                    (Some(oracle), Some(origin)) => {
+                        invoke_command_from_origin(app, &origin, request, exec_timeout(), oracle, &ASSERTIONS)
+                    }
+                    _ => invoke_command_and_check(webview, request, exec_timeout(), ASSERTIONS.iter()),
+                })
+            })
+        }
+
+        fn create_args(
+            bytes: &[u8],
+        ) -> Result<CommandArgs, ::tauri_fuzz::tauri::FromRandomBytesError> {
+            ::tauri_fuzz::__fuzz_harness!(@args bytes, $($param : $param_type),*)
+        }
+    }
+}
+
+/// Fuzz sequences of Tauri commands invoked on a single application.
+/// Each input decodes to a list of invocations of the given commands, see
+/// [`crate::split_sequence`]. The optional `setup` function receives the app builder, it is
+/// used to manage the state shared by the commands.
+/// The app is rebuilt for every sequence unless a `reset` function restoring its state is given.
+/// The optional `assertions` are checked on the response of each command, see
+/// [`crate::ResponseAssertion`].
+#[macro_export]
+macro_rules! fuzz_tauri_sequence {
+    // The policy is given as a policy file relative to the fuzz directory
+    (
+        name: $name:literal,
+        commands: [
+            $({
+                command: $command:literal,
+                path: $path:path,
+                parameters: {
+                    $($param:ident : $param_type:ty),* $(,)?
+                } $(,)?
+            }),+ $(,)?
+        ],
+        $(setup: $setup:expr,)?
+        $(reset: $reset:expr,)?
+        $(assertions: [$($assertion:expr),* $(,)?],)?
+        policy_file: $policy_file:literal $(,)?
+    ) => {
+        ::tauri_fuzz::fuzz_tauri_sequence! {
+            name: $name,
+            commands: [
+                $({
+                    command: $command,
+                    path: $path,
+                    parameters: {
+                        $($param : $param_type),*
+                    },
+                }),+
+            ],
+            $(setup: $setup,)?
+            $(reset: $reset,)?
+            $(assertions: [$($assertion),*],)?
+            policy: ::tauri_fuzz::__fuzz_harness!(@policy_file $policy_file),
+        }
+    };
+
+    (
+        name: $name:literal,
+        commands: [
+            $({
+                command: $command:literal,
+                path: $path:path,
+                parameters: {
+                    $($param:ident : $param_type:ty),* $(,)?
+                } $(,)?
+            }),+ $(,)?
+        ],
+        $(setup: $setup:expr,)?
+        $(reset: $reset:expr,)?
+        $(assertions: [$($assertion:expr),* $(,)?],)?
+        policy: $policy:expr $(,)?
+    ) => {
+        use tauri_fuzz::tauri::{invoke_sequence_and_check, MockAppHarness, SequenceCommand};
+        use libafl::inputs::{BytesInput, HasMutatorBytes};
+        use libafl::prelude::ExitKind;
+        use tauri::test::mock_builder;
+
+        const SEQUENCE_NAME: &str = $name;
+        // Assertions checked on every response of the commands
+        static ASSERTIONS: ::std::sync::LazyLock<Vec<::tauri_fuzz::ResponseAssertion>> =
+            ::std::sync::LazyLock::new(|| vec![$($($assertion),*)?]);
+
+        ::tauri_fuzz::__fuzz_harness! {
+            name: SEQUENCE_NAME,
+            policy: $policy,
+            app: {
+                let app_harness = MockAppHarness::builder(|| {
+                    let builder = mock_builder();
+                    $(let builder = ($setup)(builder);)?
+                    builder.invoke_handler(tauri::generate_handler![$($path),+])
+                });
+                // The app state is modified by the sequences, the app is reused only if its
+                // state can be reset
+                let app_harness = app_harness.reuse_app(false);
+                $(let app_harness = app_harness.reuse_app(true).reset($reset);)?
+                app_harness.build()
+            },
+            fuzz: sequence,
+        }
+
+        fn harness(input: &BytesInput) -> ExitKind {
+            // The whole sequence runs in the same app so that the commands share its state
+            let webview = MOCK_APP.with_borrow_mut(MockAppHarness::start_execution);
+            invoke_sequence_and_check(&webview, &commands(), input.bytes(), exec_timeout(), &ASSERTIONS)
+        }
+
+        fn commands() -> Vec<SequenceCommand> {
+            vec![$(
+                SequenceCommand {
+                    name: $command,
+                    create_args: |bytes| {
+                        ::tauri_fuzz::__fuzz_harness!(@args bytes, $($param : $param_type),*)
+                    },
+                }
+            ),+]
+        }
+    }
+}
 
+/// Fuzz a command of a Tauri plugin, such as `plugin:fs|read_file`, with the access control of
+/// the real app. `init` is the plugin initialization and `tauri_dir` the directory of the app
+/// containing `tauri.conf.json`, relative to the fuzz directory. The capabilities and permission
+/// manifests are loaded from there, see [`crate::tauri::setup_context_with_app_acl`].
+/// Each input also chooses the window and URL invoking the command, the input is a solution if
+/// the command is not rejected while the capabilities deny it, see [`crate::tauri::AclOracle`].
+/// The optional `assertions` are also checked on each response, see [`crate::ResponseAssertion`].
+#[macro_export]
+macro_rules! fuzz_tauri_plugin_command {
+    // The policy is given as a policy file relative to the fuzz directory
+    (
+        plugin: $plugin:literal,
+        command: $command:literal,
+        init: $init:expr,
+        tauri_dir: $tauri_dir:literal,
+        parameters: {
+            $($param:ident : $param_type:ty),* $(,)?
+        },
+        $(assertions: [$($assertion:expr),* $(,)?],)?
+        policy_file: $policy_file:literal $(,)?
+    ) => {
+        ::tauri_fuzz::fuzz_tauri_plugin_command! {
+            plugin: $plugin,
+            command: $command,
+            init: $init,
+            tauri_dir: $tauri_dir,
+            parameters: {
+                $($param : $param_type),*
+            },
+            $(assertions: [$($assertion),*],)?
+            policy: ::tauri_fuzz::__fuzz_harness!(@policy_file $policy_file),
+        }
+    };
+
+    (
+        plugin: $plugin:literal,
+        command: $command:literal,
+        init: $init:expr,
+        tauri_dir: $tauri_dir:literal,
+        parameters: {
+            $($param:ident : $param_type:ty),* $(,)?
+        },
+        $(assertions: [$($assertion:expr),* $(,)?],)?
+        policy: $policy:expr $(,)?
+    ) => {
+        use tauri_fuzz::tauri::{
+            create_invoke_request, create_invoke_request_with_body, invoke_command_from_origin,
+            mock_context_with_app_acl, AclOracle, CommandArgs, InvokeOrigin, MockAppHarness,
+        };
+        use libafl::inputs::{BytesInput, HasMutatorBytes};
+        use libafl::prelude::ExitKind;
+        use tauri::test::mock_builder;
+
+        const PLUGIN_NAME: &str = $plugin;
         const COMMAND_NAME: &str = $command;
+        // Assertions checked on every response of the command, in addition to the access
+        // control oracle
+        static ASSERTIONS: ::std::sync::LazyLock<Vec<::tauri_fuzz::ResponseAssertion>> =
+            ::std::sync::LazyLock::new(|| vec![$($($assertion),*)?]);
 
-        fn main() {
-            let fuzz_dir = ::std::path::PathBuf::from(::std::env!("CARGO_MANIFEST_DIR"));
-            let fuzz_config_file = fuzz_dir.join("fuzzer_config.toml");
-            let options = SimpleFuzzerConfig::from_toml(fuzz_config_file, COMMAND_NAME, fuzz_dir).into();
-            ::tauri_fuzz::fuzz_main(harness, &options, harness as *const () as usize, $policy, false);
+        // The plugin command is not part of the fuzz crate, the whole harness is monitored
+        ::tauri_fuzz::__fuzz_harness! {
+            // Plugin command names are not valid directory names on every platform
+            name: concat!($plugin, "_", $command),
+            policy: $policy,
+            app: MockAppHarness::builder(|| mock_builder().plugin($init))
+                .context(|| mock_context_with_app_acl(tauri_dir()))
+                .main_window(ACL_ORACLE.with(|oracle| oracle.main_label().to_string()))
+                .build(),
+            fuzz: json(|bytes| create_args(bytes).ok().map(Into::into)),
         }
 
-        fn setup_mock() -> WebviewWindow<MockRuntime> {
-            let app = mock_builder()
-                .invoke_handler(tauri::generate_handler![$path])
-                .build(mock_context(noop_assets()))
-                .expect("Failed to init Tauri app");
-            let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
-                .build()
-                .unwrap();
-            webview
+        thread_local! {
+            // Checks that the command is rejected when the capabilities deny it
+            static ACL_ORACLE: AclOracle = AclOracle::for_app(tauri_dir());
         }
 
+        /// Directory of the app containing `tauri.conf.json`
+        fn tauri_dir() -> ::std::path::PathBuf {
+            ::std::path::PathBuf::from(::std::env!("CARGO_MANIFEST_DIR")).join($tauri_dir)
+        }
 
         fn harness(input: &BytesInput) -> ExitKind {
-            let webview = setup_mock();
-            let _ = invoke_command_minimal(webview, create_request(input.bytes()));
-            ExitKind::Ok
+            // The window and URL invoking the command are chosen from the first bytes
+            let mut input = ::tauri_fuzz::tauri::RandomBytes::new(input.bytes());
+            let origin = ACL_ORACLE.with(|oracle| oracle.origin_from_bytes(&mut input));
+            // Inputs that can't be decoded into the arguments are skipped
+            let Ok(args) = create_args(input.take_rest()) else {
+                return ExitKind::Ok;
+            };
+            let request = create_invoke_request(Some(PLUGIN_NAME.into()), COMMAND_NAME, args);
+            invoke(&origin, request)
         }
 
-        fn create_request(bytes: &[u8]) -> InvokeRequest {
-            let mut params = CommandArgs::new();
+        /// Harness used in JSON mode, the input is the JSON body of the request
+        fn json_harness(input: &::tauri_fuzz::JsonInput) -> ExitKind {
+            let body = input.value().clone();
+            // The origin is derived from the body, there are no other bytes to choose it from
+            let origin = ACL_ORACLE.with(|oracle| oracle.origin_from_json(&body));
+            let request = create_invoke_request_with_body(Some(PLUGIN_NAME.into()), COMMAND_NAME, body);
+            invoke(&origin, request)
+        }
 
-            $(
-                let param: $param_type = <$param_type as ::tauri_fuzz::tauri::FromRandomBytes>::from_random_bytes(bytes).unwrap();
-                params.insert(stringify!($param).to_string(), param);
-            )*
+        /// Invoke the command from `origin` and wait for its response
+        fn invoke(origin: &InvokeOrigin, request: ::tauri::webview::InvokeRequest) -> ExitKind {
+            MOCK_APP.with_borrow_mut(|app| {
+                app.start_execution();
+                ACL_ORACLE.with(|oracle| {
+                    invoke_command_from_origin(app, origin, request, exec_timeout(), oracle, &ASSERTIONS)
+                })
+            })
+        }
 
-            create_invoke_request(None, COMMAND_NAME, params)
+        fn create_args(
+            bytes: &[u8],
+        ) -> Result<CommandArgs, ::tauri_fuzz::tauri::FromRandomBytesError> {
+            ::tauri_fuzz::__fuzz_harness!(@args bytes, $($param : $param_type),*)
         }
     }
 }
+
+/// Fuzz the listeners of an event emitted by the frontend.
+/// The payload of the event is built from the input, or is the raw JSON input in JSON mode.
+/// The `setup` function receives the app builder, it registers the listeners of the app, for
+/// example in `Builder::setup`.
+#[macro_export]
+macro_rules! fuzz_tauri_event {
+    // The policy is given as a policy file relative to the fuzz directory
+    (
+        event: $event:literal,
+        $(tauri_dir: $tauri_dir:literal,)?
+        setup: $setup:expr,
+        payload: $payload_type:ty,
+        policy_file: $policy_file:literal $(,)?
+    ) => {
+        ::tauri_fuzz::fuzz_tauri_event! {
+            event: $event,
+            $(tauri_dir: $tauri_dir,)?
+            setup: $setup,
+            payload: $payload_type,
+            policy: ::tauri_fuzz::__fuzz_harness!(@policy_file $policy_file),
+        }
+    };
+
+    (
+        event: $event:literal,
+        $(tauri_dir: $tauri_dir:literal,)?
+        setup: $setup:expr,
+        payload: $payload_type:ty,
+        policy: $policy:expr $(,)?
+    ) => {
+        use libafl::inputs::{BytesInput, HasMutatorBytes};
+        use libafl::prelude::ExitKind;
+        use tauri::test::mock_builder;
+        use tauri_fuzz::tauri::{
+            emit_event, event_payload, mock_context_allowing_emit, mock_context_with_app_acl,
+            AclOracle, MockAppHarness,
+        };
+
+        const EVENT_NAME: &str = $event;
+
+        ::tauri_fuzz::__fuzz_harness! {
+            name: concat!("event_", $event),
+            policy: $policy,
+            app: {
+                let app_harness = MockAppHarness::builder(|| ($setup)(mock_builder()));
+                match tauri_dir() {
+                    Some(tauri_dir) => {
+                        let main_label = AclOracle::for_app(&tauri_dir).main_label().to_string();
+                        app_harness
+                            .context(move || mock_context_with_app_acl(&tauri_dir))
+                            .main_window(main_label)
+                    }
+                    None => app_harness.context(mock_context_allowing_emit),
+                }
+                .build()
+            },
+            fuzz: json(|bytes| create_payload(bytes).ok().and_then(event_payload)),
+        }
+
+        /// Directory of the app containing `tauri.conf.json` if the event is emitted with the
+        /// access control of the app
+        fn tauri_dir() -> Option<::std::path::PathBuf> {
+            ::tauri_fuzz::__fuzz_harness!(@optional $(
+                ::std::path::PathBuf::from(::std::env!("CARGO_MANIFEST_DIR")).join($tauri_dir)
+            )?)
+        }
+
+        fn harness(input: &BytesInput) -> ExitKind {
+            // Inputs too short for the payload type are skipped
+            let Ok(payload) = create_payload(input.bytes()) else {
+                return ExitKind::Ok;
+            };
+            // The event is emitted from the main window, the listeners are waited for
+            let webview = MOCK_APP.with_borrow_mut(MockAppHarness::start_execution);
+            emit_event(webview, EVENT_NAME, payload, exec_timeout())
+        }
+
+        /// Harness used in JSON mode, the input is the JSON payload of the event
+        fn json_harness(input: &::tauri_fuzz::JsonInput) -> ExitKind {
+            let webview = MOCK_APP.with_borrow_mut(MockAppHarness::start_execution);
+            emit_event(webview, EVENT_NAME, input.value().clone(), exec_timeout())
+        }
+
+        fn create_payload(
+            bytes: &[u8],
+        ) -> Result<$payload_type, ::tauri_fuzz::tauri::FromRandomBytesError> {
+            <$payload_type as ::tauri_fuzz::tauri::FromRandomBytes>::from_random_bytes(bytes)
+                .map_err(::tauri_fuzz::tauri::FromRandomBytesError::new)
+        }
+    };
+}
+
+/// Fuzz the handler of a custom URI scheme protocol with requests built from the input.
+/// Use `handler:` for handlers given to `register_uri_scheme_protocol` and `async_handler:` for
+/// handlers given to `register_asynchronous_uri_scheme_protocol`, see
+/// [`handle_protocol_request`](crate::tauri::handle_protocol_request).
+/// The optional `setup` function receives the app builder, for example to manage the state
+/// used by the handler.
+#[macro_export]
+macro_rules! fuzz_tauri_protocol {
+    // Call the handler depending on its kind
+    (@handle handler, $app:expr, $handler:expr, $request:expr) => {
+        ::tauri_fuzz::tauri::handle_protocol_request($app, SCHEME, $handler, $request)
+    };
+    (@handle async_handler, $app:expr, $handler:expr, $request:expr) => {
+        ::tauri_fuzz::tauri::handle_async_protocol_request(
+            $app,
+            SCHEME,
+            $handler,
+            $request,
+            exec_timeout(),
+        )
+    };
+
+    // The policy is given as a policy file relative to the fuzz directory
+    (
+        scheme: $scheme:literal,
+        $kind:ident: $handler:expr,
+        $(setup: $setup:expr,)?
+        policy_file: $policy_file:literal $(,)?
+    ) => {
+        ::tauri_fuzz::fuzz_tauri_protocol! {
+            scheme: $scheme,
+            $kind: $handler,
+            $(setup: $setup,)?
+            policy: ::tauri_fuzz::__fuzz_harness!(@policy_file $policy_file),
+        }
+    };
+
+    (
+        scheme: $scheme:literal,
+        $kind:ident: $handler:expr,
+        $(setup: $setup:expr,)?
+        policy: $policy:expr $(,)?
+    ) => {
+        use libafl::inputs::{BytesInput, HasMutatorBytes};
+        use libafl::prelude::ExitKind;
+        use tauri::test::mock_builder;
+        use tauri_fuzz::tauri::{protocol_request, MockAppHarness, RandomBytes};
+
+        const SCHEME: &str = $scheme;
+
+        ::tauri_fuzz::__fuzz_harness! {
+            name: concat!("protocol_", $scheme),
+            policy: $policy,
+            app: MockAppHarness::builder(|| {
+                let builder = mock_builder();
+                $(let builder = ($setup)(builder);)?
+                builder
+            })
+            .build(),
+            // Requests are always built from bytes, they have no JSON representation
+            fuzz: bytes,
+        }
+
+        fn harness(input: &BytesInput) -> ExitKind {
+            // Requests whose url is too long are skipped
+            let Ok(request) = protocol_request(SCHEME, &mut RandomBytes::new(input.bytes())) else {
+                return ExitKind::Ok;
+            };
+            let webview = MOCK_APP.with_borrow_mut(MockAppHarness::start_execution);
+            ::tauri_fuzz::fuzz_tauri_protocol!(@handle $kind, &webview, $handler, request)
+        }
+    };
+}
@@ -0,0 +1,244 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Mock Tauri application shared by the executions of a fuzzer client.
+//!
+//! Building a Tauri `App` and its webview takes much longer than invoking a command.
+//! [`MockAppHarness`] builds the app on the first execution and reuses it for the next ones.
+//! Each fuzzer client runs in its own process and gets its own app.
+
+use tauri::test::{mock_context, noop_assets, MockRuntime};
+use tauri::{App, AppHandle, Builder, Context, Manager, WebviewWindow};
+
+use super::acl::DEFAULT_WINDOW_LABEL;
+use super::InvokeOrigin;
+
+type SetupFn = Box<dyn Fn() -> Builder<MockRuntime>>;
+type ContextFn = Box<dyn Fn() -> Context<MockRuntime>>;
+type ResetFn = Box<dyn Fn(&AppHandle<MockRuntime>)>;
+
+/// Builder of a [`MockAppHarness`]
+pub struct MockAppHarnessBuilder {
+    setup: SetupFn,
+    context: Option<ContextFn>,
+    reset: Option<ResetFn>,
+    reuse_app: bool,
+    main_label: String,
+}
+
+impl MockAppHarnessBuilder {
+    /// Function creating the context of the app, by default a context without any capability.
+    /// Use [`crate::tauri::mock_context_with_app_acl`] to invoke plugin commands with the access
+    /// control of the real app.
+    pub fn context(mut self, context: impl Fn() -> Context<MockRuntime> + 'static) -> Self {
+        self.context = Some(Box::new(context));
+        self
+    }
+
+    /// Function called by [`MockAppHarness::start_execution`] when the app is reused.
+    /// It restores the state managed by the app so that executions don't influence each other.
+    pub fn reset(mut self, reset: impl Fn(&AppHandle<MockRuntime>) + 'static) -> Self {
+        self.reset = Some(Box::new(reset));
+        self
+    }
+
+    /// Whether the app is reused across executions, `true` by default.
+    /// Otherwise a new app is built for each execution which is much slower.
+    pub fn reuse_app(mut self, reuse_app: bool) -> Self {
+        self.reuse_app = reuse_app;
+        self
+    }
+
+    /// Label of the main window whose webview is returned by [`MockAppHarness::webview`],
+    /// `main` by default. Use [`crate::tauri::AclOracle::main_label`] to get the label of the
+    /// main window of the real app.
+    pub fn main_window(mut self, label: impl Into<String>) -> Self {
+        self.main_label = label.into();
+        self
+    }
+
+    pub fn build(self) -> MockAppHarness {
+        MockAppHarness {
+            setup: self.setup,
+            context: self.context,
+            reset: self.reset,
+            reuse_app: self.reuse_app,
+            main_label: self.main_label,
+            app: None,
+        }
+    }
+}
+
+/// Provides the mock app on which a harness invokes commands.
+///
+/// ```rust,ignore
+/// thread_local! {
+///     static MOCK_APP: RefCell<MockAppHarness> = RefCell::new(
+///         MockAppHarness::builder(|| {
+///             mock_builder()
+///                 .manage(Session::default())
+///                 .invoke_handler(tauri::generate_handler![login])
+///         })
+///         .reset(|app| *app.state::<Session>().user.lock().unwrap() = None)
+///         .build(),
+///     );
+/// }
+///
+/// fn harness(input: &BytesInput) -> ExitKind {
+///     let webview = MOCK_APP.with_borrow_mut(MockAppHarness::start_execution);
+///     invoke_command_minimal(webview, create_request(input.bytes()));
+///     ExitKind::Ok
+/// }
+/// ```
+pub struct MockAppHarness {
+    setup: SetupFn,
+    context: Option<ContextFn>,
+    reset: Option<ResetFn>,
+    reuse_app: bool,
+    main_label: String,
+    app: Option<(App<MockRuntime>, WebviewWindow<MockRuntime>)>,
+}
+
+impl MockAppHarness {
+    /// `setup` creates the builder of the app with its command handlers and managed state
+    pub fn builder(setup: impl Fn() -> Builder<MockRuntime> + 'static) -> MockAppHarnessBuilder {
+        MockAppHarnessBuilder {
+            setup: Box::new(setup),
+            context: None,
+            reset: None,
+            reuse_app: true,
+            main_label: DEFAULT_WINDOW_LABEL.to_string(),
+        }
+    }
+
+    /// Prepare the app for a new execution and get its main webview.
+    /// This is called once at the entry of the harness: the app is reset if it's reused,
+    /// otherwise a new app is built.
+    pub fn start_execution(&mut self) -> WebviewWindow<MockRuntime> {
+        if !self.reuse_app {
+            self.app = None;
+        } else if let (Some((app, _)), Some(reset)) = (&self.app, &self.reset) {
+            reset(app.handle());
+        }
+        self.webview()
+    }
+
+    /// Main webview of the app for the current execution.
+    /// The app is built on the first call, it is neither reset nor rebuilt by the next calls
+    /// of the execution.
+    pub fn webview(&mut self) -> WebviewWindow<MockRuntime> {
+        if let Some((_, webview)) = &self.app {
+            return webview.clone();
+        }
+
+        let context = self
+            .context
+            .as_ref()
+            .map_or_else(|| mock_context(noop_assets()), |context| context());
+        let app = (self.setup)()
+            .build(context)
+            .expect("Failed to init Tauri app");
+        let webview = tauri::WebviewWindowBuilder::new(&app, &self.main_label, Default::default())
+            .build()
+            .expect("Failed to create the webview");
+        self.app = Some((app, webview.clone()));
+        webview
+    }
+
+    /// Webview of the window labelled as in `origin` with the URL of `origin` loaded.
+    /// The main webview is the one returned by [`MockAppHarness::webview`], other windows are
+    /// created the first time they are requested.
+    pub fn webview_at(&mut self, origin: &InvokeOrigin) -> WebviewWindow<MockRuntime> {
+        let main = self.webview();
+        let webview = if origin.label == main.label() {
+            main
+        } else {
+            let (app, _) = self.app.as_ref().expect("The app is built by `webview`");
+            app.get_webview_window(&origin.label).unwrap_or_else(|| {
+                tauri::WebviewWindowBuilder::new(app, &origin.label, Default::default())
+                    .build()
+                    .expect("Failed to create the webview")
+            })
+        };
+        webview
+            .navigate(origin.url.clone())
+            .expect("Failed to navigate the webview");
+        webview
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tauri::{create_invoke_request, invoke_command, CommandArgs};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tauri::test::mock_builder;
+
+    #[derive(Default)]
+    struct Counter(AtomicU32);
+
+    #[tauri::command]
+    fn increment(counter: tauri::State<'_, Counter>) -> u32 {
+        counter.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn increment(webview: &WebviewWindow<MockRuntime>) -> u32 {
+        let request = create_invoke_request(None, "increment", CommandArgs::new());
+        invoke_command::<u32, String>(webview, request).unwrap()
+    }
+
+    /// Value of the counter after two executions
+    fn increment_twice(harness: &mut MockAppHarness) -> u32 {
+        let mut res = 0;
+        for _ in 0..2 {
+            res = increment(&harness.start_execution());
+        }
+        res
+    }
+
+    fn setup() -> Builder<MockRuntime> {
+        mock_builder()
+            .manage(Counter::default())
+            .invoke_handler(tauri::generate_handler![increment])
+    }
+
+    #[test]
+    fn app_is_reused() {
+        let nb_setups = Rc::new(Cell::new(0));
+        let setups = nb_setups.clone();
+        let mut harness = MockAppHarness::builder(move || {
+            setups.set(setups.get() + 1);
+            setup()
+        })
+        .build();
+        assert_eq!(increment_twice(&mut harness), 2);
+        assert_eq!(nb_setups.get(), 1);
+    }
+
+    #[test]
+    fn app_is_reset() {
+        let mut harness = MockAppHarness::builder(setup)
+            .reset(|app| app.state::<Counter>().0.store(0, Ordering::SeqCst))
+            .build();
+        assert_eq!(increment_twice(&mut harness), 1);
+    }
+
+    #[test]
+    fn app_is_reset_once_per_execution() {
+        let mut harness = MockAppHarness::builder(setup)
+            .reset(|app| app.state::<Counter>().0.store(0, Ordering::SeqCst))
+            .build();
+        for _ in 0..2 {
+            assert_eq!(increment(&harness.start_execution()), 1);
+            assert_eq!(increment(&harness.webview()), 2);
+        }
+    }
+
+    #[test]
+    fn app_is_rebuilt() {
+        let mut harness = MockAppHarness::builder(setup).reuse_app(false).build();
+        assert_eq!(increment_twice(&mut harness), 1);
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Fuzz the handlers of custom URI scheme protocols.
+//!
+//! Handlers registered with `register_uri_scheme_protocol` or
+//! `register_asynchronous_uri_scheme_protocol` parse URLs and headers chosen by the webview
+//! content, which makes them prone to path traversals.
+//! The mock runtime of Tauri drops the protocols registered on the webviews it creates, and the
+//! `UriSchemeContext` and `UriSchemeResponder` given to the handlers can only be built by Tauri.
+//! The harness sends the requests built from the fuzzer input to the handler itself, with a
+//! [`ProtocolContext`] and a [`ProtocolResponder`] taken from the mock webview that provide the
+//! same methods. The closure registered in the app can then be fuzzed as is.
+
+use std::borrow::Cow;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use libafl::executors::ExitKind;
+use serde_json::{json, Map, Value};
+use tauri::http::header::{HeaderName, HeaderValue};
+use tauri::http::{Method, Request, Response};
+use tauri::{AppHandle, Manager, Runtime, WebviewWindow};
+
+use super::RandomBytes;
+
+/// Response returned by a custom protocol handler
+pub type ProtocolResponse = Response<Cow<'static, [u8]>>;
+
+/// Context of a request sent by a webview to a custom protocol, with the methods of the
+/// `UriSchemeContext` of Tauri
+pub struct ProtocolContext<'a, R: Runtime> {
+    app_handle: &'a AppHandle<R>,
+    webview_label: &'a str,
+}
+
+impl<'a, R: Runtime> ProtocolContext<'a, R> {
+    /// Handle of the app whose webview sent the request
+    pub fn app_handle(&self) -> &'a AppHandle<R> {
+        self.app_handle
+    }
+
+    /// Label of the webview that sent the request
+    pub fn webview_label(&self) -> &'a str {
+        self.webview_label
+    }
+}
+
+/// Responder given to asynchronous protocol handlers, with the methods of the
+/// `UriSchemeResponder` of Tauri
+pub struct ProtocolResponder(Box<dyn FnOnce(ProtocolResponse) + Send>);
+
+impl ProtocolResponder {
+    /// Send the response to the webview
+    pub fn respond<T: Into<Cow<'static, [u8]>>>(self, response: Response<T>) {
+        let (parts, body) = response.into_parts();
+        (self.0)(Response::from_parts(parts, body.into()))
+    }
+}
+
+/// Methods used by the fuzzed requests
+const METHODS: [Method; 6] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::HEAD,
+    Method::OPTIONS,
+];
+
+/// Headers commonly parsed by protocol handlers, other header names are built from the input
+const HEADERS: [&str; 6] = [
+    "range",
+    "content-type",
+    "origin",
+    "referer",
+    "accept",
+    "cookie",
+];
+
+/// Maximum number of headers in a fuzzed request
+const MAX_HEADERS: usize = 8;
+
+/// Url of `path` for the protocol `scheme`, as requested by the webview
+#[cfg(not(any(windows, target_os = "android")))]
+fn protocol_url(scheme: &str, path_and_query: &str) -> String {
+    format!("{scheme}://localhost{path_and_query}")
+}
+#[cfg(any(windows, target_os = "android"))]
+fn protocol_url(scheme: &str, path_and_query: &str) -> String {
+    format!("http://{scheme}.localhost{path_and_query}")
+}
+
+/// Build a request to the protocol `scheme` from random bytes.
+/// The method, path, query, headers and body of the request are taken from `input` in this
+/// order.
+/// Fails if the url is too long to be a valid `Uri`.
+///
+/// ```rust,ignore
+/// if let Ok(request) = protocol_request("asset", &mut RandomBytes::new(input.bytes())) {
+///     handle_protocol_request(&webview, "asset", asset_handler, request);
+/// }
+/// ```
+pub fn protocol_request(
+    scheme: &str,
+    input: &mut RandomBytes,
+) -> Result<Request<Vec<u8>>, tauri::http::Error> {
+    let method = METHODS[input.choose_index(METHODS.len())].clone();
+    let size = input.byte_size();
+    let path = encode_url_part(input.take(size), b"/");
+    let size = input.byte_size();
+    let query = encode_url_part(input.take(size), b"/?=&");
+    let path_and_query = if query.is_empty() {
+        format!("/{path}")
+    } else {
+        format!("/{path}?{query}")
+    };
+
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(protocol_url(scheme, &path_and_query));
+    for _ in 0..MAX_HEADERS {
+        if !input.keep_going() {
+            break;
+        }
+        let name_index = input.choose_index(HEADERS.len() + 1);
+        let name = match HEADERS.get(name_index) {
+            Some(name) => HeaderName::from_static(name),
+            None => {
+                let size = input.byte_size();
+                match HeaderName::from_bytes(input.take(size)) {
+                    Ok(name) => name,
+                    Err(_) => continue,
+                }
+            }
+        };
+        let size = input.byte_size();
+        // Invalid header values are rejected before reaching the handler in a real app
+        if let Ok(value) = HeaderValue::from_bytes(input.take(size)) {
+            builder = builder.header(name, value);
+        }
+    }
+    builder.body(input.take_rest().to_vec())
+}
+
+/// Percent-encode the bytes that can't appear in a url.
+/// `%` is kept as is so that the fuzzer can produce its own percent-encoded sequences.
+fn encode_url_part(bytes: &[u8], allowed: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~%".contains(&byte) || allowed.contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// JSON description of a request used in the solution reports
+fn request_to_json(request: &Request<Vec<u8>>) -> Value {
+    let headers: Map<String, Value> = request
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            (name.to_string(), Value::String(value))
+        })
+        .collect();
+    json!({
+        "method": request.method().as_str(),
+        "uri": request.uri().to_string(),
+        "headers": headers,
+        "body": String::from_utf8_lossy(request.body()),
+    })
+}
+
+/// JSON description of a response used in the solution reports
+fn response_to_json(response: &ProtocolResponse) -> Value {
+    json!({
+        "status": response.status().as_u16(),
+        "body_len": response.body().len(),
+    })
+}
+
+/// Send `request` from `webview` to the protocol `handler` registered for `scheme`.
+/// `handler` is the closure given to `register_uri_scheme_protocol` in the app, it receives a
+/// [`ProtocolContext`] of `webview` instead of the `UriSchemeContext`.
+pub fn handle_protocol_request<R: Runtime, T: Into<Cow<'static, [u8]>>>(
+    webview: &WebviewWindow<R>,
+    scheme: &str,
+    handler: impl Fn(ProtocolContext<'_, R>, Request<Vec<u8>>) -> Response<T>,
+    request: Request<Vec<u8>>,
+) -> ExitKind {
+    crate::Invocation::record(format!("protocol:{scheme}"), request_to_json(&request));
+    let (parts, body) = handler(protocol_context(webview), request).into_parts();
+    let response = Response::from_parts(parts, body.into());
+    crate::Invocation::record_response(Ok(response_to_json(&response)));
+    ExitKind::Ok
+}
+
+/// Send `request` from `webview` to the asynchronous protocol `handler` registered for `scheme`
+/// and wait for its response for at most `timeout`.
+/// `handler` is the closure given to `register_asynchronous_uri_scheme_protocol` in the app, it
+/// receives a [`ProtocolContext`] and a [`ProtocolResponder`] instead of the `UriSchemeContext`
+/// and `UriSchemeResponder`.
+/// Returns [`ExitKind::Timeout`] if the handler did not respond in time.
+pub fn handle_async_protocol_request<R: Runtime>(
+    webview: &WebviewWindow<R>,
+    scheme: &str,
+    handler: impl Fn(ProtocolContext<'_, R>, Request<Vec<u8>>, ProtocolResponder),
+    request: Request<Vec<u8>>,
+    timeout: Duration,
+) -> ExitKind {
+    crate::Invocation::record(format!("protocol:{scheme}"), request_to_json(&request));
+    let (tx, rx) = std::sync::mpsc::sync_channel(1);
+    let responder = ProtocolResponder(Box::new(move |response| {
+        let _ = tx.send(response_to_json(&response));
+    }));
+    handler(protocol_context(webview), request, responder);
+    match rx.recv_timeout(timeout) {
+        Ok(response) => {
+            crate::Invocation::record_response(Ok(response));
+            ExitKind::Ok
+        }
+        Err(RecvTimeoutError::Timeout) => ExitKind::Timeout,
+        // The handler dropped the responder without responding
+        Err(RecvTimeoutError::Disconnected) => ExitKind::Ok,
+    }
+}
+
+/// Context of the requests sent by `webview`
+fn protocol_context<R: Runtime>(webview: &WebviewWindow<R>) -> ProtocolContext<'_, R> {
+    ProtocolContext {
+        app_handle: webview.app_handle(),
+        webview_label: webview.label(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tauri::test::{mock_builder, mock_context, noop_assets};
+
+    fn echo_path<R: Runtime>(
+        ctx: ProtocolContext<'_, R>,
+        request: Request<Vec<u8>>,
+    ) -> Response<Vec<u8>> {
+        assert_eq!(ctx.webview_label(), "main");
+        Response::new(request.uri().path().as_bytes().to_vec())
+    }
+
+    #[test]
+    fn fuzzed_requests_are_valid() {
+        let inputs: [&[u8]; 4] = [
+            &[],
+            &[0xff; 64],
+            b"\x00\x05../..\x03a=b\x01\x06\x02\xff\x00body",
+            &[0x42; 1000],
+        ];
+        for bytes in inputs {
+            assert!(protocol_request("asset", &mut RandomBytes::new(bytes)).is_ok());
+        }
+
+        let bytes = b"\x00\x05../..\x03a=b\x01\x00\x08bytes=0-\x00body";
+        let request = protocol_request("asset", &mut RandomBytes::new(bytes)).unwrap();
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.uri().path(), "/../..");
+        assert_eq!(request.uri().query(), Some("a=b"));
+        assert_eq!(request.headers()["range"], "bytes=0-");
+        assert_eq!(request.body(), b"body");
+    }
+
+    #[test]
+    fn handlers_receive_requests() {
+        let _lock = crate::Invocation::lock_for_test();
+        let app = mock_builder().build(mock_context(noop_assets())).unwrap();
+        let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+        let request = protocol_request("asset", &mut RandomBytes::new(b"\x00\x04/foo")).unwrap();
+        assert_eq!(
+            handle_protocol_request(&webview, "asset", echo_path, request),
+            ExitKind::Ok
+        );
+
+        let request = protocol_request("asset", &mut RandomBytes::new(b"\x00\x04/foo")).unwrap();
+        let async_handler = |ctx: ProtocolContext<'_, _>, request, responder: ProtocolResponder| {
+            let response = echo_path(ctx, request);
+            std::thread::spawn(move || responder.respond(response));
+        };
+        let exit_kind = handle_async_protocol_request(
+            &webview,
+            "asset",
+            async_handler,
+            request,
+            Duration::from_secs(1),
+        );
+        assert_eq!(exit_kind, ExitKind::Ok);
+
+        let request = protocol_request("asset", &mut RandomBytes::new(b"")).unwrap();
+        let exit_kind = handle_async_protocol_request(
+            &webview,
+            "asset",
+            |_, _, responder| std::mem::forget(responder),
+            request,
+            Duration::from_millis(10),
+        );
+        assert_eq!(exit_kind, ExitKind::Timeout);
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Invoke sequences of Tauri commands on a single application.
+//! The inputs are decoded with [`crate::split_sequence`].
+
+use std::time::Duration;
+
+use libafl::executors::ExitKind;
+use tauri::test::MockRuntime;
+use tauri::WebviewWindow;
+
+use super::{create_invoke_request, invoke_command_and_check, CommandArgs, FromRandomBytesError};
+use crate::ResponseAssertion;
+
+/// A Tauri command that can be invoked in a fuzzed sequence
+#[derive(Debug, Clone, Copy)]
+pub struct SequenceCommand {
+    /// Name of the Tauri command
+    pub name: &'static str,
+    /// Create the arguments of the command from the bytes of its call
+    pub create_args: fn(&[u8]) -> Result<CommandArgs, FromRandomBytesError>,
+}
+
+/// Invoke the calls of the sequence `bytes` one after the other on `webview`.
+/// All the commands run in the same application and share its state.
+/// Each command is waited for at most `timeout` before invoking the next one, see
+/// [`invoke_command_and_wait`](super::invoke_command_and_wait).
+/// Calls whose arguments can't be created from their bytes are skipped.
+pub fn invoke_sequence(
+    webview: &WebviewWindow<MockRuntime>,
+    commands: &[SequenceCommand],
+    bytes: &[u8],
+    timeout: Duration,
+) -> ExitKind {
+    invoke_sequence_and_check(webview, commands, bytes, timeout, &[])
+}
+
+/// Same as [`invoke_sequence`] but also checks the `assertions` on the response of each command,
+/// see [`invoke_command_and_check`].
+/// The sequence stops at the first response breaking one of them.
+pub fn invoke_sequence_and_check(
+    webview: &WebviewWindow<MockRuntime>,
+    commands: &[SequenceCommand],
+    bytes: &[u8],
+    timeout: Duration,
+    assertions: &[ResponseAssertion],
+) -> ExitKind {
+    assert!(
+        !commands.is_empty(),
+        "A sequence needs at least one command"
+    );
+    for call in crate::split_sequence(bytes) {
+        let command = &commands[call.command_index(commands.len())];
+        let Ok(args) = (command.create_args)(call.args) else {
+            continue;
+        };
+        let request = create_invoke_request(None, command.name, args);
+        let exit_kind = invoke_command_and_check(webview.clone(), request, timeout, assertions);
+        if exit_kind != ExitKind::Ok {
+            return exit_kind;
+        }
+    }
+    ExitKind::Ok
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tauri::{FromRandomBytes, RandomBytes};
+    use std::sync::Mutex;
+    use tauri::test::{mock_builder, mock_context, noop_assets};
+    use tauri::Manager;
+
+    #[derive(Default)]
+    struct Session {
+        user: Mutex<Option<String>>,
+        exports: Mutex<Vec<String>>,
+    }
+
+    #[tauri::command]
+    fn login(session: tauri::State<'_, Session>, name: String) {
+        *session.user.lock().unwrap() = Some(name);
+    }
+
+    #[tauri::command]
+    fn export(session: tauri::State<'_, Session>) -> Result<(), String> {
+        let user = session
+            .user
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("Not logged in")?;
+        session.exports.lock().unwrap().push(user);
+        Ok(())
+    }
+
+    fn login_args(bytes: &[u8]) -> Result<CommandArgs, FromRandomBytesError> {
+        let mut args = CommandArgs::new();
+        let name = String::from_random_input(&mut RandomBytes::new(bytes))
+            .map_err(FromRandomBytesError::new)?;
+        args.insert("name", name);
+        Ok(args)
+    }
+
+    #[test]
+    fn sequence_shares_app_state() {
+        let _lock = crate::Invocation::lock_for_test();
+        let app = mock_builder()
+            .manage(Session::default())
+            .invoke_handler(tauri::generate_handler![login, export])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+        let commands = [
+            SequenceCommand {
+                name: "login",
+                create_args: login_args,
+            },
+            SequenceCommand {
+                name: "export",
+                create_args: |_| Ok(CommandArgs::new()),
+            },
+        ];
+
+        // Exporting before login fails, exporting after login uses the logged in user
+        let sequence =
+            crate::encode_sequence([(1, &[][..]), (0, &[3, b'b', b'o', b'b'][..]), (1, &[][..])]);
+        let exit_kind = invoke_sequence(&webview, &commands, &sequence, Duration::from_secs(5));
+        assert_eq!(exit_kind, ExitKind::Ok);
+
+        let session = app.state::<Session>();
+        assert_eq!(*session.exports.lock().unwrap(), vec![String::from("bob")]);
+    }
+}
@@ -2,18 +2,24 @@
 // SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
 
 /// Collection of helper functions that connects the fuzzer and Tauri
+use libafl::executors::ExitKind;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::str::FromStr;
-use tauri::ipc::{CallbackFn, InvokeBody};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+use tauri::ipc::{CallbackFn, InvokeBody, InvokeError, InvokeResponse, InvokeResponseBody};
 use tauri::test::{MockRuntime, INVOKE_KEY};
 use tauri::webview::InvokeRequest;
 use tauri::Builder;
 use tauri::WebviewWindow;
+use tauri_fuzz_policies::engine::CommandScope;
 use tauri_utils::acl::capability::CapabilityFile::{self, Capability};
 
+use crate::ResponseAssertion;
+
 /// Minimal builder for a Tauri application using the `MockRuntime`
 /// NOTE: if your Tauri command uses a state this won't work since it does manage any state
 pub fn mock_builder_minimal() -> Builder<MockRuntime> {
@@ -35,19 +41,115 @@ pub fn invoke_command<T: DeserializeOwned + Debug, E: DeserializeOwned + Debug>(
     })
 }
 
-/// Invoke a command but does not try to get the command return value
+/// Invoke a command but does not try to get the command return value.
+/// The response is only recorded so that it can be reported when replaying inputs.
+/// This returns before async commands are finished, see [`invoke_command_and_wait`].
+/// The rules specific to the command apply until it responds or the next command is invoked.
 pub fn invoke_command_minimal(webview: WebviewWindow<MockRuntime>, request: InvokeRequest) {
+    record_invocation(&request);
+    let scope = CommandScope::enter(command_name(&request.cmd));
     webview.on_message(
         request,
-        Box::new(move |_window, _cmd, _response, _callback, _error| ()),
+        Box::new(move |_window, _cmd, response, _callback, _error| {
+            drop(scope);
+            crate::Invocation::record_response(response_to_json(response));
+        }),
     )
 }
 
+/// Invoke a command and wait for its response for at most `timeout`.
+/// Async commands run on the Tauri async runtime after the request was sent. Waiting makes sure
+/// that they are fully executed while the harness, and so the fuzzed code, is still running.
+/// The task of a command that does not respond in time can't be cancelled, the execution is
+/// stopped instead: the executor reports it as a timeout and restarts the fuzzer without the
+/// task.
+pub fn invoke_command_and_wait(
+    webview: WebviewWindow<MockRuntime>,
+    request: InvokeRequest,
+    timeout: Duration,
+) -> ExitKind {
+    invoke_command_and_check(webview, request, timeout, std::iter::empty())
+}
+
+/// Same as [`invoke_command_and_wait`] but also checks the `assertions` on the response.
+/// Returns [`ExitKind::Crash`] if one of them fails so that the input is reported as a
+/// solution with the failed assertion.
+///
+/// ```rust,ignore
+/// let assertions = [
+///     ResponseAssertion::never_contains("BEGIN PRIVATE KEY"),
+///     ResponseAssertion::error_matches("error is a string", serde_json::Value::is_string),
+/// ];
+/// invoke_command_and_check(webview, request, timeout, &assertions)
+/// ```
+pub fn invoke_command_and_check<'a>(
+    webview: WebviewWindow<MockRuntime>,
+    request: InvokeRequest,
+    timeout: Duration,
+    assertions: impl IntoIterator<Item = &'a ResponseAssertion>,
+) -> ExitKind {
+    let (tx, rx) = std::sync::mpsc::sync_channel(1);
+    record_invocation(&request);
+    // The rules specific to the command apply until it responds
+    let scope = CommandScope::enter(command_name(&request.cmd));
+    webview.on_message(
+        request,
+        Box::new(move |_window, _cmd, response, _callback, _error| {
+            let _ = tx.send(response_to_json(response));
+        }),
+    );
+    let response = rx.recv_timeout(timeout);
+    drop(scope);
+    match response {
+        Ok(response) => {
+            let holds = assertions
+                .into_iter()
+                .all(|assertion| assertion.check(&response));
+            crate::Invocation::record_response(response);
+            if holds {
+                ExitKind::Ok
+            } else {
+                ExitKind::Crash
+            }
+        }
+        Err(RecvTimeoutError::Timeout) => crate::report::stop_timed_out_execution(),
+        // The command was dropped without responding
+        Err(RecvTimeoutError::Disconnected) => ExitKind::Ok,
+    }
+}
+
+/// Name of the command invoked by `cmd` as used in the policies, without the plugin prefix
+fn command_name(cmd: &str) -> &str {
+    cmd.strip_prefix("plugin:")
+        .and_then(|cmd| cmd.split_once('|'))
+        .map_or(cmd, |(_plugin, command)| command)
+}
+
+/// Record the invocation sent by the harness so that it appears in the solution reports
+fn record_invocation(request: &InvokeRequest) {
+    let args = match &request.body {
+        InvokeBody::Json(json) => json.clone(),
+        InvokeBody::Raw(bytes) => serde_json::Value::from(bytes.clone()),
+    };
+    crate::Invocation::record(request.cmd.clone(), args);
+}
+
+/// Convert the response of a command into JSON
+fn response_to_json(response: InvokeResponse) -> Result<serde_json::Value, serde_json::Value> {
+    match response {
+        InvokeResponse::Ok(InvokeResponseBody::Json(json)) => {
+            Ok(serde_json::from_str(&json).unwrap_or(serde_json::Value::String(json)))
+        }
+        InvokeResponse::Ok(InvokeResponseBody::Raw(bytes)) => Ok(serde_json::Value::from(bytes)),
+        InvokeResponse::Err(InvokeError(error)) => Err(error),
+    }
+}
+
 /// Url used by tauri commands
 #[cfg(not(any(windows, target_os = "android")))]
-const TAURI_PROTOCOL_URL: &str = "tauri://localhost";
+pub(crate) const TAURI_PROTOCOL_URL: &str = "tauri://localhost";
 #[cfg(any(windows, target_os = "android"))]
-const TAURI_PROTOCOL_URL: &str = "http://tauri.localhost";
+pub(crate) const TAURI_PROTOCOL_URL: &str = "http://tauri.localhost";
 
 /// Helper function to create a Tauri `InvokeRequest`.
 ///
@@ -62,11 +164,24 @@ pub fn create_invoke_request(
     cmd_name: &str,
     command_args: CommandArgs,
 ) -> InvokeRequest {
-    let mut json_command_args = serde_json::map::Map::new();
-    for (k, v) in command_args.inner {
-        json_command_args.insert(k, v);
-    }
+    create_invoke_request_with_body(tauri_plugin, cmd_name, command_args.into())
+}
 
+/// Helper function to create a Tauri `InvokeRequest` with an arbitrary JSON body.
+/// Contrary to [`create_invoke_request`] the body may not be an object of named arguments,
+/// this is used to send malformed bodies to the command.
+///
+/// # Arguments
+///
+/// * `tauri_module` the module the invoked command is part of. Use `None` for a custom Tauri command
+/// * `cmd_name` name of the Tauri command invoked
+/// * `body` JSON body of the invocation
+///
+pub fn create_invoke_request_with_body(
+    tauri_plugin: Option<String>,
+    cmd_name: &str,
+    body: serde_json::Value,
+) -> InvokeRequest {
     match tauri_plugin {
         // The Tauri command invoked is a custom command
 
@@ -108,7 +223,7 @@ pub fn create_invoke_request(
             callback: CallbackFn(0),
             error: CallbackFn(1),
             url: TAURI_PROTOCOL_URL.parse().unwrap(),
-            body: InvokeBody::from(serde_json::value::Value::Object(json_command_args)),
+            body: InvokeBody::from(body),
             headers: Default::default(),
             invoke_key: INVOKE_KEY.to_string(),
         },
@@ -161,7 +276,7 @@ pub fn create_invoke_request(
                 callback: CallbackFn(0),
                 error: CallbackFn(1),
                 url: TAURI_PROTOCOL_URL.parse().unwrap(),
-                body: InvokeBody::from(serde_json::value::Value::Object(json_command_args)),
+                body: InvokeBody::from(body),
                 headers: Default::default(),
                 invoke_key: INVOKE_KEY.to_string(),
             }
@@ -177,13 +292,25 @@ pub fn setup_context_with_plugin<R: tauri::Runtime>(
     permission_file_toml: &str,
     capability: &str,
 ) {
-    let runtime_authority = context.runtime_authority_mut();
+    let (acl, capabilities) = plugin_acl(plugin_name, permission_file_toml, capability);
+    // Setup our custom `RuntimeAuthority` in our application context
+    super::acl::set_runtime_authority(context, acl, capabilities);
+}
 
+/// Permissions of a plugin and capability of the app, see [`setup_context_with_plugin`]
+pub(crate) fn plugin_acl(
+    plugin_name: &str,
+    permission_file_toml: &str,
+    capability: &str,
+) -> (
+    BTreeMap<String, tauri_utils::acl::manifest::Manifest>,
+    BTreeMap<String, tauri_utils::acl::capability::Capability>,
+) {
     // The acl of our application contains the `read-files` permission from the fs plugin
     let permission_file: tauri_utils::acl::manifest::PermissionFile =
         toml::from_str(permission_file_toml).unwrap();
     let manifest = tauri_utils::acl::manifest::Manifest::new(vec![permission_file], None);
-    let mut acl = std::collections::BTreeMap::new();
+    let mut acl = BTreeMap::new();
     acl.insert(plugin_name.to_string(), manifest);
 
     // Capability of our mock app declare the use of the `fs:read-files` permission
@@ -191,19 +318,9 @@ pub fn setup_context_with_plugin<R: tauri::Runtime>(
     let Capability(capability) = capability_file else {
         unreachable!()
     };
-    let mut capability_map = std::collections::BTreeMap::new();
+    let mut capability_map = BTreeMap::new();
     capability_map.insert(capability.identifier.clone(), capability.clone());
-
-    // Resolved capabilities against the acl
-    let resolved = tauri_utils::acl::resolved::Resolved::resolve(
-        &acl,
-        capability_map,
-        tauri_utils::platform::Target::current(),
-    )
-    .unwrap();
-
-    // Setup our custom `RuntimeAuthority` in our application context
-    *runtime_authority = tauri::ipc::RuntimeAuthority::new(acl, resolved);
+    (acl, capability_map)
 }
 
 /// A wrapper around `HashMap` to facilitate `InvokePayload` creation.
@@ -236,6 +353,12 @@ impl CommandArgs {
     }
 }
 
+impl From<CommandArgs> for serde_json::Value {
+    fn from(command_args: CommandArgs) -> Self {
+        serde_json::Value::Object(command_args.inner.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -328,8 +451,104 @@ commands.allow = [
         assert_eq!(stripped_res, "foo");
     }
 
+    #[tauri::command]
+    async fn async_test_command(fail: bool) -> Result<String, String> {
+        if fail {
+            Err(String::from("failure"))
+        } else {
+            Ok(String::from("foo"))
+        }
+    }
+
+    #[test]
+    fn test_invoke_and_wait_async() {
+        let _lock = crate::Invocation::lock_for_test();
+        let app = mock_builder_minimal()
+            .invoke_handler(tauri::generate_handler![async_test_command])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+
+        for (fail, expected) in [
+            (false, Ok(serde_json::json!("foo"))),
+            (true, Err(serde_json::json!("failure"))),
+        ] {
+            let mut args = CommandArgs::new();
+            args.insert("fail", fail);
+            let request = create_invoke_request(None, "async_test_command", args);
+            let exit_kind =
+                invoke_command_and_wait(webview.clone(), request, Duration::from_secs(5));
+            assert_eq!(exit_kind, ExitKind::Ok);
+            let invocations = crate::Invocation::take_all();
+            assert_eq!(invocations.len(), 1);
+            assert_eq!(invocations[0].command, "async_test_command");
+            assert_eq!(invocations[0].response, Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_invoke_and_check() {
+        let _lock = crate::Invocation::lock_for_test();
+        let app = mock_builder_minimal()
+            .invoke_handler(tauri::generate_handler![async_test_command])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+        let assertions = [ResponseAssertion::never_contains("failure")];
+
+        for (fail, expected) in [(false, ExitKind::Ok), (true, ExitKind::Crash)] {
+            let mut args = CommandArgs::new();
+            args.insert("fail", fail);
+            let request = create_invoke_request(None, "async_test_command", args);
+            let exit_kind = invoke_command_and_check(
+                webview.clone(),
+                request,
+                Duration::from_secs(5),
+                &assertions,
+            );
+            assert_eq!(exit_kind, expected);
+            let failed = crate::FailedResponseAssertion::take();
+            assert_eq!(failed.is_some(), fail);
+        }
+    }
+
+    #[tauri::command]
+    async fn scoped_command() -> Option<String> {
+        tauri_fuzz_policies::engine::current_command().map(String::from)
+    }
+
+    #[test]
+    fn test_command_scope() {
+        assert_eq!(command_name("plugin:fs|read_file"), "read_file");
+        assert_eq!(command_name("read_file"), "read_file");
+
+        let _lock = crate::Invocation::lock_for_test();
+        let app = mock_builder_minimal()
+            .invoke_handler(tauri::generate_handler![scoped_command])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap();
+        let request = create_invoke_request(None, "scoped_command", CommandArgs::new());
+        let exit_kind = invoke_command_and_wait(webview, request, Duration::from_secs(5));
+        assert_eq!(exit_kind, ExitKind::Ok);
+        let invocations = crate::Invocation::take_all();
+        assert_eq!(
+            invocations[0].response,
+            Some(Ok(serde_json::json!("scoped_command")))
+        );
+        // The scope ends with the command
+        assert_eq!(tauri_fuzz_policies::engine::current_command(), None);
+    }
+
     #[test]
     fn test_invoke_minimal() {
+        let _lock = crate::Invocation::lock_for_test();
         let app = mock_builder_minimal()
             .invoke_handler(tauri::generate_handler![test_command])
             .build(mock_context(noop_assets()))
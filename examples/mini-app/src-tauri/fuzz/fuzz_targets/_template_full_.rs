@@ -16,6 +16,7 @@ use tauri::WebviewWindow;
 /// 5. Finally add the new fuzz target in [[bin]] table in Cargo.toml of your project
 ///
 /// Note: you may need to implement [FromRandomBytes] for your command argument types.
+/// Structs and enums can use `#[derive(tauri_fuzz::tauri::FromRandomBytes)]`.
 ///
 use tauri_fuzz::tauri_utils::{create_invoke_request, invoke_command_minimal, CommandArgs};
 use tauri_fuzz::{FromRandomBytes, SimpleFuzzerConfig};
@@ -0,0 +1,25 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+// Fuzz target of the `read_file` command from the fs plugin.
+// The command is invoked with the capabilities of the mini-app in `capabilities/`.
+//
+// Note: the permissions of the plugins are generated in `gen/schemas` when building the app,
+// build the mini-app before fuzzing.
+
+tauri_fuzz::fuzz_tauri_plugin_command! {
+    // Name of the plugin and of the command you want to fuzz
+    plugin: "fs",
+    command: "read_file",
+    // Initialization of the plugin
+    init: tauri_plugin_fs::init(),
+    // Directory of the Tauri app relative to the fuzz directory
+    tauri_dir: "..",
+    // Parameters names and types to the plugin command
+    parameters: {
+        path: String,
+    },
+    // Policy chosen for the fuzzing
+    // Any file reached through the capabilities of the app breaks the policy
+    policy: tauri_fuzz_policies::filesystem::no_file_access(),
+}
@@ -9,6 +9,7 @@
 // 3. Add the new fuzz target in [[bin]] table in Cargo.toml of your project
 //
 // Note: you may need to implement [FromRandomBytes] for your command argument types.
+// Structs and enums can use `#[derive(tauri_fuzz::tauri::FromRandomBytes)]`.
 
 tauri_fuzz::fuzz_tauri_command! {
     // Name of the tauri command you want to fuzz
@@ -3,26 +3,30 @@
 
 use libafl::inputs::{BytesInput, HasMutatorBytes};
 use libafl::prelude::ExitKind;
-use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use std::cell::RefCell;
+use std::time::Duration;
+use tauri::test::mock_builder;
 use tauri::webview::InvokeRequest;
-use tauri::WebviewWindow;
 /// This is a template to create a fuzz target
 ///
 /// Steps:
 /// 1. Copy this file and rename it
-/// 2. Change `COMMAND_NAME` const value on line 23
-/// 3. Change the path to your command in `tauri::generate_handler` on line 41
-/// 4. Modify `create_request` to create arguments for your command on line 59
+/// 2. Change `COMMAND_NAME` const value on line 27
+/// 3. Change the path to your command in `tauri::generate_handler` on line 50
+/// 4. Modify `create_request` to create arguments for your command on line 65
 /// 5. Finally add the new fuzz target in [[bin]] table in Cargo.toml of your project
 ///
 /// Note: you may need to implement [FromRandomBytes] for your command argument types.
+/// Structs and enums can use `#[derive(tauri_fuzz::tauri::FromRandomBytes)]`.
 ///
 use tauri_fuzz::tauri::{
-    create_invoke_request, invoke_command_minimal, CommandArgs, FromRandomBytes,
+    create_invoke_request, invoke_command_and_wait, CommandArgs, FromRandomBytes, MockAppHarness,
 };
 use tauri_fuzz::SimpleFuzzerConfig;
 
 const COMMAND_NAME: &str = "read_foo_file";
+// Maximum duration of a command, keep it below `timeout_ms` in `fuzzer_config.toml`
+const TIMEOUT: Duration = Duration::from_millis(5000);
 
 fn main() {
     let fuzz_dir = std::path::PathBuf::from(std::env!("CARGO_MANIFEST_DIR"));
@@ -37,26 +41,24 @@ fn main() {
     );
 }
 
-// Setup the Tauri application mockruntime and an associated "main" webview
-fn setup_mock() -> WebviewWindow<MockRuntime> {
-    let app = mock_builder()
-        .invoke_handler(tauri::generate_handler![
-            mini_app::file_access::read_foo_file
-        ])
-        .build(mock_context(noop_assets()))
-        .expect("Failed to init Tauri app");
-    let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
-        .build()
-        .unwrap();
-    webview
+thread_local! {
+    // Setup the Tauri application mockruntime once and reuse it for every input.
+    // Use `reset` on the builder to restore the state managed by the app between inputs.
+    static MOCK_APP: RefCell<MockAppHarness> = RefCell::new(
+        MockAppHarness::builder(|| {
+            mock_builder()
+                .invoke_handler(tauri::generate_handler![mini_app::file_access::read_foo_file])
+        })
+        .build(),
+    );
 }
 
 // Harness function that will be repeated extensively by the fuzzer with semi-random bytes
 // inputs
 fn harness(input: &BytesInput) -> ExitKind {
-    let webview = setup_mock();
-    let _ = invoke_command_minimal(webview, create_request(input.bytes()));
-    ExitKind::Ok
+    let webview = MOCK_APP.with_borrow_mut(MockAppHarness::start_execution);
+    // Wait for the response so that async commands are monitored until they finish
+    invoke_command_and_wait(webview, create_request(input.bytes()), TIMEOUT)
 }
 
 // Helper code to create an `InvokeRequest` to send to the Tauri app backend
@@ -20,6 +20,7 @@ pub use tauri_commands::demo;
 pub use tauri_commands::external_process;
 pub use tauri_commands::file_access;
 pub use tauri_commands::libc_calls;
+pub use tauri_commands::network;
 pub use tauri_commands::sql;
 use tracing::info;
 use tracing_subscriber::fmt;
@@ -7,4 +7,5 @@ pub mod demo;
 pub mod external_process;
 pub mod file_access;
 pub mod libc_calls;
+pub mod network;
 pub mod sql;
@@ -4,6 +4,7 @@
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 #[tauri::command]
 /// Write to the file `assets/foo.txt`
@@ -26,6 +27,26 @@ pub fn read_foo_file() -> String {
     content
 }
 
+#[tauri::command]
+/// Read the file `assets/foo.txt` from another thread
+pub fn read_foo_file_in_thread() -> String {
+    tracing::info!("[read_foo_file_in_thread] Entering");
+    std::thread::spawn(read_foo_file)
+        .join()
+        .expect("Failed to read foo file")
+}
+
+#[tauri::command]
+/// Read the file `assets/foo.txt` on the blocking thread pool of the Tauri async runtime every
+/// third call. The thread reading it was started by a previous call and is reused
+pub fn read_foo_file_in_pool() -> Option<String> {
+    static NB_CALLS: AtomicU32 = AtomicU32::new(0);
+    tracing::info!("[read_foo_file_in_pool] Entering");
+    let read = NB_CALLS.fetch_add(1, Ordering::SeqCst) % 3 == 2;
+    let task = tauri::async_runtime::spawn_blocking(move || read.then(read_foo_file));
+    tauri::async_runtime::block_on(task).expect("Failed to read foo file")
+}
+
 /// Get the path to `assets/foo.txt`
 fn get_foo_path() -> PathBuf {
     let mut file_path = std::path::PathBuf::from(std::env!("CARGO_MANIFEST_DIR"));
@@ -33,3 +54,47 @@ fn get_foo_path() -> PathBuf {
     file_path.push("foo.txt");
     file_path
 }
+
+#[tauri::command]
+/// Create a temporary `bar.txt` file and delete it
+pub fn remove_bar_file() {
+    tracing::info!("[remove_bar_file] Entering");
+    let path = get_bar_path("remove");
+    File::create(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tauri::command]
+/// Create a temporary `bar.txt` file, rename it to `bar.txt.bak` and delete it
+pub fn rename_bar_file() {
+    tracing::info!("[rename_bar_file] Entering");
+    let path = get_bar_path("rename");
+    let renamed_path = path.with_extension("txt.bak");
+    File::create(&path).unwrap();
+    std::fs::rename(&path, &renamed_path).unwrap();
+    std::fs::remove_file(renamed_path).unwrap();
+}
+
+#[tauri::command]
+/// Delete a `bar.txt` file without opening or creating it first.
+/// The file does not exist, the error is ignored
+pub fn remove_missing_bar_file() {
+    tracing::info!("[remove_missing_bar_file] Entering");
+    let _ = std::fs::remove_file(get_bar_path("remove_missing"));
+}
+
+#[tauri::command]
+/// Rename a `bar.txt` file without opening or creating it first.
+/// The file does not exist, the error is ignored
+pub fn rename_missing_bar_file() {
+    tracing::info!("[rename_missing_bar_file] Entering");
+    let path = get_bar_path("rename_missing");
+    let _ = std::fs::rename(&path, path.with_extension("txt.bak"));
+}
+
+/// Get the path to the `bar.txt` file used by `command`.
+/// Each command of each process has its own file so that concurrent tests don't remove the
+/// file of one another.
+fn get_bar_path(command: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mini-app-{}-{command}-bar.txt", std::process::id()))
+}
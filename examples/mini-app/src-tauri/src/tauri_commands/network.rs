@@ -0,0 +1,25 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+//! Tauri commands accessing the network
+use std::io::Write;
+use std::net::{TcpStream, UdpSocket};
+
+#[tauri::command]
+/// Connect to the given address with TCP and send a message
+pub fn tcp_connect(address: &str) -> bool {
+    tracing::info!("[tcp_connect] Entering with address: {}", address);
+    match TcpStream::connect(address) {
+        Ok(mut stream) => stream.write_all(b"foo").is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[tauri::command]
+/// Send a message to the given address with UDP
+pub fn udp_send_to(address: &str) -> bool {
+    tracing::info!("[udp_send_to] Entering with address: {}", address);
+    UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.send_to(b"foo", address))
+        .is_ok()
+}
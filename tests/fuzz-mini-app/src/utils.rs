@@ -69,8 +69,16 @@ commands.allow = [
             mini_app::basic::tauri_cmd_2,
             mini_app::basic::direct_panic,
             mini_app::libc_calls::fopen,
+            mini_app::network::tcp_connect,
+            mini_app::network::udp_send_to,
             mini_app::file_access::read_foo_file,
+            mini_app::file_access::read_foo_file_in_thread,
+            mini_app::file_access::read_foo_file_in_pool,
             mini_app::file_access::write_foo_file,
+            mini_app::file_access::remove_bar_file,
+            mini_app::file_access::rename_bar_file,
+            mini_app::file_access::remove_missing_bar_file,
+            mini_app::file_access::rename_missing_bar_file,
             mini_app::sql::sql_transaction,
             mini_app::external_process::ls_with_rust_command_status,
             mini_app::external_process::ls_with_rust_command_output,
@@ -5,8 +5,17 @@ mod direct_panic_tests;
 mod fopen_tests;
 mod fs_readFile_tests;
 mod ls_with_rust_command_tests;
+#[cfg(not(target_env = "msvc"))]
+mod network_tests;
+mod policy_hooks_tests;
 mod read_foo_file_tests;
+#[cfg(not(target_env = "msvc"))]
+mod remove_bar_file_tests;
+#[cfg(not(target_env = "msvc"))]
+mod rename_bar_file_tests;
 mod sql_transaction_tests;
+#[cfg(not(target_env = "msvc"))]
+mod suggested_policy_tests;
 mod tauri_cmd_1_tests;
 mod tauri_cmd_2_tests;
 mod write_foo_file_tests;
@@ -0,0 +1,131 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+use fuzz_mini_app::utils::fuzz_command_with_arg;
+use std::net::{TcpListener, UdpSocket};
+use tauri_fuzz::tauri::{start_crashing_fuzz_process, start_non_crashing_fuzz_process};
+
+// This is a trick to test fuzzers with multi-threaded and get fuzzer output when crashing.
+// Frida-gum does not support multi-threads therefore we start fuzzing in different processes.
+// The "hidden_*"  test will be started in a separate process and the exit status will be captured
+// by the parent process/test.
+#[test]
+fn block_network_access() {
+    start_crashing_fuzz_process("hidden_block_tcp_connect_with_no_network_policy");
+    start_crashing_fuzz_process("hidden_block_udp_send_to_with_no_network_policy");
+    start_crashing_fuzz_process("hidden_block_tcp_connect_with_blocked_port");
+    start_crashing_fuzz_process("hidden_block_tcp_connect_to_non_allowed_host");
+}
+
+#[test]
+fn allow_network_access() {
+    start_non_crashing_fuzz_process("hidden_allow_tcp_connect_with_no_policy");
+    start_non_crashing_fuzz_process("hidden_allow_tcp_connect_to_allowed_host");
+    start_non_crashing_fuzz_process("hidden_allow_udp_send_to_allowed_host");
+}
+
+/// Start a TCP server on the loopback interface and return its address
+fn loopback_tcp_server() -> (TcpListener, String) {
+    let server = TcpListener::bind("127.0.0.1:0").expect("Failed to start TCP server");
+    let address = server.local_addr().unwrap().to_string();
+    (server, address)
+}
+
+/// Start a UDP server on the loopback interface and return its address
+fn loopback_udp_server() -> (UdpSocket, String) {
+    let server = UdpSocket::bind("127.0.0.1:0").expect("Failed to start UDP server");
+    let address = server.local_addr().unwrap().to_string();
+    (server, address)
+}
+
+#[test]
+#[ignore]
+fn hidden_block_tcp_connect_with_no_network_policy() {
+    let (_server, address) = loopback_tcp_server();
+    fuzz_command_with_arg(
+        "tcp_connect",
+        Some(mini_app::network::tcp_connect as usize),
+        tauri_fuzz_policies::network::no_network_access(),
+        vec![("address", address)],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_udp_send_to_with_no_network_policy() {
+    let (_server, address) = loopback_udp_server();
+    fuzz_command_with_arg(
+        "udp_send_to",
+        Some(mini_app::network::udp_send_to as usize),
+        tauri_fuzz_policies::network::no_network_access(),
+        vec![("address", address)],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_tcp_connect_with_blocked_port() {
+    let (server, address) = loopback_tcp_server();
+    let port = server.local_addr().unwrap().port();
+    fuzz_command_with_arg(
+        "tcp_connect",
+        Some(mini_app::network::tcp_connect as usize),
+        tauri_fuzz_policies::network::block_ports(vec![port]),
+        vec![("address", address)],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_tcp_connect_to_non_allowed_host() {
+    let (_server, address) = loopback_tcp_server();
+    fuzz_command_with_arg(
+        "tcp_connect",
+        Some(mini_app::network::tcp_connect as usize),
+        tauri_fuzz_policies::network::allow_only_hosts(vec!["10.0.0.1".to_string()]).unwrap(),
+        vec![("address", address)],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_tcp_connect_with_no_policy() {
+    let (_server, address) = loopback_tcp_server();
+    fuzz_command_with_arg(
+        "tcp_connect",
+        Some(mini_app::network::tcp_connect as usize),
+        tauri_fuzz_policies::no_policy(),
+        vec![("address", address)],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_tcp_connect_to_allowed_host() {
+    let (_server, address) = loopback_tcp_server();
+    fuzz_command_with_arg(
+        "tcp_connect",
+        Some(mini_app::network::tcp_connect as usize),
+        tauri_fuzz_policies::network::allow_only_hosts(vec!["127.0.0.1".to_string()]).unwrap(),
+        vec![("address", address)],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_udp_send_to_allowed_host() {
+    let (_server, address) = loopback_udp_server();
+    fuzz_command_with_arg(
+        "udp_send_to",
+        Some(mini_app::network::udp_send_to as usize),
+        tauri_fuzz_policies::network::allow_only_hosts(vec!["127.0.0.1".to_string()]).unwrap(),
+        vec![("address", address)],
+        None,
+    )
+}
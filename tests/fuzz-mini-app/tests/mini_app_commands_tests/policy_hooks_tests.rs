@@ -0,0 +1,45 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+use tauri_fuzz::resolve_hooks;
+use tauri_fuzz::tauri::start_non_crashing_fuzz_process;
+
+// Frida is used to resolve the hooks, it is run in a separate process like the fuzzers.
+// A failed assertion makes the process exit with an error.
+#[test]
+fn mini_app_policy_hooks() {
+    start_non_crashing_fuzz_process("hidden_resolve_read_foo_policy_hooks");
+    #[cfg(not(target_env = "msvc"))]
+    start_non_crashing_fuzz_process("hidden_resolve_rust_command_policy_hooks");
+}
+
+// Policy of the `fuzz_read_foo` fuzz target of mini-app
+#[test]
+#[ignore]
+fn hidden_resolve_read_foo_policy_hooks() {
+    let policy = tauri_fuzz_policies::filesystem::no_file_access();
+    let report = resolve_hooks(&policy);
+    assert!(report.failed_required().next().is_none(), "{report}");
+    for function in &policy {
+        assert!(
+            function.is_optional || report.is_attached(&function.name),
+            "{report}"
+        );
+    }
+    for hook in report.attached() {
+        assert!(hook.module.is_some() && hook.address.is_some(), "{report}");
+    }
+}
+
+// The Rust functions of the policy are in the binary that links mini-app
+#[cfg(not(target_env = "msvc"))]
+#[test]
+#[ignore]
+fn hidden_resolve_rust_command_policy_hooks() {
+    let policy = tauri_fuzz_policies::external_process::block_on_entry(vec!["ls".into()]);
+    let report = resolve_hooks(&policy);
+    assert!(report.all_attached(), "{report}");
+    for function in policy.iter().map(|function| &function.name) {
+        assert!(report.is_attached(function), "{report}");
+    }
+}
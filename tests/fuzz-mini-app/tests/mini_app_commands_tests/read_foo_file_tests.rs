@@ -14,6 +14,19 @@ fn block_read_foo() {
     start_crashing_fuzz_process("hidden_block_read_foo_with_filename_policy");
 }
 
+// The file is read by a thread started by the command
+#[test]
+fn block_read_foo_in_thread() {
+    start_crashing_fuzz_process("hidden_block_read_foo_in_thread_with_nofileaccess_policy");
+}
+
+// The file is read by a pool thread started by a previous execution, the execution reading it
+// is stopped
+#[test]
+fn block_read_foo_in_pool() {
+    start_crashing_fuzz_process("hidden_block_read_foo_in_pool_with_nofileaccess_policy");
+}
+
 // Read-only access should not block `read_foo`
 #[test]
 fn allow_read_foo() {
@@ -33,6 +46,30 @@ fn hidden_block_read_foo_with_nofileaccess_policy() {
     )
 }
 
+#[test]
+#[ignore]
+fn hidden_block_read_foo_in_thread_with_nofileaccess_policy() {
+    fuzz_command_with_arg(
+        "read_foo_file_in_thread",
+        Some(mini_app::file_access::read_foo_file_in_thread as usize),
+        tauri_fuzz_policies::filesystem::no_file_access(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_read_foo_in_pool_with_nofileaccess_policy() {
+    fuzz_command_with_arg(
+        "read_foo_file_in_pool",
+        Some(mini_app::file_access::read_foo_file_in_pool as usize),
+        tauri_fuzz_policies::filesystem::no_file_access(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
 #[test]
 #[ignore]
 fn hidden_block_read_foo_with_filename_policy() {
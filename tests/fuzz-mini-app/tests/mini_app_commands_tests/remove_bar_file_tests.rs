@@ -0,0 +1,88 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+use fuzz_mini_app::utils::fuzz_command_with_arg;
+use tauri_fuzz::tauri::{start_crashing_fuzz_process, start_non_crashing_fuzz_process};
+
+// This is a trick to test fuzzers with multi-threaded and get fuzzer output when crashing.
+// Frida-gum does not support multi-threads therefore we start fuzzing in different processes.
+// The "hidden_*"  test will be started in a separate process and the exit status will be captured
+// by the parent process/test.
+#[test]
+fn block_remove_bar() {
+    start_crashing_fuzz_process("hidden_block_remove_bar_with_nodeletion_policy");
+    start_crashing_fuzz_process("hidden_block_remove_bar_with_nofileaccess_policy");
+}
+
+// Deleting a file modifies the filesystem even if the file is not opened
+#[test]
+fn block_remove_bar_with_readonly_policy() {
+    start_non_crashing_fuzz_process("hidden_allow_remove_missing_bar_with_noread_policy");
+    start_crashing_fuzz_process("hidden_block_remove_missing_bar_with_readonly_policy");
+}
+
+// Deleting files is not renaming them
+#[test]
+fn allow_remove_bar() {
+    start_non_crashing_fuzz_process("hidden_allow_remove_bar_with_norename_policy");
+}
+
+#[test]
+#[ignore]
+fn hidden_block_remove_bar_with_nodeletion_policy() {
+    fuzz_command_with_arg(
+        "remove_bar_file",
+        Some(mini_app::file_access::remove_bar_file as usize),
+        tauri_fuzz_policies::filesystem::no_file_deletion(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_remove_bar_with_nofileaccess_policy() {
+    fuzz_command_with_arg(
+        "remove_bar_file",
+        Some(mini_app::file_access::remove_bar_file as usize),
+        tauri_fuzz_policies::filesystem::no_file_access(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_remove_missing_bar_with_noread_policy() {
+    fuzz_command_with_arg(
+        "remove_missing_bar_file",
+        Some(mini_app::file_access::remove_missing_bar_file as usize),
+        tauri_fuzz_policies::filesystem::no_file_read(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_remove_missing_bar_with_readonly_policy() {
+    fuzz_command_with_arg(
+        "remove_missing_bar_file",
+        Some(mini_app::file_access::remove_missing_bar_file as usize),
+        tauri_fuzz_policies::filesystem::read_only_access(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_remove_bar_with_norename_policy() {
+    fuzz_command_with_arg(
+        "remove_bar_file",
+        Some(mini_app::file_access::remove_bar_file as usize),
+        tauri_fuzz_policies::filesystem::no_file_rename(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
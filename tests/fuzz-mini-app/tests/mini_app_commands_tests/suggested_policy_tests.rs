@@ -0,0 +1,95 @@
+// Copyright 2023-2024 CrabNebula Ltd., Alexandre Dang
+// SPDX-License-Identifier: PolyForm-Noncommercial-1.0.0
+
+use fuzz_mini_app::utils::{fuzz_command_with_arg, fuzz_dir};
+use std::path::{Path, PathBuf};
+use tauri_fuzz::tauri::{start_crashing_fuzz_process, start_non_crashing_fuzz_process};
+use tauri_fuzz::{BehaviorProfile, CommandProfile, AUDIT_ALL_HOOKS, AUDIT_ENV_VAR};
+use tauri_fuzz_policies::engine::{FuzzPolicy, PolicyBuilder};
+use tauri_fuzz_policies::filesystem;
+
+/// Directory where the fuzzer records the behavior profiles of `command` in audit mode
+fn profiles_dir(command: &str) -> PathBuf {
+    fuzz_dir()
+        .join("fuzz_solutions")
+        .join(format!("{command}_behavior_profiles"))
+}
+
+/// Policy allowing only the files accessed while auditing `read_foo_file`, built like the policy
+/// rendered by `cargo-tauri-fuzz suggest-policy`
+fn suggested_policy() -> FuzzPolicy {
+    let profile = BehaviorProfile::load(&profiles_dir("read_foo_file")).unwrap();
+    let files = |profile: &CommandProfile| profile.files.iter().cloned().collect::<Vec<_>>();
+    let all_files = profile.commands.values().flat_map(files).collect();
+    let read_foo_files = files(&profile.commands["read_foo_file"]);
+    PolicyBuilder::new()
+        .deny(
+            "filesystem",
+            filesystem::allow_only_paths(all_files).unwrap(),
+        )
+        .for_command(
+            "read_foo_file",
+            PolicyBuilder::new().deny(
+                "filesystem",
+                filesystem::allow_only_paths(read_foo_files).unwrap(),
+            ),
+        )
+        .build()
+}
+
+// The resources recorded in audit mode are allowed by the suggested policy, any other resource is
+// denied.
+// The steps run in order in separate processes since each fuzzer reads the output of the previous
+// one.
+#[test]
+fn suggested_policy_round_trip() {
+    let _ = std::fs::remove_dir_all(profiles_dir("read_foo_file"));
+    start_non_crashing_fuzz_process("hidden_audit_read_foo_with_nofileaccess_policy");
+
+    let profile = BehaviorProfile::load(&profiles_dir("read_foo_file")).unwrap();
+    assert!(profile.commands["read_foo_file"]
+        .files
+        .iter()
+        .any(|file| Path::new(file).ends_with("assets/foo.txt")));
+
+    start_non_crashing_fuzz_process("hidden_allow_read_foo_with_suggested_policy");
+    start_crashing_fuzz_process("hidden_block_rename_bar_with_suggested_policy");
+}
+
+// The policy does not block in audit mode
+#[test]
+#[ignore]
+fn hidden_audit_read_foo_with_nofileaccess_policy() {
+    std::env::set_var(AUDIT_ENV_VAR, AUDIT_ALL_HOOKS);
+    fuzz_command_with_arg(
+        "read_foo_file",
+        Some(mini_app::file_access::read_foo_file as usize),
+        filesystem::no_file_access(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_read_foo_with_suggested_policy() {
+    fuzz_command_with_arg(
+        "read_foo_file",
+        Some(mini_app::file_access::read_foo_file as usize),
+        suggested_policy(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_block_rename_bar_with_suggested_policy() {
+    fuzz_command_with_arg(
+        "rename_bar_file",
+        Some(mini_app::file_access::rename_bar_file as usize),
+        suggested_policy(),
+        Vec::<(&str, ())>::new(),
+        None,
+    )
+}
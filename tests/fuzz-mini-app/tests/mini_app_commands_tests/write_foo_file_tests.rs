@@ -25,6 +25,18 @@ fn block_by_readonly_policy() {
     start_crashing_fuzz_process("hidden_block_by_readonly_policy")
 }
 
+// Block writing to foo.txt since writes are only allowed in another directory
+#[test]
+fn block_by_path_policy() {
+    start_crashing_fuzz_process("hidden_block_by_path_policy")
+}
+
+// Writes are allowed in the mini-app assets directory
+#[test]
+fn allowed_by_path_policy() {
+    start_non_crashing_fuzz_process("hidden_allow_by_path_policy")
+}
+
 // writeonly policy and no policy should not crash
 #[test]
 fn allowed_by_writeonly_policy() {
@@ -68,6 +80,42 @@ fn hidden_block_by_readonly_policy() {
     )
 }
 
+/// Glob matching any file in the mini-app assets directory
+fn mini_app_assets_glob() -> String {
+    let mut assets_dir = std::path::PathBuf::from(std::env!("CARGO_MANIFEST_DIR"));
+    assets_dir.push("../../examples/mini-app/src-tauri/assets/**");
+    assets_dir.to_string_lossy().into_owned()
+}
+
+#[test]
+#[ignore]
+fn hidden_block_by_path_policy() {
+    fuzz_command_with_arg(
+        "write_foo_file",
+        Some(mini_app::file_access::write_foo_file as usize),
+        tauri_fuzz_policies::filesystem::allow_writes_only_to(vec![format!(
+            "{}/**",
+            std::env!("CARGO_MANIFEST_DIR")
+        )])
+        .unwrap(),
+        vec![("input", "foo")],
+        None,
+    )
+}
+
+#[test]
+#[ignore]
+fn hidden_allow_by_path_policy() {
+    fuzz_command_with_arg(
+        "write_foo_file",
+        Some(mini_app::file_access::write_foo_file as usize),
+        tauri_fuzz_policies::filesystem::allow_writes_only_to(vec![mini_app_assets_glob()])
+            .unwrap(),
+        vec![("input", "foo")],
+        None,
+    )
+}
+
 #[test]
 #[ignore]
 fn hidden_allow_by_writeonly_policy() {